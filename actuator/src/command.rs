@@ -0,0 +1,401 @@
+use std::fmt::{Display, Formatter};
+
+use datum::{DatumUnit, DatumValue, DatumValueType};
+
+/// A `Command` tells an `Actuator` to perform some action, like `HeatTo:21` or `Toggle`.
+///
+/// Every `Command` is serialized as its name, optionally followed by a single argument, as in
+/// `Name` or `Name:value`. This lets the `Controller` build, validate, and send commands to any
+/// type of `Actuator` as `GenericCommand`s, given only the `Schema` of its commands.
+pub trait Command: Display + Sized {
+    /// Describes every command of this type: their names, and the type, unit, and valid range of
+    /// their arguments.
+    fn schema() -> Schema;
+
+    /// Converts a `GenericCommand` into a command of this type.
+    fn from_generic(command: &GenericCommand) -> Result<Self, String>;
+
+    /// Parses a command serialized as `Name` or `Name:value`, checking it against the `schema`.
+    fn parse(string: &str) -> Result<Self, String> {
+        let command = GenericCommand::parse(string)?;
+        Self::schema().validate(&command)?;
+        Self::from_generic(&command)
+    }
+
+    /// Converts this command into a `GenericCommand`.
+    fn to_generic(&self) -> GenericCommand {
+        GenericCommand::parse(&self.to_string())
+            .expect("Commands should be displayed as Name or Name:value")
+    }
+}
+
+/// A `Command` for any type of `Actuator`, which is only known by its name and its argument.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GenericCommand {
+    pub name: String,
+    pub argument: Option<DatumValue>,
+}
+
+impl Display for GenericCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.argument {
+            None => write!(f, "{}", self.name),
+            Some(argument) => write!(f, "{}:{}", self.name, argument),
+        }
+    }
+}
+
+impl GenericCommand {
+    pub fn new(name: &str, argument: Option<DatumValue>) -> GenericCommand {
+        GenericCommand {
+            name: String::from(name),
+            argument,
+        }
+    }
+
+    pub fn parse(string: &str) -> Result<GenericCommand, String> {
+        let (name, argument) = match string.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (string, None),
+        };
+
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric());
+
+        let argument = argument
+            .map(|argument| DatumValue::parse(argument.to_string()))
+            .transpose();
+
+        match (valid, argument) {
+            (true, Ok(argument)) => Ok(GenericCommand::new(name, argument)),
+            _ => Err(format!("cannot parse {} as Command", string)),
+        }
+    }
+
+    /// Returns the argument of this command as an `f32`, if it is a number.
+    pub fn float(&self) -> Result<f32, String> {
+        match self.argument {
+            Some(DatumValue::Float(value)) => Ok(value),
+            Some(DatumValue::Int(value)) => Ok(value as f32),
+            _ => Err(format!("{} requires a number", self.name)),
+        }
+    }
+}
+
+/// Describes every command an `Actuator` accepts.
+///
+/// A `Schema` is serialized as its `Spec`s, separated by semicolons, e.g.
+/// `On;Off;DimTo:float@@0..100`.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Schema {
+    pub commands: Vec<Spec>,
+}
+
+/// Describes a single command: its name, and its argument (if it takes one).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Spec {
+    pub name: String,
+    pub parameter: Option<Parameter>,
+}
+
+/// Describes the argument of a command: its type, its unit, and (optionally) its valid range.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Parameter {
+    pub value_type: DatumValueType,
+    pub unit: DatumUnit,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl Display for Parameter {
+    /// Serializes the parameter as `type@unit`, followed by `@min..max` if it has a range.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.value_type, self.unit)?;
+
+        if self.min.is_some() || self.max.is_some() {
+            let bound = |bound: Option<f32>| bound.map(|b| b.to_string()).unwrap_or_default();
+            write!(f, "@{}..{}", bound(self.min), bound(self.max))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Parameter {
+    pub fn new(value_type: DatumValueType, unit: DatumUnit) -> Parameter {
+        Parameter {
+            value_type,
+            unit,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Restricts the argument to values from `min` to `max` (inclusive).
+    pub fn between(self, min: f32, max: f32) -> Parameter {
+        Parameter {
+            min: Some(min),
+            max: Some(max),
+            ..self
+        }
+    }
+
+    pub fn parse(string: &str) -> Result<Parameter, String> {
+        let mut pieces = string.split('@');
+
+        let (Some(value_type), Some(unit), range, None) =
+            (pieces.next(), pieces.next(), pieces.next(), pieces.next())
+        else {
+            return Err(format!("cannot parse '{}' as a Parameter", string));
+        };
+
+        let mut parameter =
+            Parameter::new(DatumValueType::parse(value_type)?, DatumUnit::parse(unit)?);
+
+        if let Some(range) = range {
+            let Some((min, max)) = range.split_once("..") else {
+                return Err(format!("cannot parse '{}' as a range", range));
+            };
+
+            let bound = |bound: &str| -> Result<Option<f32>, String> {
+                match bound {
+                    "" => Ok(None),
+                    bound => bound
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| format!("cannot parse '{}' as f32", bound)),
+                }
+            };
+
+            parameter.min = bound(min)?;
+            parameter.max = bound(max)?;
+        }
+
+        Ok(parameter)
+    }
+
+    /// Returns an error if the `value` is not of this parameter's type, or is out of its range.
+    fn validate(&self, value: &DatumValue) -> Result<(), String> {
+        let number = match (self.value_type, value) {
+            (DatumValueType::Bool, DatumValue::Bool(_)) => return Ok(()),
+            (DatumValueType::Int, DatumValue::Int(value)) => *value as f32,
+            (DatumValueType::Float, DatumValue::Int(value)) => *value as f32,
+            (DatumValueType::Float, DatumValue::Float(value)) => *value,
+            (value_type, value) => return Err(format!("{} is not a {}", value, value_type)),
+        };
+
+        let above = self.min.is_none_or(|min| number >= min);
+        let below = self.max.is_none_or(|max| number <= max);
+
+        if above && below && !number.is_nan() {
+            Ok(())
+        } else {
+            let bound = |bound: Option<f32>| bound.map(|b| b.to_string()).unwrap_or_default();
+            Err(format!(
+                "{} is not in the range {}..{}",
+                value,
+                bound(self.min),
+                bound(self.max)
+            ))
+        }
+    }
+}
+
+impl Display for Spec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.parameter {
+            None => write!(f, "{}", self.name),
+            Some(parameter) => write!(f, "{}:{}", self.name, parameter),
+        }
+    }
+}
+
+impl Spec {
+    pub fn parse(string: &str) -> Result<Spec, String> {
+        let (name, parameter) = match string.split_once(':') {
+            Some((name, parameter)) => (name, Some(Parameter::parse(parameter)?)),
+            None => (string, None),
+        };
+
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("'{}' is not a valid command name", name));
+        }
+
+        Ok(Spec {
+            name: String::from(name),
+            parameter,
+        })
+    }
+}
+
+impl Display for Schema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let specs: Vec<String> = self.commands.iter().map(|spec| spec.to_string()).collect();
+        write!(f, "{}", specs.join(";"))
+    }
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Adds a command which takes no argument.
+    pub fn command(mut self, name: &str) -> Schema {
+        self.commands.push(Spec {
+            name: String::from(name),
+            parameter: None,
+        });
+        self
+    }
+
+    /// Adds a command which takes a single argument, described by the `parameter`.
+    pub fn command_with(mut self, name: &str, parameter: Parameter) -> Schema {
+        self.commands.push(Spec {
+            name: String::from(name),
+            parameter: Some(parameter),
+        });
+        self
+    }
+
+    /// Returns the `Spec` of the command with the specified `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Spec> {
+        self.commands.iter().find(|spec| spec.name == name)
+    }
+
+    pub fn parse(string: &str) -> Result<Schema, String> {
+        let commands = string
+            .split(';')
+            .filter(|spec| !spec.is_empty())
+            .map(Spec::parse)
+            .collect::<Result<Vec<Spec>, String>>()?;
+
+        Ok(Schema { commands })
+    }
+
+    /// Returns an error if the `command` is not described by this `Schema`, or if its argument is
+    /// missing, of the wrong type, or out of range.
+    pub fn validate(&self, command: &GenericCommand) -> Result<(), String> {
+        let Some(spec) = self.get(&command.name) else {
+            let names: Vec<&str> = self.commands.iter().map(|s| s.name.as_str()).collect();
+            return Err(format!(
+                "unknown command '{}' (expected one of {})",
+                command.name,
+                names.join(", ")
+            ));
+        };
+
+        match (&spec.parameter, &command.argument) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(format!("{} takes no argument", spec.name)),
+            (Some(parameter), None) => Err(format!(
+                "{} requires a {} argument",
+                spec.name, parameter.value_type
+            )),
+            (Some(parameter), Some(argument)) => parameter.validate(argument),
+        }
+    }
+
+    /// Builds a `GenericCommand` with the specified `name` and `argument`, if it is valid.
+    pub fn build(
+        &self,
+        name: &str,
+        argument: Option<DatumValue>,
+    ) -> Result<GenericCommand, String> {
+        let command = GenericCommand::new(name, argument);
+        self.validate(&command)?;
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .command("On")
+            .command("Off")
+            .command_with(
+                "DimTo",
+                Parameter::new(DatumValueType::Float, DatumUnit::Unitless).between(0.0, 100.0),
+            )
+            .command_with(
+                "HeatTo",
+                Parameter::new(DatumValueType::Float, DatumUnit::DegreesC),
+            )
+    }
+
+    #[test]
+    fn test_serde_generic_command() {
+        let commands = ["Toggle", "HeatTo:21.5", "DimTo:40", "Lock:true"];
+
+        for string in commands {
+            let command = GenericCommand::parse(string).unwrap();
+            assert_eq!(command.to_string(), string);
+        }
+
+        assert_eq!(
+            GenericCommand::parse("DimTo:40"),
+            Ok(GenericCommand::new("DimTo", Some(DatumValue::Int(40))))
+        );
+
+        assert!(GenericCommand::parse("").is_err());
+        assert!(GenericCommand::parse("Heat To:21").is_err());
+        assert!(GenericCommand::parse("HeatTo:warm").is_err());
+    }
+
+    #[test]
+    fn test_serde_schema() {
+        let schema = schema();
+        let serialized = schema.to_string();
+
+        assert_eq!(serialized, "On;Off;DimTo:float@@0..100;HeatTo:float@°C");
+        assert_eq!(Schema::parse(&serialized), Ok(schema));
+
+        let parameter = Parameter::parse("int@@..10").unwrap();
+        assert_eq!((parameter.min, parameter.max), (None, Some(10.0)));
+
+        assert!(Schema::parse("DimTo:float@@0-100").is_err());
+        assert!(Schema::parse("DimTo:percent@").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = schema();
+        let validate = |string: &str| schema.validate(&GenericCommand::parse(string).unwrap());
+
+        assert_eq!(validate("On"), Ok(()));
+        assert_eq!(validate("DimTo:40"), Ok(()));
+        assert_eq!(validate("HeatTo:-5.5"), Ok(()));
+
+        assert_eq!(
+            validate("Toggle"),
+            Err(String::from(
+                "unknown command 'Toggle' (expected one of On, Off, DimTo, HeatTo)"
+            ))
+        );
+        assert_eq!(validate("On:1"), Err(String::from("On takes no argument")));
+        assert_eq!(
+            validate("DimTo"),
+            Err(String::from("DimTo requires a float argument"))
+        );
+        assert_eq!(
+            validate("DimTo:150"),
+            Err(String::from("150 is not in the range 0..100"))
+        );
+        assert_eq!(
+            validate("DimTo:true"),
+            Err(String::from("true is not a float"))
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let schema = schema();
+
+        let command = schema.build("DimTo", Some(DatumValue::Float(25.0)));
+        assert_eq!(command.unwrap().to_string(), "DimTo:25.0");
+
+        assert!(schema.build("DimTo", None).is_err());
+    }
+}
@@ -1,73 +1,143 @@
-use std::io::Read;
-use std::io::{BufRead, BufReader, Write};
+use std::collections::HashMap;
+use std::io::Write;
 use std::net::TcpListener;
 
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use device::{Device, Id};
+use datum::{Datum, DatumUnit};
+use device::{protocol, Device, Id};
+
+pub use crate::command::{Command, GenericCommand, Parameter, Schema, Spec};
+
+mod command;
 
 /// An Actuator mutates the Environment.
 pub trait Actuator: Device {
+    /// The type of `Command` this actuator accepts.
+    type Command: Command;
+
     /// The `act` command tells the actuator to perform some action.
     ///
     /// The action can be anything (turning on a light, setting a thermostat target temperature to
-    /// some value, locking a door, etc.), so the `command` is a `String` which can be formatted in
-    /// any way by the `Controller` and parsed in any way by the `Actuator`.
+    /// some value, locking a door, etc.), so each type of actuator defines its own `Command`s, and
+    /// describes them with a `Schema`, so the `Controller` can build them generically.
     ///
     /// In the "real world", this would perform some actual, physical action.
     ///
     /// In our example MVP, this sends a command to the `Environment` which mutates its state.
-    fn act(&self, device: Id, command: String);
+    /// Returns the state of the `Environment` after the action (e.g. whether a plug is now on), or
+    /// an error if the command is invalid, or could not be carried out.
+    fn act(&self, device: Id, command: Self::Command) -> Result<Datum, String>;
+
+    /// Returns the kind of this actuator, e.g. `temperature` or `plug`.
+    fn get_kind(&self) -> &str;
+
+    /// Describes the capabilities of this actuator as mDNS TXT properties: its `kind`, and the
+    /// `Schema` of the `commands` it accepts (see `Device::get_capabilities`).
+    fn describe(&self) -> HashMap<String, String> {
+        let mut properties = HashMap::new();
+        properties.insert(String::from("kind"), self.get_kind().to_string());
+        properties.insert(
+            String::from("commands"),
+            Self::Command::schema().to_string(),
+        );
+        properties
+    }
 
     /// Responds to all incoming requests by forwarding them to the `Environment`.
+    ///
+    /// `GET /schema` requests are answered with the `Schema` of this actuator's `Command`s, and
+    /// commands which do not match it are rejected with a `400`, as are requests for protocol
+    /// versions this actuator doesn't speak (see `device::protocol`).
     fn respond(&self, listener: TcpListener) {
         for mut stream in listener.incoming().flatten() {
-            let mut reader = BufReader::new(&mut stream);
-            let mut request = String::new();
-            let mut content_length: usize = 0;
-
-            // Read the headers
-            loop {
-                let mut line = String::new();
-                let len = reader.read_line(&mut line).unwrap();
-                if len == 0 || line == "\r\n" {
-                    break;
+            let request = match device::http::read_request(&mut stream, device::http::TIMEOUT) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(id = %self.get_id(), name = %self.get_name(), error = %err, "cannot read request");
+                    continue;
                 }
+            };
 
-                if line.starts_with("Content-Length:") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    content_length = parts[1].parse().unwrap_or(0);
+            let (request, body) = request.split_once("\r\n\r\n").unwrap_or((&request, ""));
+
+            let version = match protocol::version(request) {
+                Ok(version) => version,
+                Err(msg) => {
+                    warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "invalid request");
+                    let ack = format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg);
+                    stream.write_all(ack.as_bytes()).unwrap();
+                    continue;
                 }
+            };
 
-                request.push_str(&line);
-            }
+            let header = format!("{}: {}", protocol::HEADER, version);
 
-            // Read the body based on Content-Length
-            let mut body = String::new();
-            if content_length > 0 {
-                let mut body_buffer = vec![0; content_length];
-                reader.read_exact(&mut body_buffer).unwrap();
-                body = String::from_utf8(body_buffer).unwrap_or_default();
+            if request.starts_with("GET /schema") {
+                debug!(id = %self.get_id(), name = %self.get_name(), "received schema request");
+                let schema = Self::Command::schema();
+                let ack = format!("HTTP/1.1 200 OK\r\n{}\r\n\r\n{}", header, schema);
+                stream.write_all(ack.as_bytes()).unwrap();
+                continue;
             }
 
-            println!(
-                "{} received request: {} with body: {}",
-                self.get_name(),
-                request.trim(),
-                &body
+            info!(
+                id = %self.get_id(),
+                name = %self.get_name(),
+                request = request.trim(),
+                body,
+                "received request"
             );
 
-            // TODO Does Id matter since the appropriate Actuator is handling it anyways?
-            let temp_id = Id::new(&Uuid::new_v4().to_string());
+            let ack = match Self::Command::parse(body.trim()) {
+                Ok(command) => {
+                    // TODO Does Id matter since the appropriate Actuator is handling it anyways?
+                    let temp_id = Id::new(&Uuid::new_v4().to_string());
 
-            // TODO Should we add a return type to `act` and then our HTTP response
-            //  depends on the success of the act call? Or just always send back 200?
-            self.act(temp_id, body.to_string());
+                    match self.act(temp_id, command) {
+                        Ok(datum) => format!("HTTP/1.1 200 OK\r\n{}\r\n\r\n{}", header, datum),
+                        Err(msg) => {
+                            warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "cannot act");
+                            format!(
+                                "HTTP/1.1 500 Internal Server Error\r\n{}\r\n\r\n{}",
+                                header, msg
+                            )
+                        }
+                    }
+                }
+                Err(msg) => {
+                    warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "invalid command");
+                    format!("HTTP/1.1 400 Bad Request\r\n{}\r\n\r\n{}", header, msg)
+                }
+            };
 
-            let ack = "HTTP/1.1 200 OK\r\n\r\n";
             stream.write_all(ack.as_bytes()).unwrap();
         }
     }
 }
 
-pub trait Command {}
+/// Sends the `command` to the `Environment` at `address`, to be carried out on the attribute with
+/// the specified `Id` and `unit`, returning the attribute's new state.
+///
+/// The `unit` lets the `Environment` model the attribute even if no `Sensor` has measured it yet.
+pub fn command_environment(
+    address: &str,
+    id: &Id,
+    unit: DatumUnit,
+    command: &str,
+) -> Result<Datum, String> {
+    let request = format!(
+        "POST /set/{}/{} HTTP/1.1\r\n{}: {}\r\nContent-Length: {}\r\n\r\n{}",
+        id,
+        unit,
+        protocol::HEADER,
+        protocol::PROTOCOL_VERSION,
+        command.len(),
+        command
+    );
+
+    let body = device::http::send(address, &request, device::http::TIMEOUT)?;
+    Datum::parse(&body)
+}
@@ -0,0 +1,86 @@
+use std::fmt::{Display, Formatter};
+
+use actuator::{GenericCommand, Parameter, Schema};
+use datum::{DatumUnit, DatumValueType};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Command {
+    HumidifyTo(f32), // the Controller tells the Actuator to raise the relative humidity to 'x' %RH
+    DehumidifyTo(f32), // the Controller tells the Actuator to lower the relative humidity to 'x' %RH
+    Off,               // the Controller tells the Actuator to stop humidifying and dehumidifying
+}
+
+impl actuator::Command for Command {
+    fn schema() -> Schema {
+        let humidity =
+            Parameter::new(DatumValueType::Float, DatumUnit::RelativeHumidity).between(0.0, 100.0);
+
+        Schema::new()
+            .command_with("HumidifyTo", humidity)
+            .command_with("DehumidifyTo", humidity)
+            .command("Off")
+    }
+
+    fn from_generic(command: &GenericCommand) -> Result<Command, String> {
+        match command.name.as_str() {
+            "HumidifyTo" => command.float().map(Command::HumidifyTo),
+            "DehumidifyTo" => command.float().map(Command::DehumidifyTo),
+            "Off" => Ok(Command::Off),
+            _ => Err(format!("cannot parse {} as Command", command)),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::HumidifyTo(humidity) => write!(f, "HumidifyTo:{}", humidity),
+            Command::DehumidifyTo(humidity) => write!(f, "DehumidifyTo:{}", humidity),
+            Command::Off => write!(f, "Off"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod actuator_humidity_command_tests {
+    use actuator::Command as _;
+
+    use super::*;
+
+    fn serde(command: &Command) -> Result<Command, String> {
+        let serialized = command.to_string();
+        Command::parse(serialized.as_str())
+    }
+
+    #[test]
+    fn test_serde_humidify_to() {
+        let command = Command::HumidifyTo(65.0);
+        let deserialized = serde(&command);
+
+        assert_eq!(deserialized, Ok(command))
+    }
+
+    #[test]
+    fn test_serde_dehumidify_to() {
+        let command = Command::DehumidifyTo(42.5);
+        let deserialized = serde(&command);
+
+        assert_eq!(deserialized, Ok(command))
+    }
+
+    #[test]
+    fn test_serde_off() {
+        let command = Command::Off;
+        let deserialized = serde(&command);
+
+        assert_eq!(deserialized, Ok(command))
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Command::parse("HumidifyTo:wet").is_err());
+        assert!(Command::parse("HumidifyTo:120").is_err());
+        assert!(Command::parse("Off:50").is_err());
+        assert!(Command::parse("HeatTo:21").is_err());
+    }
+}
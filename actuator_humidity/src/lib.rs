@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use actuator::Actuator;
+use datum::{Datum, DatumUnit};
+use device::{Device, Id, Name};
+
+pub use crate::command::Command;
+
+mod command;
+
+pub struct HumidityActuator {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` which this actuator humidifies and dehumidifies
+    environment: String,
+}
+
+impl Device for HumidityActuator {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Actuator for HumidityActuator {
+    type Command = Command;
+
+    fn act(&self, _id: Id, command: Command) -> Result<Datum, String> {
+        // the Environment knows this actuator's zone by the actuator's own Id
+        actuator::command_environment(
+            &self.environment,
+            &self.id,
+            DatumUnit::RelativeHumidity,
+            &command.to_string(),
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "humidity"
+    }
+}
+
+impl HumidityActuator {
+    pub fn new(id: Id, name: Name, environment: &str) -> HumidityActuator {
+        HumidityActuator {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::fmt::{Display, Formatter};
+
+use actuator::{GenericCommand, Parameter, Schema};
+use datum::{DatumUnit, DatumValueType};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Command {
+    On,         // the Controller tells the Actuator to turn the lights on at full brightness
+    Off,        // the Controller tells the Actuator to turn the lights off
+    DimTo(f32), // the Controller tells the Actuator to set the lights to 'x' percent brightness
+}
+
+impl actuator::Command for Command {
+    fn schema() -> Schema {
+        let percent =
+            Parameter::new(DatumValueType::Float, DatumUnit::Unitless).between(0.0, 100.0);
+
+        Schema::new()
+            .command("On")
+            .command("Off")
+            .command_with("DimTo", percent)
+    }
+
+    fn from_generic(command: &GenericCommand) -> Result<Command, String> {
+        match command.name.as_str() {
+            "On" => Ok(Command::On),
+            "Off" => Ok(Command::Off),
+            "DimTo" => command.float().map(Command::DimTo),
+            _ => Err(format!("cannot parse {} as Command", command)),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::On => write!(f, "On"),
+            Command::Off => write!(f, "Off"),
+            Command::DimTo(percent) => write!(f, "DimTo:{}", percent),
+        }
+    }
+}
+
+impl Command {
+    /// Returns how bright the lights are after this command, in percent.
+    pub fn brightness(&self) -> f32 {
+        match self {
+            Command::On => 100.0,
+            Command::Off => 0.0,
+            Command::DimTo(percent) => *percent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod actuator_light_command_tests {
+    use actuator::Command as _;
+
+    use super::*;
+
+    fn serde(command: &Command) -> Result<Command, String> {
+        let serialized = command.to_string();
+        Command::parse(serialized.as_str())
+    }
+
+    #[test]
+    fn test_serde_on_off() {
+        assert_eq!(serde(&Command::On), Ok(Command::On));
+        assert_eq!(serde(&Command::Off), Ok(Command::Off));
+    }
+
+    #[test]
+    fn test_serde_dim_to() {
+        let command = Command::DimTo(37.5);
+        let deserialized = serde(&command);
+
+        assert_eq!(deserialized, Ok(command))
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Command::parse("DimTo:bright").is_err());
+        assert!(Command::parse("DimTo:150").is_err());
+        assert!(Command::parse("On:50").is_err());
+        assert!(Command::parse("HeatTo:21").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use actuator::Actuator;
+use datum::{Datum, DatumUnit};
+use device::{Device, Id, Name};
+
+pub use crate::command::Command;
+
+mod command;
+
+pub struct LightActuator {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` whose lights this actuator switches and dims
+    environment: String,
+}
+
+impl Device for LightActuator {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Actuator for LightActuator {
+    type Command = Command;
+
+    fn act(&self, _id: Id, command: Command) -> Result<Datum, String> {
+        // the Environment knows this actuator's zone by the actuator's own Id
+        actuator::command_environment(
+            &self.environment,
+            &self.id,
+            DatumUnit::Lux,
+            &command.to_string(),
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "light"
+    }
+}
+
+impl LightActuator {
+    pub fn new(id: Id, name: Name, environment: &str) -> LightActuator {
+        LightActuator {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
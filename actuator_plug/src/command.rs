@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+
+use actuator::{GenericCommand, Schema};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Command {
+    On,     // the Controller tells the Actuator to power the plug on
+    Off,    // the Controller tells the Actuator to power the plug off
+    Toggle, // the Controller tells the Actuator to switch the plug to the opposite state
+}
+
+impl actuator::Command for Command {
+    fn schema() -> Schema {
+        Schema::new().command("On").command("Off").command("Toggle")
+    }
+
+    fn from_generic(command: &GenericCommand) -> Result<Command, String> {
+        match command.name.as_str() {
+            "On" => Ok(Command::On),
+            "Off" => Ok(Command::Off),
+            "Toggle" => Ok(Command::Toggle),
+            _ => Err(format!("cannot parse {} as Command", command)),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::On => write!(f, "On"),
+            Command::Off => write!(f, "Off"),
+            Command::Toggle => write!(f, "Toggle"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod actuator_plug_command_tests {
+    use actuator::Command as _;
+
+    use super::*;
+
+    fn serde(command: &Command) -> Result<Command, String> {
+        let serialized = command.to_string();
+        Command::parse(serialized.as_str())
+    }
+
+    #[test]
+    fn test_serde() {
+        assert_eq!(serde(&Command::On), Ok(Command::On));
+        assert_eq!(serde(&Command::Off), Ok(Command::Off));
+        assert_eq!(serde(&Command::Toggle), Ok(Command::Toggle));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Command::parse("on").is_err());
+        assert!(Command::parse("Toggle:1").is_err());
+        assert!(Command::parse("DimTo:50").is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use actuator::Actuator;
+use datum::{Datum, DatumUnit};
+use device::{Device, Id, Name};
+
+pub use crate::command::Command;
+
+mod command;
+
+/// A smart plug, which switches the power to whatever is plugged into it, and reports whether it
+/// is now on.
+pub struct PlugActuator {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` whose plug this actuator switches
+    environment: String,
+}
+
+impl Device for PlugActuator {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Actuator for PlugActuator {
+    type Command = Command;
+
+    fn act(&self, _id: Id, command: Command) -> Result<Datum, String> {
+        // the Environment knows this plug by the actuator's own Id
+        actuator::command_environment(
+            &self.environment,
+            &self.id,
+            DatumUnit::PoweredOn,
+            &command.to_string(),
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "plug"
+    }
+}
+
+impl PlugActuator {
+    pub fn new(id: Id, name: Name, environment: &str) -> PlugActuator {
+        PlugActuator {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use clap::Parser;
+
+use actuator::Actuator;
+use actuator_plug::PlugActuator;
+use device::config::DeviceCli;
+use device::Device;
+
+/// Switches a smart plug in the `Environment` on and off, as commanded by the `Controller`.
+fn main() {
+    let config = DeviceCli::parse()
+        .load(9901, "_actuator")
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+
+    let actuator = PlugActuator::new(config.id, config.name, &config.environment);
+    let listener = actuator.bind(config.address, config.port, &config.group);
+
+    actuator.respond(listener)
+}
@@ -1,12 +1,31 @@
 use std::fmt::{Display, Formatter};
 
-#[derive(PartialEq, Debug)]
+use actuator::{GenericCommand, Parameter, Schema};
+use datum::{DatumUnit, DatumValueType};
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum Command {
     CoolTo(f32), // the Controller tells the Actuator to cool the Environment to 'x' degrees C
     HeatTo(f32), // the Controller tells the Actuator to heat the Environment to 'x' degrees C
 }
 
-impl actuator::Command for Command {}
+impl actuator::Command for Command {
+    fn schema() -> Schema {
+        let temperature = Parameter::new(DatumValueType::Float, DatumUnit::DegreesC);
+
+        Schema::new()
+            .command_with("CoolTo", temperature)
+            .command_with("HeatTo", temperature)
+    }
+
+    fn from_generic(command: &GenericCommand) -> Result<Command, String> {
+        match command.name.as_str() {
+            "CoolTo" => command.float().map(Command::CoolTo),
+            "HeatTo" => command.float().map(Command::HeatTo),
+            _ => Err(format!("cannot parse {} as Command", command)),
+        }
+    }
+}
 
 impl Display for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -17,26 +36,10 @@ impl Display for Command {
     }
 }
 
-impl Command {
-    pub fn parse(string: &str) -> Result<Command, String> {
-        let mut pieces = string.split(':');
-
-        match (pieces.next(), pieces.next()) {
-            (Some("CoolTo"), Some(temp)) => match temp.parse() {
-                Ok(temp) => Ok(Command::CoolTo(temp)),
-                Err(_) => Err(format!("cannot parse {} as f32", temp)),
-            },
-            (Some("HeatTo"), Some(temp)) => match temp.parse() {
-                Ok(temp) => Ok(Command::HeatTo(temp)),
-                Err(_) => Err(format!("cannot parse {} as f32", temp)),
-            },
-            _ => Err(format!("cannot parse {} as Command", string)),
-        }
-    }
-}
-
 #[cfg(test)]
 mod actuator_temperature_command_tests {
+    use actuator::Command as _;
+
     use super::*;
 
     fn serde(command: &Command) -> Result<Command, String> {
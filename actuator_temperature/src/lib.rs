@@ -1,13 +1,18 @@
+use std::collections::HashMap;
+
 use actuator::Actuator;
+use datum::{Datum, DatumUnit};
 use device::{Device, Id, Name};
 
-use crate::command::Command;
+pub use crate::command::Command;
 
 mod command;
 
 pub struct TemperatureActuator {
     id: Id,
     name: Name,
+    /// The address of the `Environment` which this actuator heats and cools
+    environment: String,
 }
 
 impl Device for TemperatureActuator {
@@ -18,20 +23,36 @@ impl Device for TemperatureActuator {
     fn get_id(&self) -> &Id {
         &self.id
     }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
 }
 
 impl Actuator for TemperatureActuator {
-    fn act(&self, _id: Id, command: String) {
-        let command_is_valid = Command::parse(command.as_str()).is_ok();
+    type Command = Command;
+
+    fn act(&self, _id: Id, command: Command) -> Result<Datum, String> {
+        // the Environment knows this actuator's zone by the actuator's own Id
+        actuator::command_environment(
+            &self.environment,
+            &self.id,
+            DatumUnit::DegreesC,
+            &command.to_string(),
+        )
+    }
 
-        if command_is_valid {
-            todo!() // send to Environment
-        }
+    fn get_kind(&self) -> &str {
+        "temperature"
     }
 }
 
 impl TemperatureActuator {
-    pub fn new(id: Id, name: Name) -> TemperatureActuator {
-        TemperatureActuator { id, name }
+    pub fn new(id: Id, name: Name, environment: &str) -> TemperatureActuator {
+        TemperatureActuator {
+            id,
+            name,
+            environment: String::from(environment),
+        }
     }
 }
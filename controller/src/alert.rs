@@ -0,0 +1,858 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use datum::Datum;
+use device::Id;
+
+/// What an `Alert` is about.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum AlertKind {
+    /// A `Sensor`'s readings stayed outside of a critical `Band`
+    OutOfRange,
+    /// A device stopped responding to the `Controller`
+    Offline,
+    /// A `Zone` didn't reach the setpoint it was commanded to
+    SetpointNotReached,
+}
+
+impl Display for AlertKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertKind::OutOfRange => write!(f, "out_of_range"),
+            AlertKind::Offline => write!(f, "offline"),
+            AlertKind::SetpointNotReached => write!(f, "setpoint_not_reached"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl Display for AlertState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertState::Firing => write!(f, "firing"),
+            AlertState::Resolved => write!(f, "resolved"),
+        }
+    }
+}
+
+/// A problem the `Controller` has noticed (or noticed has gone away).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Alert {
+    /// Identifies the problem, so that it is only reported once while it persists
+    pub key: String,
+    pub kind: AlertKind,
+    /// The `Id` of the `Sensor`, device, or `Zone` with the problem
+    pub subject: Id,
+    pub state: AlertState,
+    /// When the problem started
+    pub since: DateTime<Utc>,
+    /// When the `Alert` fired or was resolved
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "key": self.key,
+            "kind": self.kind.to_string(),
+            "subject": self.subject.to_string(),
+            "state": self.state.to_string(),
+            "since": self.since.to_rfc3339(),
+            "at": self.at.to_rfc3339(),
+            "message": self.message,
+        })
+    }
+}
+
+/// A critical band for the readings of some `Sensor`s, e.g. "the freezer must stay below -15°C".
+#[derive(PartialEq, Debug, Clone)]
+pub struct Band {
+    pub name: String,
+    pub sensors: Vec<Id>,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// How long a reading must stay outside of the band before an `Alert` fires
+    pub duration: Duration,
+}
+
+impl Band {
+    fn contains(&self, value: f32) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+/// When an `Alert` fires because a `Zone` didn't reach the setpoint it was commanded to.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SetpointCheck {
+    /// How long a `Zone` has to reach its setpoint
+    pub within: Duration,
+    /// How close to the setpoint counts as reaching it
+    pub tolerance: f32,
+}
+
+/// Configures the `Alert`s raised by the `Controller`, for example
+///
+/// ```toml
+/// # a device is offline after it hasn't responded for 1 minute
+/// offline = "1m"
+///
+/// # alerts are POSTed as JSON to each of these URLs
+/// webhooks = ["http://127.0.0.1:9000/alerts"]
+///
+/// [setpoint]
+/// within = "30m"
+/// tolerance = 0.5
+///
+/// [[bands]]
+/// name = "freezer"
+/// sensors = ["freezer-1", "freezer-2"]
+/// min = -30
+/// max = -15
+/// for = "10m"
+/// ```
+///
+/// Every section is optional; checks which aren't configured never raise `Alert`s.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct AlertConfig {
+    pub bands: Vec<Band>,
+    /// How long a device can go without responding before it is considered offline
+    pub offline: Option<Duration>,
+    pub setpoint: Option<SetpointCheck>,
+    pub webhooks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAlertConfig {
+    #[serde(default)]
+    bands: Vec<RawBand>,
+    offline: Option<String>,
+    setpoint: Option<RawSetpointCheck>,
+    #[serde(default)]
+    webhooks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBand {
+    name: String,
+    sensors: Vec<String>,
+    min: Option<f32>,
+    max: Option<f32>,
+    #[serde(rename = "for", default = "RawBand::immediately")]
+    duration: String,
+}
+
+impl RawBand {
+    fn immediately() -> String {
+        String::from("0s")
+    }
+
+    fn validate(&self) -> Result<Band, String> {
+        if self.sensors.is_empty() {
+            return Err(String::from("a band needs at least one sensor"));
+        }
+
+        match (self.min, self.max) {
+            (None, None) => return Err(String::from("a band needs a min, a max, or both")),
+            (Some(min), Some(max)) if min > max => {
+                return Err(format!("min ({}) is greater than max ({})", min, max))
+            }
+            _ => {}
+        }
+
+        Ok(Band {
+            name: self.name.clone(),
+            sensors: self.sensors.iter().map(|id| Id::new(id)).collect(),
+            min: self.min,
+            max: self.max,
+            duration: non_negative(&self.duration)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSetpointCheck {
+    within: String,
+    #[serde(default = "RawSetpointCheck::default_tolerance")]
+    tolerance: f32,
+}
+
+impl RawSetpointCheck {
+    fn default_tolerance() -> f32 {
+        0.5
+    }
+}
+
+/// Parses a duration with `device::config::parse_duration`, which never allows negative ones.
+fn non_negative(string: &str) -> Result<Duration, String> {
+    let duration = device::config::parse_duration(string)?;
+    Duration::from_std(duration).map_err(|err| format!("duration '{}': {}", string, err))
+}
+
+impl AlertConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<AlertConfig, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read alerts {}: {}", path.display(), err))?;
+
+        AlertConfig::parse_toml(&contents)
+            .map_err(|msg| format!("invalid alerts {}:\n{}", path.display(), msg))
+    }
+
+    pub fn parse_toml(string: &str) -> Result<AlertConfig, String> {
+        let raw: RawAlertConfig = toml::from_str(string).map_err(|err| err.to_string())?;
+
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        let mut config = AlertConfig::default();
+
+        for (index, band) in raw.bands.iter().enumerate() {
+            if !names.insert(band.name.as_str()) {
+                errors.push(format!("bands[{}]: duplicate name '{}'", index, band.name));
+                continue;
+            }
+
+            match band.validate() {
+                Ok(band) => config.bands.push(band),
+                Err(msg) => errors.push(format!("bands[{}]: {}", index, msg)),
+            }
+        }
+
+        if let Some(offline) = raw.offline.as_deref() {
+            match non_negative(offline) {
+                Ok(duration) => config.offline = Some(duration),
+                Err(msg) => errors.push(format!("offline: {}", msg)),
+            }
+        }
+
+        if let Some(setpoint) = raw.setpoint.as_ref() {
+            match non_negative(&setpoint.within) {
+                Ok(within) if setpoint.tolerance >= 0.0 => {
+                    config.setpoint = Some(SetpointCheck {
+                        within,
+                        tolerance: setpoint.tolerance,
+                    })
+                }
+                Ok(_) => errors.push(String::from("setpoint: tolerance must not be negative")),
+                Err(msg) => errors.push(format!("setpoint: {}", msg)),
+            }
+        }
+
+        for (index, url) in raw.webhooks.iter().enumerate() {
+            match WebhookSink::new(url) {
+                Ok(_) => config.webhooks.push(url.clone()),
+                Err(msg) => errors.push(format!("webhooks[{}]: {}", index, msg)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+/// What the `Alerter` needs to know about the `Controller`'s state.
+#[derive(Default)]
+pub struct Observations {
+    /// The latest `Datum` from each `Sensor`
+    pub latest: HashMap<Id, Datum>,
+    /// When each device which isn't responding first failed to respond
+    pub failing: HashMap<Id, DateTime<Utc>>,
+    /// The setpoint each `Zone` was last commanded to, and its current reading
+    pub zones: HashMap<Id, (f32, f32)>,
+}
+
+/// Decides when `Alert`s fire and resolve.
+///
+/// An `Alert` is only reported when it fires and when it resolves, not every time the problem is
+/// observed.
+#[derive(Default)]
+pub struct Alerter {
+    config: AlertConfig,
+    /// When each problem which hasn't fired yet was first observed
+    pending: HashMap<String, DateTime<Utc>>,
+    /// The setpoint each `Zone` was commanded to when the pending problem was first observed
+    targets: HashMap<Id, f32>,
+    /// The `Alert`s which are currently firing
+    firing: HashMap<String, Alert>,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig) -> Alerter {
+        Alerter {
+            config,
+            ..Alerter::default()
+        }
+    }
+
+    /// Returns the `Alert`s which are currently firing, oldest first.
+    pub fn firing(&self) -> Vec<&Alert> {
+        let mut firing: Vec<&Alert> = self.firing.values().collect();
+        firing.sort_by_key(|alert| (alert.at, alert.key.clone()));
+        firing
+    }
+
+    /// Checks the `observations` against the `AlertConfig`, returning every `Alert` which fired or
+    /// was resolved since the last check.
+    pub fn evaluate(&mut self, now: DateTime<Utc>, observations: &Observations) -> Vec<Alert> {
+        let mut changes = Vec::new();
+
+        for band in self.config.bands.clone() {
+            for sensor in band.sensors.iter() {
+                let key = format!("band:{}:{}", band.name, sensor);
+
+                let Some(value) = observations
+                    .latest
+                    .get(sensor)
+                    .and_then(|d| d.get_as_float())
+                else {
+                    continue;
+                };
+
+                let problem = (!band.contains(value)).then(|| {
+                    let since = *self.pending.entry(key.clone()).or_insert(now);
+                    let message = format!(
+                        "{} is {}, outside of band '{}' [{}, {}]",
+                        sensor,
+                        value,
+                        band.name,
+                        band.min.map_or(String::from("-∞"), |min| min.to_string()),
+                        band.max.map_or(String::from("∞"), |max| max.to_string()),
+                    );
+                    (since, band.duration, message)
+                });
+
+                self.observe(
+                    &key,
+                    AlertKind::OutOfRange,
+                    sensor,
+                    problem,
+                    now,
+                    &mut changes,
+                );
+            }
+        }
+
+        if let Some(after) = self.config.offline {
+            // devices which respond again are no longer in `failing`, so check those too
+            let mut devices: Vec<Id> = observations.failing.keys().cloned().collect();
+            devices.extend(self.subjects(AlertKind::Offline));
+
+            for device in devices {
+                let key = format!("offline:{}", device);
+
+                let problem = observations.failing.get(&device).map(|since| {
+                    let message =
+                        format!("{} has not responded since {}", device, since.to_rfc3339());
+                    (*since, after, message)
+                });
+
+                self.observe(
+                    &key,
+                    AlertKind::Offline,
+                    &device,
+                    problem,
+                    now,
+                    &mut changes,
+                );
+            }
+        }
+
+        if let Some(check) = self.config.setpoint {
+            for (zone, (target, current)) in observations.zones.iter() {
+                let key = format!("setpoint:{}", zone);
+
+                // a new setpoint restarts the clock
+                if self.targets.insert(zone.clone(), *target) != Some(*target) {
+                    self.pending.remove(&key);
+                }
+
+                let problem = ((current - target).abs() > check.tolerance).then(|| {
+                    let since = *self.pending.entry(key.clone()).or_insert(now);
+                    let message = format!(
+                        "{} is {}, and has not reached its setpoint of {}",
+                        zone, current, target
+                    );
+                    (since, check.within, message)
+                });
+
+                let kind = AlertKind::SetpointNotReached;
+                self.observe(&key, kind, zone, problem, now, &mut changes);
+            }
+        }
+
+        changes
+    }
+
+    /// Returns the subjects of the firing `Alert`s of the specified kind.
+    fn subjects(&self, kind: AlertKind) -> Vec<Id> {
+        self.firing
+            .values()
+            .filter(|alert| alert.kind == kind && alert.state == AlertState::Firing)
+            .map(|alert| alert.subject.clone())
+            .collect()
+    }
+
+    /// Records whether a problem is observed: if so, when it started, how long it must persist
+    /// before an `Alert` fires, and a description of it.
+    fn observe(
+        &mut self,
+        key: &str,
+        kind: AlertKind,
+        subject: &Id,
+        problem: Option<(DateTime<Utc>, Duration, String)>,
+        now: DateTime<Utc>,
+        changes: &mut Vec<Alert>,
+    ) {
+        match problem {
+            Some((since, duration, message)) => {
+                if self.firing.contains_key(key) || now - since < duration {
+                    return;
+                }
+
+                let alert = Alert {
+                    key: key.to_string(),
+                    kind,
+                    subject: subject.clone(),
+                    state: AlertState::Firing,
+                    since,
+                    at: now,
+                    message,
+                };
+
+                self.firing.insert(key.to_string(), alert.clone());
+                changes.push(alert);
+            }
+            None => {
+                self.pending.remove(key);
+
+                if let Some(mut alert) = self.firing.remove(key) {
+                    alert.state = AlertState::Resolved;
+                    alert.at = now;
+                    changes.push(alert);
+                }
+            }
+        }
+    }
+}
+
+/// Somewhere `Alert`s are sent when they fire or resolve.
+pub trait AlertSink: Send {
+    fn notify(&mut self, alert: &Alert) -> Result<(), String>;
+}
+
+/// Prints `Alert`s to the `Controller`'s log.
+pub struct LogSink;
+
+impl AlertSink for LogSink {
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        let (state, kind, subject) = (alert.state, alert.kind, &alert.subject);
+
+        match alert.state {
+            AlertState::Firing => {
+                warn!(%state, %kind, %subject, key = alert.key, "{}", alert.message)
+            }
+            AlertState::Resolved => {
+                info!(%state, %kind, %subject, key = alert.key, "{}", alert.message)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// POSTs `Alert`s as JSON to a URL, like "http://127.0.0.1:9000/alerts".
+///
+/// `Alert`s are queued, and delivered in order on a background thread, so a slow or unreachable
+/// webhook never holds up the control loop. Delivery failures are logged.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    address: String,
+    path: String,
+    queue: Sender<String>,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Result<WebhookSink, String> {
+        let rest = url.strip_prefix("http://").unwrap_or(url);
+
+        let (address, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+
+        if !address.contains(':') {
+            return Err(format!(
+                "cannot parse '{}' as a webhook, expected e.g. 'http://127.0.0.1:9000/alerts'",
+                url
+            ));
+        }
+
+        let (address, path) = (address.to_string(), path.to_string());
+        let (queue, receiver) = std::sync::mpsc::channel::<String>();
+
+        // the thread stops once every clone of this sink has been dropped and the queue is empty
+        let (to, at) = (address.clone(), path.clone());
+        std::thread::spawn(move || {
+            for body in receiver {
+                if let Err(msg) = WebhookSink::deliver(&to, &at, &body) {
+                    warn!(
+                        webhook = format!("{}{}", to, at),
+                        error = msg,
+                        "could not send alert"
+                    )
+                }
+            }
+        });
+
+        Ok(WebhookSink {
+            address,
+            path,
+            queue,
+        })
+    }
+
+    /// POSTs the JSON `body` to the webhook at `path` on `address`, giving up after the
+    /// `device::http::TIMEOUT`.
+    fn deliver(address: &str, path: &str, body: &str) -> Result<(), String> {
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            address,
+            body.len(),
+            body
+        );
+
+        device::http::send(address, &request, device::http::TIMEOUT).map(|_| ())
+    }
+}
+
+impl AlertSink for WebhookSink {
+    /// Queues the `alert` for delivery; returns an error only if the delivery thread has stopped.
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        self.queue
+            .send(alert.to_json().to_string())
+            .map_err(|_| format!("webhook {}{} is not running", self.address, self.path))
+    }
+}
+
+/// Forwards `Alert`s (as JSON) to every subscriber, e.g. the `Controller` API's event stream.
+#[derive(Clone, Default)]
+pub struct AlertStream {
+    subscribers: Arc<Mutex<Vec<Sender<String>>>>,
+}
+
+impl AlertStream {
+    pub fn subscribe(&self) -> Receiver<String> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+impl AlertSink for AlertStream {
+    fn notify(&mut self, alert: &Alert) -> Result<(), String> {
+        let json = alert.to_json().to_string();
+
+        // subscribers which have gone away are forgotten
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(json.clone()).is_ok());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod alert_tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use datum::DatumUnit;
+
+    use super::*;
+
+    const ALERTS: &str = r#"
+        offline = "1m"
+
+        [setpoint]
+        within = "30m"
+
+        [[bands]]
+        name = "freezer"
+        sensors = ["freezer"]
+        max = -15
+        for = "10m"
+    "#;
+
+    fn reading(id: &str, value: f32, now: DateTime<Utc>) -> Observations {
+        let mut observations = Observations::default();
+        let datum = Datum::new(value, DatumUnit::DegreesC, now);
+        observations.latest.insert(Id::new(id), datum);
+        observations
+    }
+
+    fn states(alerts: &[Alert]) -> Vec<AlertState> {
+        alerts.iter().map(|alert| alert.state).collect()
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let config = AlertConfig::parse_toml(include_str!("../alerts.toml")).unwrap();
+
+        assert_eq!(config.bands.len(), 1);
+        assert_eq!(config.offline, Some(Duration::minutes(1)));
+    }
+
+    #[test]
+    /// Alerts accept the same durations as every other configuration file
+    fn test_parse_durations() {
+        let config = AlertConfig::parse_toml(r#"offline = "500ms""#).unwrap();
+        assert_eq!(config.offline, Some(Duration::milliseconds(500)));
+
+        let config = AlertConfig::parse_toml(r#"offline = "1d""#).unwrap();
+        assert_eq!(config.offline, Some(Duration::days(1)));
+
+        assert!(AlertConfig::parse_toml(r#"offline = "-1m""#).is_err());
+    }
+
+    #[test]
+    fn test_band_fires_after_duration_and_resolves() {
+        let mut alerter = Alerter::new(AlertConfig::parse_toml(ALERTS).unwrap());
+        let now = Utc::now();
+
+        assert!(alerter
+            .evaluate(now, &reading("freezer", -18.0, now))
+            .is_empty());
+        assert!(alerter
+            .evaluate(now, &reading("freezer", -10.0, now))
+            .is_empty());
+
+        let later = now + Duration::minutes(10);
+        let fired = alerter.evaluate(later, &reading("freezer", -11.0, later));
+        assert_eq!(states(&fired), vec![AlertState::Firing]);
+        assert_eq!(fired[0].since, now);
+        assert_eq!(fired[0].kind, AlertKind::OutOfRange);
+
+        // while the problem persists, the alert is not repeated
+        let later = later + Duration::minutes(1);
+        assert!(alerter
+            .evaluate(later, &reading("freezer", -12.0, later))
+            .is_empty());
+        assert_eq!(alerter.firing().len(), 1);
+
+        let resolved = alerter.evaluate(later, &reading("freezer", -20.0, later));
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+        assert!(alerter.firing().is_empty());
+    }
+
+    #[test]
+    /// A reading which returns to the band before the duration has passed never fires
+    fn test_band_blip_does_not_fire() {
+        let mut alerter = Alerter::new(AlertConfig::parse_toml(ALERTS).unwrap());
+        let now = Utc::now();
+
+        alerter.evaluate(now, &reading("freezer", -10.0, now));
+        alerter.evaluate(now, &reading("freezer", -20.0, now));
+
+        let later = now + Duration::minutes(10);
+        assert!(alerter
+            .evaluate(later, &reading("freezer", -10.0, later))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_offline() {
+        let mut alerter = Alerter::new(AlertConfig::parse_toml(ALERTS).unwrap());
+        let now = Utc::now();
+
+        let mut observations = Observations::default();
+        observations.failing.insert(Id::new("furnace"), now);
+
+        assert!(alerter.evaluate(now, &observations).is_empty());
+
+        let fired = alerter.evaluate(now + Duration::minutes(1), &observations);
+        assert_eq!(fired[0].kind, AlertKind::Offline);
+        assert_eq!(fired[0].subject, Id::new("furnace"));
+
+        let resolved = alerter.evaluate(now + Duration::minutes(2), &Observations::default());
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+    }
+
+    #[test]
+    /// The clock restarts whenever a zone is commanded to a new setpoint
+    fn test_setpoint_not_reached() {
+        let mut alerter = Alerter::new(AlertConfig::parse_toml(ALERTS).unwrap());
+        let now = Utc::now();
+        let zone = Id::new("house");
+
+        let commanded = |target: f32, current: f32| {
+            let mut observations = Observations::default();
+            observations.zones.insert(zone.clone(), (target, current));
+            observations
+        };
+
+        alerter.evaluate(now, &commanded(21.0, 17.0));
+
+        let later = now + Duration::minutes(20);
+        alerter.evaluate(later, &commanded(22.0, 17.5));
+        assert!(alerter
+            .evaluate(now + Duration::minutes(30), &commanded(22.0, 18.0))
+            .is_empty());
+
+        let fired = alerter.evaluate(later + Duration::minutes(30), &commanded(22.0, 18.5));
+        assert_eq!(fired[0].kind, AlertKind::SetpointNotReached);
+
+        let resolved = alerter.evaluate(later + Duration::minutes(31), &commanded(22.0, 21.8));
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let config = r#"
+            offline = "soon"
+            webhooks = ["nowhere"]
+
+            [[bands]]
+            name = "a"
+            sensors = []
+            max = 1
+
+            [[bands]]
+            name = "b"
+            sensors = ["x"]
+
+            [[bands]]
+            name = "b"
+            sensors = ["x"]
+            min = 2
+            max = 1
+        "#;
+
+        let errors = AlertConfig::parse_toml(config).unwrap_err();
+        let lines: Vec<&str> = errors.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("bands[0]"));
+        assert!(lines[1].starts_with("bands[1]"));
+        assert!(lines[2].starts_with("bands[2]: duplicate"));
+        assert!(lines[3].starts_with("offline"));
+        assert!(lines[4].starts_with("webhooks[0]"));
+    }
+
+    #[test]
+    /// Alerts can be sent to a webhook, here a local stand-in for a real HTTP service
+    fn test_webhook_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+
+            // read until the end of the JSON body
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut sink = WebhookSink::new(&format!("http://127.0.0.1:{}/alerts", port)).unwrap();
+        let now = Utc::now();
+
+        let alert = Alert {
+            key: String::from("offline:furnace"),
+            kind: AlertKind::Offline,
+            subject: Id::new("furnace"),
+            state: AlertState::Firing,
+            since: now,
+            at: now,
+            message: String::from("furnace has not responded"),
+        };
+
+        assert_eq!(sink.notify(&alert), Ok(()));
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /alerts HTTP/1.1"));
+        assert!(request.contains(r#""kind":"offline""#));
+    }
+
+    #[test]
+    /// A webhook which never responds doesn't hold up whoever sends it an alert
+    fn test_webhook_sink_does_not_block() {
+        // connections are queued by the OS, but never accepted
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let mut sink = WebhookSink::new(&format!("http://127.0.0.1:{}/alerts", port)).unwrap();
+        let now = Utc::now();
+
+        let alert = Alert {
+            key: String::from("offline:furnace"),
+            kind: AlertKind::Offline,
+            subject: Id::new("furnace"),
+            state: AlertState::Firing,
+            since: now,
+            at: now,
+            message: String::from("furnace has not responded"),
+        };
+
+        let started = std::time::Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(sink.notify(&alert), Ok(()));
+        }
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_alert_stream() {
+        let mut stream = AlertStream::default();
+        let receiver = stream.subscribe();
+        let now = Utc::now();
+
+        let alert = Alert {
+            key: String::from("offline:furnace"),
+            kind: AlertKind::Offline,
+            subject: Id::new("furnace"),
+            state: AlertState::Resolved,
+            since: now,
+            at: now,
+            message: String::from("furnace has not responded"),
+        };
+
+        stream.notify(&alert).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&receiver.recv().unwrap()).unwrap();
+        assert_eq!(json["state"], "resolved");
+
+        // a subscriber which has gone away is forgotten
+        drop(receiver);
+        stream.notify(&alert).unwrap();
+        assert!(stream.subscribers.lock().unwrap().is_empty());
+    }
+}
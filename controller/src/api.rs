@@ -0,0 +1,702 @@
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::json;
+use tracing::warn;
+
+use datum::{Datum, Reading};
+use device::{protocol, Id};
+
+use actuator::GenericCommand;
+
+use crate::audit::{AuditLog, Query};
+use crate::capabilities::Capabilities;
+use crate::metrics::Metrics;
+use crate::pairing::Zone;
+use crate::setpoint::{Override, Source};
+use crate::state::{Rejection, State};
+use crate::store::Record;
+
+/// The address the `Controller`'s HTTP API listens on.
+pub const ADDRESS: &str = "127.0.0.1:6565";
+
+/// Creates a new thread to serve the `Controller`'s HTTP API.
+///
+/// - `GET /setpoint/<id>` returns the active setpoint of a sensor-actuator pair and its source
+/// - `PUT /setpoint/<id>/override` with a body like `23:2h` holds 23°C for 2 hours
+/// - `DELETE /setpoint/<id>/override` cancels an override
+/// - `GET /zones` lists all explicit `Zone`s, and `GET /zones/<name>` describes one
+/// - `PUT /zones/<name>` with a body like `{"sensors": ["a", "b"], "actuators": ["c"]}` adds or
+///   replaces a `Zone`
+/// - `DELETE /zones/<name>` removes a `Zone`
+/// - `POST /sensors/<id>/readings` with a `Reading` as the body is how `Sensor`s in push mode send
+///   their data; each `Sensor` may only push once every `min_push_interval`, and is answered with a
+///   `429` (and a `Retry-After`) if it pushes any sooner
+/// - `GET /devices` lists every discovered `Sensor` and `Actuator`, and the `Capabilities` each
+///   advertised (what it measures, which commands it accepts, its firmware version, etc.)
+/// - `PUT /actuators/<id>/command` with a body like `HeatTo:23` sends a `Command` to an `Actuator`,
+///   if it matches the `Schema` of the `Actuator`'s commands; `GET /actuators/<id>/schema` returns it
+/// - `GET /audit` lists the `Command`s sent to `Actuator`s, and why; `GET /audit.jsonl` exports
+///   them as JSON lines. Both accept a query like `?actuator=<id>&trigger=rule&since=<rfc3339>&limit=10`
+/// - `GET /metrics` exports the `Controller`'s `Metrics` for Prometheus
+/// - `GET /alerts` lists the `Alert`s which are firing, and `GET /alerts/stream` is a stream of
+///   server-sent events, one for each `Alert` as it fires or is resolved
+///
+/// For implicit sensor-actuator pairs, the `<id>` of a setpoint is their shared `Id`; otherwise
+/// it is the name of the `Zone`.
+pub fn serve(state: State, address: &str) -> std::io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+
+    Ok(std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle_client(&state, stream) {
+                        warn!(error = %e, "failed to respond to client")
+                    }
+                }
+                Err(e) => warn!(error = %e, "failed to handle client"),
+            }
+        }
+    }))
+}
+
+/// Reads a request from a client and responds to it. The API serves one client at a time, so a
+/// client which stops sending its request is given up on after the `device::http::TIMEOUT`.
+fn handle_client(state: &State, mut stream: TcpStream) -> std::io::Result<()> {
+    let Some(request) = device::http::read_request(&mut stream, device::http::TIMEOUT)? else {
+        return Ok(());
+    };
+
+    if parse_request_line(&request) == ("GET", "/alerts/stream") {
+        let alerts = state.subscribe_to_alerts();
+        std::thread::spawn(move || stream_alerts(stream, alerts));
+        return Ok(());
+    }
+
+    let response = handle_request(state, request.trim());
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// Sends each `Alert` to the client as a server-sent event, until the client disconnects.
+fn stream_alerts(mut stream: TcpStream, alerts: Receiver<String>) {
+    let headers =
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n";
+
+    if stream.write_all(headers.as_bytes()).is_err() {
+        return;
+    }
+
+    loop {
+        // a comment line every so often, so we notice if the client has gone away
+        let event = match alerts.recv_timeout(Duration::from_secs(15)) {
+            Ok(alert) => format!("event: alert\ndata: {}\n\n", alert),
+            Err(RecvTimeoutError::Timeout) => String::from(": keep-alive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        if stream.write_all(event.as_bytes()).is_err() || stream.flush().is_err() {
+            return;
+        }
+    }
+}
+
+/// Returns the method and path from the first line of an HTTP request, e.g. ("GET", "/setpoint/id").
+fn parse_request_line(request: &str) -> (&str, &str) {
+    let mut pieces = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    (
+        pieces.next().unwrap_or_default(),
+        pieces.next().unwrap_or_default(),
+    )
+}
+
+/// Returns the body of an HTTP request, or an empty string if there is no body.
+fn parse_body(request: &str) -> &str {
+    match request.split_once("\r\n\r\n") {
+        Some((_, body)) => body.trim(),
+        None => "",
+    }
+}
+
+fn ok_json(value: serde_json::Value) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+        value
+    )
+}
+
+/// Describes the active setpoint of a sensor-actuator pair as JSON.
+fn describe_setpoint(state: &State, id: &Id) -> serde_json::Value {
+    let (setpoint, source) = state.active_setpoint(id);
+
+    let until = match &source {
+        Source::Override(until) => Some(until.to_rfc3339()),
+        _ => None,
+    };
+
+    json!({
+        "id": id.to_string(),
+        "setpoint": setpoint,
+        "source": source.to_string(),
+        "until": until,
+        "adjustment": state.adjustment(id),
+    })
+}
+
+pub(crate) fn handle_request(state: &State, request: &str) -> String {
+    let (method, path) = parse_request_line(request);
+
+    if let Some(rest) = path.strip_prefix("/setpoint/") {
+        handle_setpoint_request(state, request, method, rest)
+    } else if path == "/zones" || path.starts_with("/zones/") {
+        let name = path.trim_start_matches("/zones").trim_start_matches('/');
+        handle_zone_request(state, request, method, name)
+    } else if path == "/metrics" && method == "GET" {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\n\r\n{}",
+            Metrics::content_type(),
+            state.metrics()
+        )
+    } else if path == "/alerts" && method == "GET" {
+        let alerts = state.alerts();
+        ok_json(json!(alerts
+            .iter()
+            .map(|a| a.to_json())
+            .collect::<Vec<_>>()))
+    } else if path == "/devices" && method == "GET" {
+        ok_json(json!(state
+            .devices()
+            .iter()
+            .map(|(id, role, capabilities)| describe_device(id, role, capabilities.as_ref()))
+            .collect::<Vec<_>>()))
+    } else if let Some(rest) = path.strip_prefix("/sensors/") {
+        handle_sensor_request(state, request, method, rest)
+    } else if let Some(rest) = path.strip_prefix("/actuators/") {
+        handle_actuator_request(state, request, method, rest)
+    } else if path == "/audit" || path.starts_with("/audit?") || path.starts_with("/audit.jsonl") {
+        handle_audit_request(state, method, path)
+    } else {
+        "HTTP/1.1 404 Not Found\r\n\r\n".to_string()
+    }
+}
+
+/// Describes a `Zone` as JSON.
+fn describe_zone(zone: &Zone) -> serde_json::Value {
+    let ids = |ids: &[Id]| -> Vec<String> { ids.iter().map(|id| id.to_string()).collect() };
+
+    json!({
+        "name": zone.name,
+        "sensors": ids(&zone.sensors),
+        "actuators": ids(&zone.actuators),
+        "aggregate": zone.aggregate.to_string(),
+    })
+}
+
+/// Describes a discovered `Sensor` or `Actuator` as JSON, along with its `Capabilities` (if it
+/// advertised any).
+fn describe_device(id: &Id, role: &str, capabilities: Option<&Capabilities>) -> serde_json::Value {
+    json!({
+        "id": id.to_string(),
+        "role": role,
+        "capabilities": capabilities.map(|c| c.to_json()),
+    })
+}
+
+fn handle_zone_request(state: &State, request: &str, method: &str, name: &str) -> String {
+    let name = device::http::percent_decode(name);
+    let mut pairings = state.pairings();
+
+    match method {
+        "GET" if name.is_empty() => {
+            let zones: Vec<_> = pairings.zones().iter().map(describe_zone).collect();
+            ok_json(json!(zones))
+        }
+        "GET" => match pairings.get(&name) {
+            Some(zone) => ok_json(describe_zone(zone)),
+            None => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+        },
+        "PUT" if !name.is_empty() => match pairings.insert_json(&name, parse_body(request)) {
+            Ok(()) => {
+                state.record(Record::Pairings {
+                    toml: pairings.to_toml(),
+                });
+                ok_json(describe_zone(pairings.get(&name).unwrap()))
+            }
+            Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+        },
+        "DELETE" if !name.is_empty() => match pairings.remove(&name) {
+            Ok(true) => {
+                state.record(Record::Pairings {
+                    toml: pairings.to_toml(),
+                });
+                "HTTP/1.1 200 OK\r\n\r\n".to_string()
+            }
+            Ok(false) => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+            Err(msg) => format!("HTTP/1.1 500 Internal Server Error\r\n\r\n{}", msg),
+        },
+        _ => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+    }
+}
+
+fn handle_sensor_request(state: &State, request: &str, method: &str, rest: &str) -> String {
+    let Some(id) = rest.strip_suffix("/readings").filter(|_| method == "POST") else {
+        return "HTTP/1.1 404 Not Found\r\n\r\n".to_string();
+    };
+
+    let id = Id::new(&device::http::percent_decode(id));
+
+    let version = match protocol::version(request) {
+        Ok(version) => version,
+        Err(msg) => return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+    };
+
+    // version 1 Sensors only ever report a single Datum
+    let reading = match version {
+        1 => Datum::parse(parse_body(request)).map(Reading::from),
+        _ => Reading::parse(parse_body(request)),
+    };
+
+    let reading = match reading {
+        Ok(reading) => reading,
+        Err(msg) => return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+    };
+
+    match state.receive(&id, version, reading) {
+        Ok(()) => format!(
+            "HTTP/1.1 200 OK\r\n{}: {}\r\n\r\n",
+            protocol::HEADER,
+            version
+        ),
+        Err(Rejection::Unknown) => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+        Err(Rejection::TooSoon(wait)) => format!(
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: {}\r\n\r\n",
+            wait.as_secs_f64().ceil() as u64
+        ),
+        Err(Rejection::Invalid(msg)) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+    }
+}
+
+fn handle_actuator_request(state: &State, request: &str, method: &str, rest: &str) -> String {
+    let (id, action) = match (rest.strip_suffix("/command"), rest.strip_suffix("/schema")) {
+        (Some(id), _) if method == "PUT" => (id, "command"),
+        (_, Some(id)) if method == "GET" => (id, "schema"),
+        _ => return "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+    };
+
+    let id = Id::new(&device::http::percent_decode(id));
+
+    if !state.is_actuator(&id) {
+        return "HTTP/1.1 404 Not Found\r\n\r\n".to_string();
+    }
+
+    // the Actuator describes the Commands it accepts, so they can be checked before they're sent
+    let schema = match state.schema(&id) {
+        Ok(schema) => schema,
+        Err(msg) => return format!("HTTP/1.1 502 Bad Gateway\r\n\r\n{}", msg),
+    };
+
+    if action == "schema" {
+        return format!("HTTP/1.1 200 OK\r\n\r\n{}", schema);
+    }
+
+    let command = GenericCommand::parse(parse_body(request))
+        .and_then(|command| schema.validate(&command).map(|_| command));
+
+    let command = match command {
+        Ok(command) => command,
+        Err(msg) => return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+    };
+
+    match state.command_manually(&id, command) {
+        Ok(status) => ok_json(json!({ "id": id.to_string(), "status": status })),
+        Err(msg) => format!("HTTP/1.1 502 Bad Gateway\r\n\r\n{}", msg),
+    }
+}
+
+fn handle_audit_request(state: &State, method: &str, path: &str) -> String {
+    if method != "GET" {
+        return "HTTP/1.1 404 Not Found\r\n\r\n".to_string();
+    }
+
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+
+    let query = match Query::parse(&device::http::percent_decode(query)) {
+        Ok(query) => query,
+        Err(msg) => return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+    };
+
+    let entries = state.audit(&query);
+
+    match path {
+        "/audit" => ok_json(json!(entries
+            .iter()
+            .map(|entry| entry.to_json())
+            .collect::<Vec<_>>())),
+        "/audit.jsonl" => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/jsonl\r\n\r\n{}",
+            AuditLog::to_json_lines(&entries.iter().collect::<Vec<_>>())
+        ),
+        _ => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+    }
+}
+
+/// Decodes percent-encoded characters (like "%20") in a path segment.
+fn handle_setpoint_request(state: &State, request: &str, method: &str, rest: &str) -> String {
+    let rest = device::http::percent_decode(rest);
+    let rest = rest.as_str();
+
+    let id = Id::new(rest.strip_suffix("/override").unwrap_or(rest));
+
+    if !state.is_known(&id) {
+        return "HTTP/1.1 404 Not Found\r\n\r\n".to_string();
+    }
+
+    match (method, rest.strip_suffix("/override")) {
+        ("GET", None) => ok_json(describe_setpoint(state, &id)),
+        ("PUT", Some(_)) => match Override::parse(parse_body(request), Utc::now()) {
+            Ok(hold) => {
+                state.hold(&id, hold);
+                ok_json(describe_setpoint(state, &id))
+            }
+            Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+        },
+        ("DELETE", Some(_)) => {
+            state.cancel_hold(&id);
+            ok_json(describe_setpoint(state, &id))
+        }
+        _ => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod api_tests {
+    use std::io::{BufRead, BufReader};
+
+    use chrono::NaiveTime;
+
+    use crate::setpoint::{Profile, SetpointSchedule};
+    use crate::strategy::BangBang;
+
+    use super::*;
+
+    fn body(response: &str) -> serde_json::Value {
+        serde_json::from_str(parse_body(response)).unwrap()
+    }
+
+    #[test]
+    fn test_get_audit() {
+        let state = State::new();
+
+        let response = handle_request(&state, "GET /audit?trigger=manual&limit=5 HTTP/1.1");
+        assert_eq!(body(&response), json!([]));
+
+        let response = handle_request(&state, "GET /audit.jsonl HTTP/1.1");
+        assert_eq!(parse_body(&response), "");
+
+        let response = handle_request(&state, "GET /audit?trigger=cron HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_get_metrics() {
+        let state = State::new();
+        let response = handle_request(&state, "GET /metrics HTTP/1.1");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4"));
+    }
+
+    #[test]
+    fn test_get_devices() {
+        let state = State::new();
+        let response = handle_request(&state, "GET /devices HTTP/1.1");
+
+        assert_eq!(body(&response), json!([]));
+
+        let capabilities = Capabilities {
+            kind: Some(String::from("plug")),
+            protocol: device::protocol::Versions::new(1, 2),
+            firmware: None,
+            measurements: Vec::new(),
+            commands: Some(actuator::Schema::new().command("On").command("Off")),
+        };
+
+        assert_eq!(
+            describe_device(&Id::new("kettle"), "actuator", Some(&capabilities)),
+            json!({
+                "id": "kettle",
+                "role": "actuator",
+                "capabilities": {
+                    "kind": "plug",
+                    "protocol": "1..2",
+                    "negotiated": 2,
+                    "firmware": null,
+                    "measures": [],
+                    "commands": "On;Off",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_push_reading() {
+        let state = State::new();
+
+        let request = |body: &str| {
+            format!(
+                "POST /sensors/k1/readings HTTP/1.1\r\nProtocol-Version: 2\r\n\r\n{}",
+                body
+            )
+        };
+
+        let response = handle_request(&state, &request("21.5@°C@2024-01-01T00:00:00Z"));
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+
+        let response = handle_request(&state, &request("warm"));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+
+        let response = handle_request(&state, "GET /sensors/k1/readings HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+    }
+
+    #[test]
+    fn test_payload_too_large() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle_client(&State::new(), stream).unwrap();
+        });
+
+        let request = "PUT /zones/kitchen HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n{}";
+        let response = device::http::send(&address, request, Duration::from_secs(1));
+
+        assert_eq!(
+            response,
+            Err(String::from(
+                "HTTP/1.1 413 Payload Too Large: request bodies are limited to 1048576 bytes"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_alerts() {
+        let state = State::new();
+        let response = handle_request(&state, "GET /alerts HTTP/1.1");
+
+        assert_eq!(body(&response), json!([]));
+    }
+
+    #[test]
+    /// Alerts are streamed to the client as server-sent events
+    fn test_stream_alerts() {
+        use crate::alert::{AlertConfig, AlertState};
+
+        let state = State::new();
+        let config = AlertConfig::parse_toml(r#"offline = "0s""#).unwrap();
+        state.set_alerts(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = {
+            let state = state.clone();
+            std::thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                handle_client(&state, stream).unwrap();
+            })
+        };
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .write_all(b"GET /alerts/stream HTTP/1.1\r\n\r\n")
+            .unwrap();
+        server.join().unwrap();
+
+        // the client is subscribed once the request has been handled
+        state.record_response(&Id::new("furnace"), false);
+        state.check_alerts(Utc::now());
+
+        let mut reader = BufReader::new(client);
+        let mut header = String::new();
+
+        // skip the headers
+        while header != "\r\n" {
+            header.clear();
+            reader.read_line(&mut header).unwrap();
+        }
+
+        let mut event = String::new();
+        reader.read_line(&mut event).unwrap();
+        assert_eq!(event, "event: alert\n");
+
+        let mut data = String::new();
+        reader.read_line(&mut data).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&data["data: ".len()..]).unwrap();
+
+        assert_eq!(json["state"], AlertState::Firing.to_string());
+        assert_eq!(json["subject"], "furnace");
+    }
+
+    #[test]
+    fn test_command_unknown_actuator() {
+        let state = State::new();
+        let request = "PUT /actuators/nope/command HTTP/1.1\r\n\r\nHeatTo:23";
+
+        let response = handle_request(&state, request);
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+
+        // Zones, Sensors, and configuration share the Id space, but can't be commanded
+        let id = Id::new("kitchen");
+        state.set_strategy(id.clone(), Box::new(BangBang::new(21.0, 0.5)));
+
+        let request = "PUT /actuators/kitchen/command HTTP/1.1\r\n\r\nHeatTo:23";
+        assert!(state.is_known(&id));
+        assert_eq!(
+            handle_request(&state, request),
+            "HTTP/1.1 404 Not Found\r\n\r\n"
+        );
+        assert_eq!(
+            handle_request(&state, "GET /actuators/kitchen/schema HTTP/1.1"),
+            "HTTP/1.1 404 Not Found\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_get_setpoint_unknown() {
+        let state = State::new();
+        let response = handle_request(&state, "GET /setpoint/nope HTTP/1.1");
+
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+    }
+
+    #[test]
+    fn test_get_setpoint_from_strategy() {
+        let state = State::new();
+        let id = Id::new("kitchen");
+        state.set_strategy(id, Box::new(BangBang::new(19.0, 1.0)));
+
+        let response = handle_request(&state, "GET /setpoint/kitchen HTTP/1.1");
+        let json = body(&response);
+
+        assert_eq!(json["setpoint"], 19.0);
+        assert_eq!(json["source"], "strategy");
+    }
+
+    #[test]
+    fn test_get_setpoint_from_schedule() {
+        let state = State::new();
+        let profile = Profile::new(vec![(NaiveTime::MIN, 20.5)]);
+        let schedule = SetpointSchedule::new(chrono_tz::UTC, profile.clone()).with_weekend(profile);
+        state.set_schedule(Id::new("kitchen"), schedule);
+
+        let response = handle_request(&state, "GET /setpoint/kitchen HTTP/1.1");
+        let json = body(&response);
+
+        assert_eq!(json["setpoint"], 20.5);
+        assert!(json["source"] == "weekday" || json["source"] == "weekend");
+    }
+
+    #[test]
+    fn test_override() {
+        let state = State::new();
+        let id = Id::new("kitchen");
+        state.set_strategy(id, Box::new(BangBang::new(19.0, 1.0)));
+
+        let request = "PUT /setpoint/kitchen/override HTTP/1.1\r\nContent-Length: 5\r\n\r\n23:2h";
+        let json = body(&handle_request(&state, request));
+
+        assert_eq!(json["setpoint"], 23.0);
+        assert!(json["source"]
+            .as_str()
+            .unwrap()
+            .starts_with("override until"));
+        assert!(json["until"].is_string());
+
+        // when the override is cancelled, we return to the strategy's setpoint
+        let request = "DELETE /setpoint/kitchen/override HTTP/1.1";
+        let json = body(&handle_request(&state, request));
+
+        assert_eq!(json["setpoint"], 19.0);
+    }
+
+    #[test]
+    /// Overrides can't be set on (or cancelled for) setpoints which don't exist
+    fn test_override_unknown() {
+        let state = State::new();
+
+        let request = "PUT /setpoint/typo/override HTTP/1.1\r\nContent-Length: 5\r\n\r\n23:2h";
+        assert_eq!(
+            handle_request(&state, request),
+            "HTTP/1.1 404 Not Found\r\n\r\n"
+        );
+
+        let request = "DELETE /setpoint/typo/override HTTP/1.1";
+        assert_eq!(
+            handle_request(&state, request),
+            "HTTP/1.1 404 Not Found\r\n\r\n"
+        );
+
+        assert!(!state.is_known(&Id::new("typo")));
+    }
+
+    #[test]
+    fn test_zones() {
+        let state = State::new();
+
+        let body_json = r#"{"sensors": ["t1", "t2"], "actuators": ["furnace"]}"#;
+        let request = format!(
+            "PUT /zones/living%20room HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body_json.len(),
+            body_json
+        );
+
+        let json = body(&handle_request(&state, &request));
+        assert_eq!(json["name"], "living room");
+        assert_eq!(json["aggregate"], "mean");
+
+        let json = body(&handle_request(&state, "GET /zones HTTP/1.1"));
+        assert_eq!(json.as_array().unwrap().len(), 1);
+
+        // the zone's setpoint can be queried by its name
+        let response = handle_request(&state, "GET /setpoint/living%20room HTTP/1.1");
+        assert_eq!(body(&response)["setpoint"], 21.0);
+
+        let response = handle_request(&state, "DELETE /zones/living%20room HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let response = handle_request(&state, "GET /zones/living%20room HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+    }
+
+    #[test]
+    fn test_zone_invalid() {
+        let state = State::new();
+
+        let request = "PUT /zones/den HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}";
+        let response = handle_request(&state, request);
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_override_invalid() {
+        let state = State::new();
+        state.set_strategy(Id::new("kitchen"), Box::new(BangBang::new(21.0, 0.5)));
+
+        let request = "PUT /setpoint/kitchen/override HTTP/1.1\r\nContent-Length: 4\r\n\r\nwarm";
+        let response = handle_request(&state, request);
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+}
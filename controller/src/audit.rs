@@ -0,0 +1,308 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use actuator::GenericCommand;
+use datum::Datum;
+use device::Id;
+
+/// The maximum number of `Entry`s kept in the `AuditLog`.
+pub(crate) const AUDIT_LENGTH: usize = 1000;
+
+/// Why a `Command` was sent to an `Actuator`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Trigger {
+    /// The rule with the specified name fired
+    Rule(String),
+    /// The `ControlStrategy` of the `Zone` with the specified name decided to act
+    Strategy(String),
+    /// Someone sent the `Command` through the `Controller`'s API
+    Manual,
+}
+
+impl Display for Trigger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trigger::Rule(name) => write!(f, "rule:{}", name),
+            Trigger::Strategy(zone) => write!(f, "strategy:{}", zone),
+            Trigger::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+impl Trigger {
+    pub fn parse(string: &str) -> Result<Trigger, String> {
+        match string.split_once(':') {
+            Some(("rule", name)) => Ok(Trigger::Rule(name.to_string())),
+            Some(("strategy", zone)) => Ok(Trigger::Strategy(zone.to_string())),
+            None if string == "manual" => Ok(Trigger::Manual),
+            _ => Err(format!("cannot parse {} as Trigger", string)),
+        }
+    }
+
+    /// The kind of `Trigger`: "rule", "strategy", or "manual".
+    pub fn kind(&self) -> &str {
+        match self {
+            Trigger::Rule(_) => "rule",
+            Trigger::Strategy(_) => "strategy",
+            Trigger::Manual => "manual",
+        }
+    }
+
+    /// The name of the rule which sent the `Command`, if a rule sent it.
+    pub fn rule(&self) -> Option<&str> {
+        match self {
+            Trigger::Rule(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A record of one `Command` sent (or attempted) to one `Actuator`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Entry {
+    /// When the `Command` was sent
+    pub at: DateTime<Utc>,
+    /// The `Id` of the `Actuator` the `Command` was sent to
+    pub actuator: Id,
+    pub command: GenericCommand,
+    pub trigger: Trigger,
+    /// The latest `Datum` from each `Sensor` which drove the decision
+    pub readings: Vec<(Id, Datum)>,
+    /// The status line of the `Actuator`'s response, or why there was no response
+    pub status: Result<String, String>,
+    /// How long the `Actuator` took to respond
+    pub latency: std::time::Duration,
+}
+
+impl Entry {
+    pub fn to_json(&self) -> serde_json::Value {
+        let readings: serde_json::Map<String, serde_json::Value> = self
+            .readings
+            .iter()
+            .map(|(id, datum)| (id.to_string(), json!(datum.to_string())))
+            .collect();
+
+        let (status, error) = match &self.status {
+            Ok(status) => (Some(status), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        json!({
+            "at": self.at.to_rfc3339(),
+            "actuator": self.actuator.to_string(),
+            "command": self.command.to_string(),
+            "trigger": self.trigger.to_string(),
+            "readings": readings,
+            "status": status,
+            "error": error,
+            "latency_ms": self.latency.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Selects `Entry`s from the `AuditLog`; every field which is set must match.
+#[derive(PartialEq, Debug, Default)]
+pub struct Query {
+    pub actuator: Option<Id>,
+    /// The kind of `Trigger`; see `Trigger::kind`
+    pub trigger: Option<String>,
+    /// Only `Entry`s at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only this many of the most recent matching `Entry`s
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Parses a `Query` from a URL query string, like "actuator=kitchen&trigger=rule&limit=10".
+    pub fn parse(string: &str) -> Result<Query, String> {
+        let mut query = Query::default();
+
+        for pair in string.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            match key {
+                "actuator" => query.actuator = Some(Id::new(value)),
+                "trigger" => match value {
+                    "rule" | "strategy" | "manual" => query.trigger = Some(value.to_string()),
+                    _ => return Err(format!("unknown trigger '{}'", value)),
+                },
+                "since" => {
+                    let since = value
+                        .parse::<DateTime<Utc>>()
+                        .map_err(|err| format!("cannot parse since '{}': {}", value, err))?;
+                    query.since = Some(since)
+                }
+                "limit" => {
+                    let limit = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("cannot parse limit '{}'", value))?;
+                    query.limit = Some(limit)
+                }
+                _ => return Err(format!("unknown query parameter '{}'", key)),
+            }
+        }
+
+        Ok(query)
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        self.actuator
+            .as_ref()
+            .is_none_or(|id| *id == entry.actuator)
+            && (self.trigger.as_deref()).is_none_or(|kind| kind == entry.trigger.kind())
+            && self.since.is_none_or(|since| entry.at >= since)
+    }
+}
+
+/// The most recent `Command`s sent to any `Actuator`, oldest first, and why they were sent.
+#[derive(Default)]
+pub struct AuditLog {
+    entries: VecDeque<Entry>,
+}
+
+impl AuditLog {
+    /// Appends an `Entry`, discarding the oldest one if the log is full.
+    pub fn push(&mut self, entry: Entry) {
+        if self.entries.len() >= AUDIT_LENGTH {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry)
+    }
+
+    /// Returns the `Entry`s which match the `query`, oldest first.
+    pub fn query(&self, query: &Query) -> Vec<&Entry> {
+        let matching: Vec<&Entry> = self.entries.iter().filter(|e| query.matches(e)).collect();
+        let skip = matching
+            .len()
+            .saturating_sub(query.limit.unwrap_or(usize::MAX));
+
+        matching.into_iter().skip(skip).collect()
+    }
+
+    /// Exports `Entry`s as JSON lines: one JSON object per line.
+    pub fn to_json_lines(entries: &[&Entry]) -> String {
+        entries
+            .iter()
+            .map(|entry| format!("{}\n", entry.to_json()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod audit_tests {
+    use std::time::Duration;
+
+    use datum::DatumUnit;
+
+    use super::*;
+
+    fn entry(actuator: &str, trigger: Trigger, at: DateTime<Utc>) -> Entry {
+        Entry {
+            at,
+            actuator: Id::new(actuator),
+            command: GenericCommand::parse("HeatTo:21").unwrap(),
+            trigger,
+            readings: vec![(
+                Id::new("kitchen"),
+                Datum::new(15.0, DatumUnit::DegreesC, at),
+            )],
+            status: Ok(String::from("HTTP/1.1 200 OK")),
+            latency: Duration::from_millis(12),
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let now = Utc::now();
+        let mut log = AuditLog::default();
+
+        log.push(entry("furnace", Trigger::Rule(String::from("frost")), now));
+        log.push(entry(
+            "furnace",
+            Trigger::Strategy(String::from("house")),
+            now,
+        ));
+        log.push(entry("fan", Trigger::Manual, now));
+
+        let query = Query::parse("actuator=furnace").unwrap();
+        assert_eq!(log.query(&query).len(), 2);
+
+        let query = Query::parse("trigger=manual").unwrap();
+        assert_eq!(log.query(&query)[0].actuator, Id::new("fan"));
+
+        // the limit keeps the most recent entries
+        let query = Query::parse("limit=1").unwrap();
+        assert_eq!(log.query(&query)[0].trigger, Trigger::Manual);
+
+        let query = Query {
+            since: Some(now + chrono::Duration::seconds(1)),
+            ..Query::default()
+        };
+        assert!(log.query(&query).is_empty());
+    }
+
+    #[test]
+    fn test_serde_trigger() {
+        let triggers = [
+            Trigger::Rule(String::from("frost: night")),
+            Trigger::Strategy(String::from("house")),
+            Trigger::Manual,
+        ];
+
+        for trigger in triggers {
+            assert_eq!(Trigger::parse(&trigger.to_string()), Ok(trigger));
+        }
+
+        assert!(Trigger::parse("cron").is_err());
+        assert!(Trigger::parse("manual:me").is_err());
+    }
+
+    #[test]
+    fn test_parse_query_errors() {
+        assert!(Query::parse("trigger=cron").is_err());
+        assert!(Query::parse("limit=ten").is_err());
+        assert!(Query::parse("since=yesterday").is_err());
+        assert!(Query::parse("colour=red").is_err());
+    }
+
+    #[test]
+    fn test_log_is_bounded() {
+        let mut log = AuditLog::default();
+
+        for _ in 0..(AUDIT_LENGTH + 5) {
+            log.push(entry("furnace", Trigger::Manual, Utc::now()));
+        }
+
+        assert_eq!(log.query(&Query::default()).len(), AUDIT_LENGTH);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let log = {
+            let mut log = AuditLog::default();
+            log.push(entry(
+                "furnace",
+                Trigger::Rule(String::from("frost")),
+                Utc::now(),
+            ));
+            log.push(entry("fan", Trigger::Manual, Utc::now()));
+            log
+        };
+
+        let exported = AuditLog::to_json_lines(&log.query(&Query::default()));
+        let lines: Vec<serde_json::Value> = exported
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["trigger"], "rule:frost");
+        let reading = lines[0]["readings"]["kitchen"].as_str().unwrap();
+        assert_eq!(Datum::parse(reading).unwrap().get_as_float(), Some(15.0));
+        assert_eq!(lines[0]["status"], "HTTP/1.1 200 OK");
+        assert_eq!(lines[1]["error"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,209 @@
+use mdns_sd::ServiceInfo;
+use serde_json::json;
+
+use actuator::{GenericCommand, Schema};
+use datum::Reading;
+use device::protocol::{Versions, MIN_PROTOCOL_VERSION};
+use sensor::Measurement;
+
+/// What a device can do, as advertised in the TXT properties of its mDNS service (see
+/// `Device::register`).
+///
+/// Devices which don't advertise some capability (e.g. because they run older firmware) are
+/// treated as capable of anything, so the `Controller` polls and commands them as it always has.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Capabilities {
+    /// The kind of device, e.g. `temperature`, `climate`, or `plug`
+    pub kind: Option<String>,
+    /// The versions of the protocol the device speaks
+    pub protocol: Versions,
+    pub firmware: Option<String>,
+    /// The quantities a `Sensor` measures, on each of its channels
+    pub measurements: Vec<Measurement>,
+    /// The `Command`s an `Actuator` accepts
+    pub commands: Option<Schema>,
+}
+
+impl Capabilities {
+    /// Parses the capabilities advertised by a device, returning an error if any are malformed.
+    pub fn parse(info: &ServiceInfo) -> Result<Capabilities, String> {
+        let property = |key: &str| {
+            info.get_property_val_str(key)
+                .filter(|value| !value.is_empty())
+        };
+
+        // devices which don't advertise a protocol version predate versioning, so speak version 1
+        let protocol = match property("protocol") {
+            None => Versions::new(MIN_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION),
+            Some(versions) => Versions::parse(versions)?,
+        };
+
+        let measurements = match property("measures") {
+            None => Vec::new(),
+            Some(measures) => Measurement::parse_all(measures)?,
+        };
+
+        let commands = property("commands").map(Schema::parse).transpose()?;
+
+        Ok(Capabilities {
+            kind: property("kind").map(String::from),
+            protocol,
+            firmware: property("firmware").map(String::from),
+            measurements,
+            commands,
+        })
+    }
+
+    /// Returns the newest protocol version spoken by both the device and the `Controller`, or an
+    /// error if they have none in common.
+    pub fn negotiate(&self) -> Result<u32, String> {
+        Versions::supported().negotiate(&self.protocol)
+    }
+
+    /// Returns an error if a `Reading` from a `Sensor` is missing any of the quantities it
+    /// advertised, or if any are of the wrong type or unit.
+    pub fn check(&self, reading: &Reading) -> Result<(), String> {
+        for measurement in self.measurements.iter() {
+            let Some(datum) = reading.get(&measurement.channel) else {
+                return Err(format!("missing {}", measurement));
+            };
+
+            measurement.check(&datum)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if an `Actuator` doesn't accept the `command`, according to the `Schema`
+    /// it advertised.
+    pub fn accepts(&self, command: &GenericCommand) -> Result<(), String> {
+        match &self.commands {
+            Some(schema) => schema.validate(command),
+            None => Ok(()),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let measurements: Vec<String> = self.measurements.iter().map(|m| m.to_string()).collect();
+
+        json!({
+            "kind": self.kind,
+            "protocol": self.protocol.to_string(),
+            "negotiated": self.negotiate().ok(),
+            "firmware": self.firmware,
+            "measures": measurements,
+            "commands": self.commands.as_ref().map(|schema| schema.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod capabilities_tests {
+    use std::collections::HashMap;
+
+    use datum::{DatumUnit, DatumValueType};
+
+    use super::*;
+
+    fn info(properties: &[(&str, &str)]) -> ServiceInfo {
+        let properties: HashMap<String, String> = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        ServiceInfo::new(
+            "_sensor._tcp.local.",
+            "test",
+            "localhost.",
+            "127.0.0.1",
+            8787,
+            properties,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_sensor() {
+        let info = info(&[
+            ("id", "probe"),
+            ("kind", "climate"),
+            ("protocol", "1"),
+            ("firmware", "0.1.0"),
+            ("measures", "temperature:float@°C;co2:float@ppm"),
+        ]);
+
+        let capabilities = Capabilities::parse(&info).unwrap();
+
+        assert_eq!(capabilities.kind.as_deref(), Some("climate"));
+        assert_eq!(capabilities.protocol, Versions::new(1, 1));
+        assert_eq!(capabilities.negotiate(), Ok(1));
+        assert_eq!(capabilities.firmware.as_deref(), Some("0.1.0"));
+        assert_eq!(
+            capabilities.measurements,
+            vec![
+                Measurement::new("temperature", DatumValueType::Float, DatumUnit::DegreesC),
+                Measurement::new("co2", DatumValueType::Float, DatumUnit::PartsPerMillion),
+            ]
+        );
+        assert_eq!(capabilities.commands, None);
+
+        let reading = Reading::parse("temperature=21.5@°C;co2=612@ppm@2024-01-01T00:00:00Z");
+        assert_eq!(capabilities.check(&reading.unwrap()), Ok(()));
+
+        let reading = Reading::parse("temperature=21.5@°C@2024-01-01T00:00:00Z");
+        assert_eq!(
+            capabilities.check(&reading.unwrap()),
+            Err(String::from("missing co2:float@ppm"))
+        );
+    }
+
+    #[test]
+    fn test_parse_actuator() {
+        let info = info(&[("id", "lamp"), ("kind", "light"), ("commands", "On;Off")]);
+
+        let capabilities = Capabilities::parse(&info).unwrap();
+
+        assert_eq!(
+            capabilities.commands,
+            Some(Schema::new().command("On").command("Off"))
+        );
+        assert!(capabilities
+            .accepts(&GenericCommand::parse("On").unwrap())
+            .is_ok());
+        assert!(capabilities
+            .accepts(&GenericCommand::parse("HeatTo:21").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let negotiate = |versions: &str| {
+            let capabilities = Capabilities::parse(&info(&[("protocol", versions)])).unwrap();
+            capabilities.negotiate()
+        };
+
+        assert_eq!(negotiate("1..2"), Ok(2));
+        assert_eq!(negotiate("2..7"), Ok(3));
+        assert_eq!(
+            negotiate("4..5"),
+            Err(String::from(
+                "no common protocol version (this end speaks 1..3, the other speaks 4..5)"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_unadvertised() {
+        let capabilities = Capabilities::parse(&info(&[("id", "legacy")])).unwrap();
+
+        assert_eq!(capabilities.kind, None);
+        assert_eq!(capabilities.protocol, Versions::new(1, 1));
+        assert!(capabilities
+            .accepts(&GenericCommand::parse("HeatTo:21").unwrap())
+            .is_ok());
+
+        assert!(Capabilities::parse(&info(&[("protocol", "two")])).is_err());
+        assert!(Capabilities::parse(&info(&[("protocol", "2..1")])).is_err());
+        assert!(Capabilities::parse(&info(&[("measures", "float@kg")])).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use serde::Deserialize;
+
+use device::config::{parse_duration, validate_group, DEADBAND};
+
+use crate::api;
+
+/// The `Controller`'s settings, as given in a configuration file, CLI flags, or environment
+/// variables. Anything which isn't set takes its value from `ControllerConfig::default`.
+#[derive(Args, Deserialize, PartialEq, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ControllerArgs {
+    /// The address the HTTP API listens on [default: 127.0.0.1:6565]
+    #[arg(long, env = "CONTROLLER_API_ADDRESS")]
+    pub api_address: Option<String>,
+
+    /// How often every sensor is polled, e.g. "1s" or "500ms" [default: 1s]
+    #[arg(long, env = "CONTROLLER_POLL_INTERVAL")]
+    pub poll_interval: Option<String>,
+
+    /// How often the control loop decides which commands to send [default: 1s]
+    #[arg(long, env = "CONTROLLER_CONTROL_INTERVAL")]
+    pub control_interval: Option<String>,
+
+    /// How often each sensor may push data to the controller; pushes any sooner are rejected [default: 1s]
+    #[arg(long, env = "CONTROLLER_MIN_PUSH_INTERVAL")]
+    pub min_push_interval: Option<String>,
+
+    /// How much a value must change by before a streaming sensor sends it; every reading is sent at least once per poll interval regardless [default: 0.5]
+    #[arg(long, env = "CONTROLLER_STREAM_DEADBAND")]
+    pub stream_deadband: Option<f32>,
+
+    /// How long to wait for a device to respond [default: 5s]
+    #[arg(long, env = "CONTROLLER_TIMEOUT")]
+    pub timeout: Option<String>,
+
+    /// The mDNS group sensors are discovered in [default: _sensor]
+    #[arg(long, env = "CONTROLLER_SENSOR_GROUP")]
+    pub sensor_group: Option<String>,
+
+    /// The mDNS group actuators are discovered in [default: _actuator]
+    #[arg(long, env = "CONTROLLER_ACTUATOR_GROUP")]
+    pub actuator_group: Option<String>,
+
+    /// A file of rules; see `RuleSet`
+    #[arg(long, env = "CONTROLLER_RULES")]
+    pub rules: Option<PathBuf>,
+
+    /// A file of sensor-actuator pairings; see `Pairings`
+    #[arg(long, env = "CONTROLLER_PAIRINGS")]
+    pub pairings: Option<PathBuf>,
+
+    /// A file of setpoint schedules and control strategies for each zone; see `Schedules`
+    #[arg(long, env = "CONTROLLER_SCHEDULES")]
+    pub schedules: Option<PathBuf>,
+
+    /// A file of alerts; see `AlertConfig`
+    #[arg(long, env = "CONTROLLER_ALERTS")]
+    pub alerts: Option<PathBuf>,
+
+    /// A file to persist state to; see `Store`
+    #[arg(long, env = "CONTROLLER_STORE")]
+    pub store: Option<PathBuf>,
+}
+
+impl ControllerArgs {
+    /// Fills in anything which isn't set here from the `file`.
+    pub fn or(self, file: ControllerArgs) -> ControllerArgs {
+        ControllerArgs {
+            api_address: self.api_address.or(file.api_address),
+            poll_interval: self.poll_interval.or(file.poll_interval),
+            control_interval: self.control_interval.or(file.control_interval),
+            min_push_interval: self.min_push_interval.or(file.min_push_interval),
+            stream_deadband: self.stream_deadband.or(file.stream_deadband),
+            timeout: self.timeout.or(file.timeout),
+            sensor_group: self.sensor_group.or(file.sensor_group),
+            actuator_group: self.actuator_group.or(file.actuator_group),
+            rules: self.rules.or(file.rules),
+            pairings: self.pairings.or(file.pairings),
+            schedules: self.schedules.or(file.schedules),
+            alerts: self.alerts.or(file.alerts),
+            store: self.store.or(file.store),
+        }
+    }
+}
+
+/// The `Controller`'s settings.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ControllerConfig {
+    pub api_address: String,
+    pub poll_interval: Duration,
+    pub control_interval: Duration,
+    /// How often each `Sensor` may push data to the `Controller`; see `State::receive`
+    pub min_push_interval: Duration,
+    /// How much a value must change by before a streaming `Sensor` sends it; see `State::stream`
+    pub stream_deadband: f32,
+    pub timeout: Duration,
+    pub sensor_group: String,
+    pub actuator_group: String,
+    pub rules: Option<PathBuf>,
+    pub pairings: Option<PathBuf>,
+    pub schedules: Option<PathBuf>,
+    pub alerts: Option<PathBuf>,
+    pub store: Option<PathBuf>,
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        Self {
+            api_address: String::from(api::ADDRESS),
+            poll_interval: Duration::from_secs(1),
+            control_interval: Duration::from_secs(1),
+            min_push_interval: Duration::from_secs(1),
+            stream_deadband: DEADBAND,
+            timeout: Duration::from_secs(5),
+            sensor_group: String::from("_sensor"),
+            actuator_group: String::from("_actuator"),
+            rules: None,
+            pairings: None,
+            schedules: None,
+            alerts: None,
+            store: None,
+        }
+    }
+}
+
+impl ControllerConfig {
+    /// Validates the `args`, using the default for anything which isn't set.
+    pub fn from_args(args: ControllerArgs) -> Result<ControllerConfig, String> {
+        let mut config = ControllerConfig::default();
+        let mut errors = Vec::new();
+
+        let mut duration = |name: &str, value: Option<String>, field: &mut Duration| {
+            if let Some(value) = value {
+                match parse_duration(&value) {
+                    Ok(duration) if duration.is_zero() => {
+                        errors.push(format!("{}: must be greater than zero", name))
+                    }
+                    Ok(duration) => *field = duration,
+                    Err(msg) => errors.push(format!("{}: {}", name, msg)),
+                }
+            }
+        };
+
+        duration(
+            "poll_interval",
+            args.poll_interval,
+            &mut config.poll_interval,
+        );
+        duration(
+            "control_interval",
+            args.control_interval,
+            &mut config.control_interval,
+        );
+        duration(
+            "min_push_interval",
+            args.min_push_interval,
+            &mut config.min_push_interval,
+        );
+        duration("timeout", args.timeout, &mut config.timeout);
+
+        if let Some(deadband) = args.stream_deadband {
+            if deadband.is_nan() || deadband < 0.0 {
+                errors.push(String::from("stream_deadband: must not be negative"))
+            } else {
+                config.stream_deadband = deadband;
+            }
+        }
+
+        for (name, value, field) in [
+            ("sensor_group", args.sensor_group, &mut config.sensor_group),
+            (
+                "actuator_group",
+                args.actuator_group,
+                &mut config.actuator_group,
+            ),
+        ] {
+            if let Some(group) = value {
+                match validate_group(&group) {
+                    Ok(()) => *field = group,
+                    Err(msg) => errors.push(format!("{}: {}", name, msg)),
+                }
+            }
+        }
+
+        if config.sensor_group == config.actuator_group {
+            errors.push(String::from("sensor_group and actuator_group must differ"));
+        }
+
+        if let Some(address) = args.api_address {
+            config.api_address = address;
+        }
+
+        config.rules = args.rules;
+        config.pairings = args.pairings;
+        config.schedules = args.schedules;
+        config.alerts = args.alerts;
+        config.store = args.store;
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config = ControllerConfig::from_args(ControllerArgs::default());
+        assert_eq!(config, Ok(ControllerConfig::default()));
+    }
+
+    #[test]
+    fn test_from_file() {
+        let file: ControllerArgs = toml::from_str(
+            r#"
+            poll_interval = "500ms"
+            stream_deadband = 0.1
+            sensor_group = "_thermometer"
+            rules = "rules/frost.toml"
+            "#,
+        )
+        .unwrap();
+
+        let args = ControllerArgs {
+            poll_interval: Some(String::from("2s")),
+            ..ControllerArgs::default()
+        };
+
+        let config = ControllerConfig::from_args(args.or(file)).unwrap();
+
+        assert_eq!(config.poll_interval, Duration::from_secs(2));
+        assert_eq!(config.stream_deadband, 0.1);
+        assert_eq!(config.sensor_group, "_thermometer");
+        assert_eq!(config.rules, Some(PathBuf::from("rules/frost.toml")));
+        assert_eq!(config.timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let args = ControllerArgs {
+            poll_interval: Some(String::from("0s")),
+            timeout: Some(String::from("soon")),
+            stream_deadband: Some(-0.5),
+            actuator_group: Some(String::from("_sensor")),
+            ..ControllerArgs::default()
+        };
+
+        let errors = ControllerConfig::from_args(args).unwrap_err();
+        let lines: Vec<&str> = errors.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("poll_interval"));
+        assert!(lines[1].starts_with("timeout"));
+        assert!(lines[2].starts_with("stream_deadband"));
+        assert!(lines[3].contains("must differ"));
+    }
+}
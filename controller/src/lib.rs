@@ -1,16 +1,41 @@
-use std::time::Duration;
+use std::path::Path;
+
+use chrono::Utc;
+use tracing::info;
 
 use device::{Device, Id, Name};
 
+use crate::alert::{AlertConfig, AlertSink};
+use crate::config::ControllerConfig;
+use crate::pairing::Pairings;
+use crate::rules::RuleSet;
+use crate::schedules::Schedules;
+use crate::setpoint::SetpointSchedule;
 use crate::state::State;
+use crate::store::{Retention, Store};
+use crate::strategy::ControlStrategy;
 
+pub mod alert;
+pub mod api;
+pub mod audit;
+pub mod capabilities;
+pub mod config;
+pub mod metrics;
+pub mod pairing;
+pub mod rules;
+pub mod schedules;
+pub mod setpoint;
 mod state;
+pub mod store;
+pub mod strategy;
+pub mod stream;
 
 /// The Controller queries the `Sensor`s for `Datum`s and sends commands to the `Actuator`s.
 ///
-/// The Controller logically ties a `Sensor` to its corresponding `Actuator`. It queries the
-/// `Sensor` for its data, and makes a decision based on its state and the `Sensor` data, then
-/// constructs an appropriate command to send to that `Sensor`'s `Actuator`.
+/// The Controller logically ties `Sensor`s to their corresponding `Actuator`s (by default, the
+/// `Actuator` with the same `Id`; see `Pairings`). It queries the `Sensor`s for their data, and
+/// makes a decision based on its state and the `Sensor` data, then constructs an appropriate
+/// command to send to the `Sensor`s' `Actuator`s.
 ///
 /// The `Controller`'s state can be queried by an HTML frontend, so some historic data is held
 /// in memory.
@@ -18,6 +43,7 @@ pub struct Controller {
     name: Name,
     id: Id,
     state: State,
+    config: ControllerConfig,
 }
 
 impl Device for Controller {
@@ -36,6 +62,7 @@ impl Default for Controller {
             name: Name::new("controller"),
             id: Id::new("controller"),
             state: State::new(),
+            config: ControllerConfig::default(),
         }
     }
 }
@@ -45,61 +72,126 @@ impl Controller {
         Controller::default()
     }
 
-    /// Starts the discovery process as well as polling sensors
-    pub fn run(&mut self) {
+    /// Creates a `Controller` with the specified settings, loading the rules, pairings, schedules,
+    /// alerts, and store it names.
+    pub fn with_config(config: &ControllerConfig) -> Result<Controller, String> {
+        let controller = Controller {
+            state: State::with_config(config.clone()),
+            config: config.clone(),
+            ..Controller::default()
+        };
+
+        if let Some(path) = &config.rules {
+            controller.set_rules(RuleSet::from_file(path)?);
+            info!(path = %path.display(), "loaded rules");
+        }
+
+        if let Some(path) = &config.pairings {
+            controller.set_pairings(Pairings::from_file(path)?);
+            info!(path = %path.display(), "loaded pairings");
+        }
+
+        if let Some(path) = &config.schedules {
+            controller.set_schedules(Schedules::from_file(path)?);
+            info!(path = %path.display(), "loaded schedules");
+        }
+
+        if let Some(path) = &config.alerts {
+            controller.set_alerts(AlertConfig::from_file(path)?);
+            info!(path = %path.display(), "loaded alerts");
+        }
+
+        // the store is opened last, so that it can restore pairings changed through the API
+        if let Some(path) = &config.store {
+            controller.open_store(path, Retention::default())?;
+            info!(path = %path.display(), "persisting state");
+        }
+
+        Ok(controller)
+    }
+
+    /// Sets the `ControlStrategy` used for the `Zone` with the specified name (for implicit
+    /// sensor-actuator pairs, this is their shared `Id`).
+    ///
+    /// Zones without an explicit strategy are kept at 21°C ± 1°C by a `BangBang` strategy.
+    pub fn set_strategy(&self, id: Id, strategy: Box<dyn ControlStrategy>) {
+        self.state.set_strategy(id, strategy)
+    }
+
+    /// Sets the `SetpointSchedule` used for the `Zone` with the specified name (for implicit
+    /// sensor-actuator pairs, this is their shared `Id`).
+    pub fn set_schedule(&self, id: Id, schedule: SetpointSchedule) {
+        self.state.set_schedule(id, schedule)
+    }
+
+    /// Sets the `SetpointSchedule` and `ControlStrategy` of every `Zone` in the `schedules`.
+    pub fn set_schedules(&self, schedules: Schedules) {
+        for zone in schedules.zones {
+            if let Some(strategy) = zone.strategy {
+                self.set_strategy(zone.zone.clone(), strategy.build());
+            }
+
+            if let Some(schedule) = zone.schedule {
+                self.set_schedule(zone.zone, schedule);
+            }
+        }
+    }
+
+    /// Sets the explicit pairings of `Sensor`s and `Actuator`s. By default, a `Sensor` is paired
+    /// with the `Actuator` which has the same `Id`.
+    pub fn set_pairings(&self, pairings: Pairings) {
+        self.state.set_pairings(pairings)
+    }
+
+    /// Sets the rules which automate `Actuator`s based on the data from any `Sensor`.
+    pub fn set_rules(&self, rules: RuleSet) {
+        self.state.set_rules(rules)
+    }
+
+    /// Sets the checks which raise `Alert`s, and the webhooks they are sent to.
+    pub fn set_alerts(&self, config: AlertConfig) {
+        self.state.set_alerts(config)
+    }
+
+    /// Sends `Alert`s to the `sink`, as well as to the log, the HTTP API, and any webhooks.
+    pub fn add_alert_sink(&self, sink: Box<dyn AlertSink>) {
+        self.state.add_alert_sink(sink)
+    }
+
+    /// Persists `Sensor` data, `Actuator` commands, and configuration changes to the store at
+    /// `path`, restoring whatever state it already contains.
+    ///
+    /// Call this after `set_pairings`, so pairings changed through the API can be restored.
+    pub fn open_store<P: AsRef<Path>>(&self, path: P, retention: Retention) -> Result<(), String> {
+        let (store, snapshot) = Store::open(path, retention)?;
+        self.state.set_store(store, snapshot);
+        Ok(())
+    }
+
+    /// Starts the discovery process, polling sensors, the HTTP API, and the control loop
+    ///
+    /// Only returns if the HTTP API can't be served (e.g. its address is already in use).
+    pub fn run(&mut self) -> Result<(), String> {
         // spawn a thread to look for sensors on the network continually
         self.state.discover_sensors();
 
         // spawn a thread to look for actuators on the network continually
         self.state.discover_actuators();
 
-        // poll sensors for data in perpetuity, waiting 1s in between polls
+        // poll sensors for data in perpetuity, waiting the poll interval in between polls
         self.state.poll();
 
-        // TODO I think we need two more loops
-        //      Loop 1 should be another state-internal loop, polling all known sensors for data and saving it in the histories
-        //      Loop 2 should be in this scope right here, and it should be the "control loop".
-        //
-        //      The "control loop" should
-        //        1. get the latest Datums for each sensor
-        //        2. determine if each sensor is outside of some user-defined range
-        //        3. if so, command the sensor's corresponding actuator to perform some command
-
-        // // Cycle through and poll the Sensors, if the return Datum is outside a defined range
-        // // send a command off to the Actuator
-        // let self_api_clone = Arc::clone(&self.state);
-        //
-        // // FIXME I think this loop below needs to happen inside the State, like the discover() loop
-        // std::thread::spawn(move || loop {
-        //
-        //     // acquire a mutex lock on the state
-        //     let mut ctrl = self_api_clone.lock().unwrap();
-        //
-        //     // loop over all known sensors
-        //     for (id, )
-        //
-        //
-        //
-        //     // Create a temp vec to hold the data history as there is a lock on the controller and
-        //     // we can't populate the history until the lock is released.
-        //     let mut data_history: Vec<(Id, SensorHistory)> = Vec::new();
-        //     {
-        //         let ctrl = self_api_clone.lock().unwrap();
-        //
-        //     }
-        //
-        //     // Once we have exited the scope where we acquired the data and send commands
-        //     // its safe to acquire lock on ctrl again and update its data history
-        //     let mut ctrl = self_api_clone.lock().unwrap();
-        //     for (id, history) in data_history {
-        //         ctrl.data.insert(id, history);
-        //     }
-        //     std::thread::sleep(Duration::from_secs(5));
-        // });
-        //
-        // Ok(())
-
-        // run() should loop continually
-        std::thread::sleep(Duration::MAX)
+        // serve the HTTP API, so the state of the Controller can be queried
+        let address = &self.config.api_address;
+
+        api::serve(self.state.clone(), address)
+            .map_err(|err| format!("cannot serve the controller API on {}: {}", address, err))?;
+
+        // the control loop: react to the latest sensor data every interval, and raise any alerts
+        loop {
+            self.state.control();
+            self.state.check_alerts(Utc::now());
+            std::thread::sleep(self.config.control_interval)
+        }
     }
 }
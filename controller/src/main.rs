@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use controller::config::{ControllerArgs, ControllerConfig};
+use controller::Controller;
+use device::config::LoggingArgs;
+
+/// Discovers sensors and actuators on the network, and controls the actuators.
+#[derive(Parser)]
+struct Cli {
+    /// A TOML configuration file; flags and environment variables take precedence over it
+    #[arg(long, env = "CONTROLLER_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    controller: ControllerArgs,
+
+    #[command(flatten)]
+    logging: LoggingArgs,
+}
+
+/// The layout of the configuration file.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    controller: ControllerArgs,
+    #[serde(default)]
+    logging: LoggingArgs,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let file: File = device::config::read_file(cli.config.as_deref()).unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    });
+
+    if let Err(msg) = cli.logging.or(file.logging).init() {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    }
+
+    let config = ControllerConfig::from_args(cli.controller.or(file.controller))
+        .unwrap_or_else(|msg| exit(&msg));
+
+    let mut controller = Controller::with_config(&config).unwrap_or_else(|msg| exit(&msg));
+
+    info!(address = config.api_address, "serving controller API");
+    if let Err(msg) = controller.run() {
+        exit(&msg)
+    }
+}
+
+fn exit(msg: &str) -> ! {
+    error!("{}", msg);
+    std::process::exit(1)
+}
@@ -0,0 +1,366 @@
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use datum::{DatumValue, Reading};
+use device::Id;
+
+/// The `Controller`'s Prometheus metrics, exported in the text exposition format.
+///
+/// Each `Metrics` has its own `Registry`, so that more than one `Controller` can run in the
+/// same process (e.g. in tests) without their metrics colliding.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    /// The latest value from each `Sensor`
+    sensor_values: GaugeVec,
+    /// The number of times each `Sensor` was polled, by outcome ("success" or "failure")
+    polls: IntCounterVec,
+    /// How long each `Sensor` took to respond to a poll
+    poll_latency: HistogramVec,
+    /// The number of `Reading`s each `Sensor` pushed, by outcome ("success" or "failure")
+    pushes: IntCounterVec,
+    /// The number of `Reading`s each `Sensor` streamed, by outcome ("success" or "failure")
+    streamed: IntCounterVec,
+    /// The number of devices discovered in each mDNS group
+    devices: IntGaugeVec,
+    /// The number of `Command`s sent to each `Actuator`, by outcome ("success" or "failure")
+    commands: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let sensor_values = GaugeVec::new(
+            Opts::new("sensor_value", "The latest value read from each sensor"),
+            &["id", "name", "unit"],
+        )
+        .unwrap();
+
+        let polls = IntCounterVec::new(
+            Opts::new("sensor_polls_total", "Sensor polls, by outcome"),
+            &["id", "outcome"],
+        )
+        .unwrap();
+
+        let poll_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "sensor_poll_duration_seconds",
+                "How long sensors take to respond to polls",
+            ),
+            &["id"],
+        )
+        .unwrap();
+
+        let pushes = IntCounterVec::new(
+            Opts::new(
+                "sensor_pushes_total",
+                "Readings pushed by sensors, by outcome",
+            ),
+            &["id", "outcome"],
+        )
+        .unwrap();
+
+        let streamed = IntCounterVec::new(
+            Opts::new(
+                "sensor_streamed_readings_total",
+                "Readings streamed by sensors, by outcome",
+            ),
+            &["id", "outcome"],
+        )
+        .unwrap();
+
+        let devices = IntGaugeVec::new(
+            Opts::new(
+                "discovered_devices",
+                "Devices discovered via mDNS, by group",
+            ),
+            &["group"],
+        )
+        .unwrap();
+
+        let commands = IntCounterVec::new(
+            Opts::new(
+                "actuator_commands_total",
+                "Commands sent to actuators, by outcome",
+            ),
+            &["id", "outcome"],
+        )
+        .unwrap();
+
+        let registry = Registry::new_custom(Some(String::from("controller")), None).unwrap();
+
+        registry.register(Box::new(sensor_values.clone())).unwrap();
+        registry.register(Box::new(polls.clone())).unwrap();
+        registry.register(Box::new(poll_latency.clone())).unwrap();
+        registry.register(Box::new(pushes.clone())).unwrap();
+        registry.register(Box::new(streamed.clone())).unwrap();
+        registry.register(Box::new(devices.clone())).unwrap();
+        registry.register(Box::new(commands.clone())).unwrap();
+
+        Self {
+            registry,
+            sensor_values,
+            polls,
+            poll_latency,
+            pushes,
+            streamed,
+            devices,
+            commands,
+        }
+    }
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Records a poll of the `Sensor` with the specified `Id` and `name`, which took `latency`.
+    pub fn record_poll(
+        &self,
+        id: &Id,
+        name: &str,
+        result: &Result<Reading, String>,
+        latency: Duration,
+    ) {
+        let label = id.to_string();
+
+        self.polls
+            .with_label_values(&[&label, outcome(result.is_ok())])
+            .inc();
+
+        self.poll_latency
+            .with_label_values(&[&label])
+            .observe(latency.as_secs_f64());
+
+        if let Ok(reading) = result {
+            self.record_values(id, name, reading)
+        }
+    }
+
+    /// Records a `Reading` pushed by the `Sensor` with the specified `Id` and `name`, or `None` if
+    /// the push was rejected (see `State::receive`).
+    pub fn record_push(&self, id: &Id, name: &str, reading: Option<&Reading>) {
+        self.pushes
+            .with_label_values(&[&id.to_string(), outcome(reading.is_some())])
+            .inc();
+
+        if let Some(reading) = reading {
+            self.record_values(id, name, reading)
+        }
+    }
+
+    /// Records a `Reading` (or error) streamed by the `Sensor` with the specified `Id` and `name`.
+    pub fn record_streamed(&self, id: &Id, name: &str, result: &Result<Reading, String>) {
+        self.streamed
+            .with_label_values(&[&id.to_string(), outcome(result.is_ok())])
+            .inc();
+
+        if let Ok(reading) = result {
+            self.record_values(id, name, reading)
+        }
+    }
+
+    /// Records the value of each channel of a `Reading`, labelled with the `Id` of its series; see
+    /// `Id::channel`.
+    fn record_values(&self, id: &Id, name: &str, reading: &Reading) {
+        for channel in reading.channels.iter() {
+            let value = match channel.value {
+                DatumValue::Float(value) => value as f64,
+                DatumValue::Int(value) => value as f64,
+                DatumValue::Bool(value) => value as i32 as f64,
+            };
+
+            let series = id.channel(&channel.name).to_string();
+            let unit = channel.unit.to_string();
+            let labels = [series.as_str(), name, unit.as_str()];
+            self.sensor_values.with_label_values(&labels).set(value);
+        }
+    }
+
+    /// Records the number of devices discovered in the specified mDNS `group`.
+    pub fn record_devices(&self, group: &str, count: usize) {
+        let group = group.trim_start_matches('_');
+        self.devices.with_label_values(&[group]).set(count as i64);
+    }
+
+    /// Records a `Command` sent to the `Actuator` with the specified `Id`.
+    pub fn record_command(&self, id: &Id, success: bool) {
+        let id = id.to_string();
+        self.commands
+            .with_label_values(&[&id, outcome(success)])
+            .inc();
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+
+        String::from_utf8(buffer).unwrap()
+    }
+
+    /// The content type of `Metrics::render`.
+    pub fn content_type() -> &'static str {
+        prometheus::TEXT_FORMAT
+    }
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use chrono::Utc;
+    use datum::{Datum, DatumUnit};
+
+    use super::*;
+
+    #[test]
+    fn test_sensor_value() {
+        let metrics = Metrics::new();
+        let datum = Datum::new_now(21.5, DatumUnit::DegreesC);
+
+        metrics.record_poll(
+            &Id::new("k1"),
+            "Kitchen",
+            &Ok(datum.into()),
+            Duration::from_millis(3),
+        );
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("# TYPE controller_sensor_value gauge"));
+        assert!(
+            rendered.contains(r#"controller_sensor_value{id="k1",name="Kitchen",unit="°C"} 21.5"#)
+        );
+    }
+
+    #[test]
+    fn test_sensor_value_channels() {
+        let metrics = Metrics::new();
+        let reading = Reading::new(Utc::now())
+            .with("temperature", 18.0, DatumUnit::DegreesC)
+            .and_then(|r| r.with("co2", 640, DatumUnit::PartsPerMillion))
+            .unwrap();
+
+        metrics.record_poll(
+            &Id::new("probe"),
+            "Greenhouse",
+            &Ok(reading),
+            Duration::from_millis(3),
+        );
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(
+            r#"controller_sensor_value{id="probe:temperature",name="Greenhouse",unit="°C"} 18"#
+        ));
+        assert!(rendered.contains(
+            r#"controller_sensor_value{id="probe:co2",name="Greenhouse",unit="ppm"} 640"#
+        ));
+        assert!(
+            rendered.contains(r#"controller_sensor_polls_total{id="probe",outcome="success"} 1"#)
+        );
+    }
+
+    #[test]
+    fn test_poll_counters_and_latency() {
+        let metrics = Metrics::new();
+        let id = Id::new("k1");
+        let datum = Datum::new_now(true, DatumUnit::Unitless);
+
+        metrics.record_poll(&id, "Kitchen", &Ok(datum.into()), Duration::from_millis(3));
+        metrics.record_poll(
+            &id,
+            "Kitchen",
+            &Err(String::from("refused")),
+            Duration::from_secs(2),
+        );
+        metrics.record_poll(
+            &id,
+            "Kitchen",
+            &Err(String::from("refused")),
+            Duration::from_secs(2),
+        );
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(r#"controller_sensor_polls_total{id="k1",outcome="success"} 1"#));
+        assert!(rendered.contains(r#"controller_sensor_polls_total{id="k1",outcome="failure"} 2"#));
+        assert!(rendered.contains("# TYPE controller_sensor_poll_duration_seconds histogram"));
+        assert!(rendered
+            .contains(r#"controller_sensor_poll_duration_seconds_bucket{id="k1",le="0.005"} 1"#));
+        assert!(rendered.contains(r#"controller_sensor_poll_duration_seconds_count{id="k1"} 3"#));
+    }
+
+    #[test]
+    fn test_pushes() {
+        let metrics = Metrics::new();
+        let id = Id::new("k1");
+        let reading = Datum::new_now(19.5, DatumUnit::DegreesC).into();
+
+        metrics.record_push(&id, "Kitchen", Some(&reading));
+        metrics.record_push(&id, "Kitchen", None);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(r#"controller_sensor_pushes_total{id="k1",outcome="success"} 1"#));
+        assert!(rendered.contains(r#"controller_sensor_pushes_total{id="k1",outcome="failure"} 1"#));
+        assert!(
+            rendered.contains(r#"controller_sensor_value{id="k1",name="Kitchen",unit="°C"} 19.5"#)
+        );
+    }
+
+    #[test]
+    fn test_streamed() {
+        let metrics = Metrics::new();
+        let id = Id::new("k1");
+        let reading = Datum::new_now(19.5, DatumUnit::DegreesC).into();
+
+        metrics.record_streamed(&id, "Kitchen", &Ok(reading));
+        metrics.record_streamed(&id, "Kitchen", &Err(String::from("unreachable")));
+
+        let rendered = metrics.render();
+        assert!(rendered
+            .contains(r#"controller_sensor_streamed_readings_total{id="k1",outcome="success"} 1"#));
+        assert!(rendered
+            .contains(r#"controller_sensor_streamed_readings_total{id="k1",outcome="failure"} 1"#));
+    }
+
+    #[test]
+    fn test_devices_and_commands() {
+        let metrics = Metrics::new();
+        let id = Id::new("furnace");
+
+        metrics.record_devices("_actuator", 2);
+        metrics.record_command(&id, true);
+        metrics.record_command(&id, false);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(r#"controller_discovered_devices{group="actuator"} 2"#));
+        assert!(rendered
+            .contains(r#"controller_actuator_commands_total{id="furnace",outcome="success"} 1"#));
+        assert!(rendered
+            .contains(r#"controller_actuator_commands_total{id="furnace",outcome="failure"} 1"#));
+    }
+
+    #[test]
+    /// Each `Metrics` has its own registry
+    fn test_independent_registries() {
+        let first = Metrics::new();
+        let second = Metrics::new();
+
+        first.record_devices("_sensor", 1);
+
+        assert!(first.render().contains("controller_discovered_devices"));
+        assert!(!second.render().contains("controller_discovered_devices"));
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use datum::{Datum, DatumValue};
+use device::Id;
+
+/// How the data from the `Sensor`s in a `Zone` are combined into a single value.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Aggregate {
+    #[default]
+    Mean,
+    Min,
+    Max,
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregate::Mean => write!(f, "mean"),
+            Aggregate::Min => write!(f, "min"),
+            Aggregate::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl Aggregate {
+    pub fn parse(string: &str) -> Result<Aggregate, String> {
+        match string {
+            "mean" => Ok(Aggregate::Mean),
+            "min" => Ok(Aggregate::Min),
+            "max" => Ok(Aggregate::Max),
+            _ => Err(format!(
+                "cannot parse '{}' as an aggregate (expected mean, min, or max)",
+                string
+            )),
+        }
+    }
+
+    fn apply(&self, values: &[f32]) -> f32 {
+        match self {
+            Aggregate::Mean => values.iter().sum::<f32>() / values.len() as f32,
+            Aggregate::Min => values.iter().copied().fold(f32::INFINITY, f32::min),
+            Aggregate::Max => values.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+}
+
+/// A `Zone` is a named group of `Sensor`s which together drive a group of `Actuator`s, e.g. the
+/// average of three thermometers drives one furnace.
+///
+/// The `ControlStrategy` and `SetpointSchedule` of a `Zone` are keyed by its name.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Zone {
+    pub name: String,
+    pub sensors: Vec<Id>,
+    pub actuators: Vec<Id>,
+    pub aggregate: Aggregate,
+}
+
+impl Zone {
+    /// The implicit `Zone` of a `Sensor` and an `Actuator` which share the same `Id`.
+    fn implicit(id: &Id) -> Zone {
+        Zone {
+            name: id.to_string(),
+            sensors: vec![id.clone()],
+            actuators: vec![id.clone()],
+            aggregate: Aggregate::default(),
+        }
+    }
+
+    pub fn key(&self) -> Id {
+        Id::new(&self.name)
+    }
+
+    /// Combines the `histories` of this `Zone`'s `Sensor`s into a single history, oldest first.
+    ///
+    /// The most recent `Datum`s of each history are combined, then the second-most recent, etc.
+    /// The combined history is as long as the shortest history. Histories of non-numeric data are
+    /// ignored.
+    pub fn aggregate(&self, histories: &[&[Datum]]) -> Vec<Datum> {
+        let histories: Vec<&[Datum]> = histories
+            .iter()
+            .filter(|history| {
+                history
+                    .iter()
+                    .all(|datum| !matches!(datum.value, DatumValue::Bool(_)))
+            })
+            .copied()
+            .collect();
+
+        let Some(length) = histories.iter().map(|history| history.len()).min() else {
+            return Vec::new();
+        };
+
+        (0..length)
+            .map(|age| {
+                let data: Vec<&Datum> = histories
+                    .iter()
+                    .map(|history| &history[history.len() - length + age])
+                    .collect();
+
+                let values: Vec<f32> = data
+                    .iter()
+                    .map(|datum| match datum.value {
+                        DatumValue::Float(value) => value,
+                        DatumValue::Int(value) => value as f32,
+                        DatumValue::Bool(_) => unreachable!("bools were filtered out above"),
+                    })
+                    .collect();
+
+                let timestamp = data.iter().map(|datum| datum.timestamp).max().unwrap();
+
+                Datum::new(self.aggregate.apply(&values), data[0].unit, timestamp)
+            })
+            .collect()
+    }
+}
+
+/// Explicit pairings of `Sensor`s and `Actuator`s, for example
+///
+/// ```toml
+/// [[zones]]
+/// name = "living room"
+/// sensors = ["thermometer-1", "thermometer-2", "thermometer-3"]
+/// actuators = ["furnace"]
+/// aggregate = "mean"
+/// ```
+///
+/// Any `Actuator` which is not in an explicit `Zone` is paired with the `Sensor` which has the
+/// same `Id`, if there is one.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Pairings {
+    zones: Vec<Zone>,
+    /// The file these `Pairings` were read from, and are saved to when they change.
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RawPairings {
+    #[serde(default)]
+    zones: Vec<RawZone>,
+}
+
+/// The definition of a `Zone`, as it appears in a configuration file or an API request.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawZone {
+    #[serde(default)]
+    name: String,
+    sensors: Vec<String>,
+    actuators: Vec<String>,
+    #[serde(default)]
+    aggregate: Option<String>,
+}
+
+impl RawZone {
+    fn validate(&self) -> Result<Zone, String> {
+        if self.name.trim().is_empty() {
+            return Err(String::from("'name' is required"));
+        }
+
+        if self.sensors.is_empty() {
+            return Err(String::from("at least one sensor is required"));
+        }
+
+        if self.actuators.is_empty() {
+            return Err(String::from("at least one actuator is required"));
+        }
+
+        let aggregate = match &self.aggregate {
+            Some(aggregate) => Aggregate::parse(aggregate)?,
+            None => Aggregate::default(),
+        };
+
+        Ok(Zone {
+            name: self.name.clone(),
+            sensors: self.sensors.iter().map(|id| Id::new(id)).collect(),
+            actuators: self.actuators.iter().map(|id| Id::new(id)).collect(),
+            aggregate,
+        })
+    }
+}
+
+impl From<&Zone> for RawZone {
+    fn from(zone: &Zone) -> Self {
+        RawZone {
+            name: zone.name.clone(),
+            sensors: zone.sensors.iter().map(|id| id.to_string()).collect(),
+            actuators: zone.actuators.iter().map(|id| id.to_string()).collect(),
+            aggregate: Some(zone.aggregate.to_string()),
+        }
+    }
+}
+
+impl Pairings {
+    /// Reads `Pairings` from a TOML file. Changes made through the API are saved to the same file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Pairings, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read pairings {}: {}", path.display(), err))?;
+
+        let mut pairings = Pairings::parse_toml(&contents)
+            .map_err(|msg| format!("invalid pairings {}:\n{}", path.display(), msg))?;
+
+        pairings.path = Some(path.to_path_buf());
+        Ok(pairings)
+    }
+
+    /// Parses TOML `Pairings`, collecting all errors (one per line) rather than stopping at the
+    /// first one.
+    pub fn parse_toml(string: &str) -> Result<Pairings, String> {
+        let raw: RawPairings = toml::from_str(string).map_err(|err| err.to_string())?;
+
+        let mut errors = Vec::new();
+        let mut pairings = Pairings::default();
+
+        for (index, zone) in raw.zones.iter().enumerate() {
+            if pairings.get(&zone.name).is_some() {
+                errors.push(format!(
+                    "zones[{}]: zone '{}' is defined more than once",
+                    index, zone.name
+                ));
+                continue;
+            }
+
+            let result = zone
+                .validate()
+                .and_then(|zone| pairings.check(&zone).map(|_| zone));
+
+            match result {
+                Ok(zone) => pairings.zones.push(zone),
+                Err(msg) => errors.push(format!("zones[{}]: {}", index, msg)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(pairings)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        let raw = RawPairings {
+            zones: self.zones.iter().map(RawZone::from).collect(),
+        };
+
+        toml::to_string(&raw).unwrap_or_default()
+    }
+
+    /// Checks that a new `zone` doesn't conflict with any other (differently-named) `Zone`.
+    fn check(&self, zone: &Zone) -> Result<(), String> {
+        for other in self.zones.iter().filter(|other| other.name != zone.name) {
+            if let Some(id) = zone
+                .actuators
+                .iter()
+                .find(|id| other.actuators.contains(id))
+            {
+                return Err(format!(
+                    "actuator '{}' is already in zone '{}'",
+                    id, other.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.name == name)
+    }
+
+    /// Adds a `Zone`, or replaces the `Zone` with the same name.
+    ///
+    /// If the change can't be saved, these `Pairings` are left unchanged.
+    pub fn insert(&mut self, zone: Zone) -> Result<(), String> {
+        self.check(&zone)?;
+
+        let mut updated = self.clone();
+
+        match updated
+            .zones
+            .iter_mut()
+            .find(|other| other.name == zone.name)
+        {
+            Some(other) => *other = zone,
+            None => updated.zones.push(zone),
+        }
+
+        updated.save()?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Parses a JSON definition of a `Zone` (for the API), then adds or replaces it.
+    pub fn insert_json(&mut self, name: &str, json: &str) -> Result<(), String> {
+        let mut raw: RawZone = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        raw.name = String::from(name);
+        self.insert(raw.validate()?)
+    }
+
+    /// Removes the `Zone` with the specified name, returning `true` if there was one.
+    ///
+    /// If the change can't be saved, these `Pairings` are left unchanged.
+    pub fn remove(&mut self, name: &str) -> Result<bool, String> {
+        if self.get(name).is_none() {
+            return Ok(false);
+        }
+
+        let mut updated = self.clone();
+        updated.zones.retain(|zone| zone.name != name);
+
+        updated.save()?;
+        *self = updated;
+        Ok(true)
+    }
+
+    /// Returns the file these `Pairings` were read from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Saves these `Pairings` to the file they were read from, if any.
+    fn save(&self) -> Result<(), String> {
+        match &self.path {
+            Some(path) => std::fs::write(path, self.to_toml())
+                .map_err(|err| format!("cannot save pairings {}: {}", path.display(), err)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns every explicit `Zone`, plus an implicit `Zone` for each of the `actuators` which is
+    /// not in an explicit `Zone`.
+    pub fn resolve(&self, actuators: &[Id]) -> Vec<Zone> {
+        let paired: HashSet<&Id> = self
+            .zones
+            .iter()
+            .flat_map(|zone| zone.actuators.iter())
+            .collect();
+
+        let implicit = actuators
+            .iter()
+            .filter(|id| !paired.contains(id))
+            .map(Zone::implicit);
+
+        self.zones.iter().cloned().chain(implicit).collect()
+    }
+}
+
+#[cfg(test)]
+mod pairing_tests {
+    use chrono::{Duration, Utc};
+
+    use datum::DatumUnit;
+
+    use super::*;
+
+    const PAIRINGS: &str = r#"
+        [[zones]]
+        name = "living room"
+        sensors = ["thermometer-1", "thermometer-2", "thermometer-3"]
+        actuators = ["furnace"]
+
+        [[zones]]
+        name = "upstairs"
+        sensors = ["thermometer-4"]
+        actuators = ["radiator-1", "radiator-2"]
+        aggregate = "min"
+    "#;
+
+    fn ids(ids: &[&str]) -> Vec<Id> {
+        ids.iter().map(|id| Id::new(id)).collect()
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let pairings = Pairings::parse_toml(include_str!("../pairings.toml")).unwrap();
+        assert_eq!(pairings.zones().len(), 2);
+    }
+
+    #[test]
+    fn test_parse() {
+        let pairings = Pairings::parse_toml(PAIRINGS).unwrap();
+
+        let living_room = pairings.get("living room").unwrap();
+        assert_eq!(living_room.sensors.len(), 3);
+        assert_eq!(living_room.aggregate, Aggregate::Mean);
+
+        let upstairs = pairings.get("upstairs").unwrap();
+        assert_eq!(upstairs.actuators, ids(&["radiator-1", "radiator-2"]));
+        assert_eq!(upstairs.aggregate, Aggregate::Min);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pairings = Pairings::parse_toml(PAIRINGS).unwrap();
+        let reparsed = Pairings::parse_toml(&pairings.to_toml()).unwrap();
+
+        assert_eq!(reparsed, pairings);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let result = Pairings::parse_toml(
+            r#"
+            [[zones]]
+            name = "a"
+            sensors = ["s"]
+            actuators = ["furnace"]
+
+            [[zones]]
+            name = "b"
+            sensors = ["s"]
+            actuators = ["furnace"]
+
+            [[zones]]
+            name = "c"
+            sensors = []
+            actuators = ["radiator"]
+
+            [[zones]]
+            name = "d"
+            sensors = ["s"]
+            actuators = ["fan"]
+            aggregate = "median"
+
+            [[zones]]
+            name = "a"
+            sensors = ["s"]
+            actuators = ["heater"]
+            "#,
+        );
+
+        let expected = [
+            "zones[1]: actuator 'furnace' is already in zone 'a'",
+            "zones[2]: at least one sensor is required",
+            "zones[3]: cannot parse 'median' as an aggregate (expected mean, min, or max)",
+            "zones[4]: zone 'a' is defined more than once",
+        ];
+
+        assert_eq!(result, Err(expected.join("\n")));
+    }
+
+    #[test]
+    /// Actuators which aren't in an explicit zone are paired with the sensor with the same Id
+    fn test_resolve() {
+        let pairings = Pairings::parse_toml(PAIRINGS).unwrap();
+        let zones = pairings.resolve(&ids(&["furnace", "radiator-1", "kitchen"]));
+
+        let names: Vec<&str> = zones.iter().map(|zone| zone.name.as_str()).collect();
+        assert_eq!(names, vec!["living room", "upstairs", "kitchen"]);
+
+        let kitchen = &zones[2];
+        assert_eq!(kitchen.sensors, ids(&["kitchen"]));
+        assert_eq!(kitchen.actuators, ids(&["kitchen"]));
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut pairings = Pairings::parse_toml(PAIRINGS).unwrap();
+
+        let json =
+            r#"{"sensors": ["thermometer-1"], "actuators": ["furnace"], "aggregate": "max"}"#;
+        pairings.insert_json("living room", json).unwrap();
+        assert_eq!(
+            pairings.get("living room").unwrap().aggregate,
+            Aggregate::Max
+        );
+
+        let json = r#"{"sensors": ["thermometer-1"], "actuators": ["furnace"]}"#;
+        assert_eq!(
+            pairings.insert_json("den", json),
+            Err(String::from(
+                "actuator 'furnace' is already in zone 'living room'"
+            ))
+        );
+
+        assert_eq!(pairings.remove("living room"), Ok(true));
+        assert_eq!(pairings.remove("living room"), Ok(false));
+        assert!(pairings.insert_json("den", json).is_ok());
+    }
+
+    #[test]
+    /// Changes which can't be saved aren't applied either
+    fn test_insert_and_remove_unsaved() {
+        let mut pairings = Pairings::parse_toml(PAIRINGS).unwrap();
+        pairings.path = Some(PathBuf::from("/nonexistent/pairings.toml"));
+        let before = pairings.clone();
+
+        let json = r#"{"sensors": ["thermometer-9"], "actuators": ["heater-9"]}"#;
+        let result = pairings.insert_json("den", json);
+        assert!(result.unwrap_err().starts_with("cannot save pairings"));
+
+        let result = pairings.remove("living room");
+        assert!(result.unwrap_err().starts_with("cannot save pairings"));
+
+        assert_eq!(pairings, before);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let zone = Zone {
+            aggregate: Aggregate::Mean,
+            ..Zone::implicit(&Id::new("zone"))
+        };
+
+        let now = Utc::now();
+        let datum = |value: f32, age: i64| {
+            Datum::new(value, DatumUnit::DegreesC, now - Duration::seconds(age))
+        };
+
+        let first = [datum(18.0, 2), datum(19.0, 1), datum(20.0, 0)];
+        let second = [datum(22.0, 1), datum(23.0, 0)];
+        let door = [Datum::new(true, DatumUnit::PoweredOn, now)];
+
+        let history = zone.aggregate(&[&first, &second, &door]);
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].get_as_float(), Some(20.5));
+        assert_eq!(history[1].get_as_float(), Some(21.5));
+        assert_eq!(history[1].timestamp, now);
+        assert_eq!(history[1].unit, DatumUnit::DegreesC);
+
+        let min = Zone {
+            aggregate: Aggregate::Min,
+            ..zone.clone()
+        };
+        assert_eq!(
+            min.aggregate(&[&first, &second])[1].get_as_float(),
+            Some(20.0)
+        );
+        assert!(zone.aggregate(&[]).is_empty());
+    }
+}
@@ -0,0 +1,655 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use actuator::GenericCommand;
+use datum::{Datum, DatumValue};
+use device::Id;
+
+/// A `RuleSet` automates actuators based on the data from any number of sensors, for example
+///
+/// ```toml
+/// [groups]
+/// bedroom = ["bedroom-1", "bedroom-2"]
+///
+/// [[rules]]
+/// name = "cool the bedrooms when it's hot and the door is open"
+/// when = ["any bedroom > 26", "back_door == true"]
+/// command = "CoolTo:22"
+/// targets = ["bedroom"]
+/// priority = 10
+///
+/// [[rules]]
+/// name = "frost protection"
+/// when = ["outdoor < 0"]
+/// adjust = 1.0
+/// targets = ["all"]
+/// ```
+///
+/// Conditions and targets refer to `Id`s, or to groups of `Id`s; the target "all" refers to every
+/// known `Actuator`. A rule fires when all of its conditions hold for the latest `Datum` of each
+/// `Id`. A rule either sends a `command` to its targets, or `adjust`s their setpoints.
+///
+/// When more than one rule sends a command to the same `Actuator`, the rule with the highest
+/// `priority` wins (or, if priorities are equal, the rule defined first). Setpoint adjustments
+/// from all rules which fire are added together.
+#[derive(PartialEq, Debug, Default)]
+pub struct RuleSet {
+    groups: HashMap<String, Vec<Id>>,
+    rules: Vec<Rule>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub priority: i32,
+    pub conditions: Vec<Condition>,
+    pub action: Action,
+    pub targets: Vec<String>,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Action {
+    /// Send this `Command` to every target.
+    Command(GenericCommand),
+    /// Add this amount to the setpoint of every target.
+    Adjust(f32),
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Quantifier {
+    /// At least one `Id` in the group must satisfy the comparison
+    Any,
+    /// Every `Id` in the group must satisfy the comparison
+    All,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Comparison {
+    pub fn parse(string: &str) -> Result<Comparison, String> {
+        match string {
+            "<" => Ok(Comparison::Lt),
+            "<=" => Ok(Comparison::Le),
+            ">" => Ok(Comparison::Gt),
+            ">=" => Ok(Comparison::Ge),
+            "==" => Ok(Comparison::Eq),
+            "!=" => Ok(Comparison::Ne),
+            _ => Err(format!("cannot parse '{}' as a comparison", string)),
+        }
+    }
+
+    /// Compares `left` to `right`. Numbers can be compared to numbers, and bools can be tested for
+    /// (in)equality; any other comparison is `false`.
+    fn holds(&self, left: &DatumValue, right: &DatumValue) -> bool {
+        let ordering = match (left, right) {
+            (DatumValue::Bool(left), DatumValue::Bool(right)) => {
+                return match self {
+                    Comparison::Eq => left == right,
+                    Comparison::Ne => left != right,
+                    _ => false,
+                }
+            }
+            (DatumValue::Bool(_), _) | (_, DatumValue::Bool(_)) => return false,
+            (left, right) => as_f64(left).partial_cmp(&as_f64(right)),
+        };
+
+        let Some(ordering) = ordering else {
+            // NaN is not comparable to anything
+            return false;
+        };
+
+        match self {
+            Comparison::Lt => ordering.is_lt(),
+            Comparison::Le => ordering.is_le(),
+            Comparison::Gt => ordering.is_gt(),
+            Comparison::Ge => ordering.is_ge(),
+            Comparison::Eq => ordering.is_eq(),
+            Comparison::Ne => ordering.is_ne(),
+        }
+    }
+}
+
+fn as_f64(value: &DatumValue) -> f64 {
+    match value {
+        DatumValue::Float(value) => *value as f64,
+        DatumValue::Int(value) => *value as f64,
+        DatumValue::Bool(value) => *value as i32 as f64,
+    }
+}
+
+/// A condition like "any bedroom > 26", "all bedroom > 26", or "back_door == true".
+///
+/// The quantifier defaults to "any", and only matters when the selector names a group.
+#[derive(PartialEq, Debug)]
+pub struct Condition {
+    pub quantifier: Quantifier,
+    pub selector: String,
+    pub comparison: Comparison,
+    pub value: DatumValue,
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let quantifier = match self.quantifier {
+            Quantifier::Any => "any",
+            Quantifier::All => "all",
+        };
+
+        write!(
+            f,
+            "{} {} {} {}",
+            quantifier, self.selector, self.comparison, self.value
+        )
+    }
+}
+
+impl Condition {
+    pub fn parse(string: &str) -> Result<Condition, String> {
+        let pieces: Vec<&str> = string.split_whitespace().collect();
+
+        let (quantifier, rest) = match pieces.as_slice() {
+            ["any", rest @ ..] => (Quantifier::Any, rest),
+            ["all", rest @ ..] => (Quantifier::All, rest),
+            rest => (Quantifier::Any, rest),
+        };
+
+        match rest {
+            [selector, comparison, value] => Ok(Condition {
+                quantifier,
+                selector: selector.to_string(),
+                comparison: Comparison::parse(comparison)?,
+                value: DatumValue::parse(value.to_string())?,
+            }),
+            _ => Err(format!(
+                "cannot parse '{}' as a condition, expected e.g. 'any bedroom > 26'",
+                string
+            )),
+        }
+    }
+
+    /// Returns `true` if this condition holds for the `latest` data. An `Id` with no data never
+    /// satisfies a condition.
+    fn holds(&self, members: &[Id], latest: &HashMap<Id, Datum>) -> bool {
+        let mut values = members
+            .iter()
+            .map(|id| latest.get(id).map(|datum| &datum.value));
+
+        let satisfied = |value: Option<&DatumValue>| {
+            value.is_some_and(|v| self.comparison.holds(v, &self.value))
+        };
+
+        match self.quantifier {
+            Quantifier::Any => values.any(satisfied),
+            Quantifier::All => !members.is_empty() && values.all(satisfied),
+        }
+    }
+}
+
+/// What the `RuleSet` wants done, as of the latest data.
+#[derive(PartialEq, Debug, Default)]
+pub struct Outcome {
+    /// The `Command` to send to each `Actuator`, and the name of the rule which sent it
+    pub commands: HashMap<Id, (GenericCommand, String)>,
+    /// The total adjustment to the setpoint of each sensor-actuator pair
+    pub adjustments: HashMap<Id, f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRuleSet {
+    #[serde(default)]
+    groups: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: String,
+    #[serde(default)]
+    priority: i32,
+    when: Vec<String>,
+    command: Option<String>,
+    adjust: Option<f32>,
+    targets: Vec<String>,
+}
+
+impl RawRule {
+    fn validate(&self) -> Result<Rule, String> {
+        if self.when.is_empty() {
+            return Err(String::from("at least one condition is required in 'when'"));
+        }
+
+        if self.targets.is_empty() {
+            return Err(String::from("at least one target is required in 'targets'"));
+        }
+
+        let conditions = self
+            .when
+            .iter()
+            .map(|condition| Condition::parse(condition))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let action = match (&self.command, self.adjust) {
+            (Some(command), None) => Action::Command(GenericCommand::parse(command)?),
+            (None, Some(adjust)) => Action::Adjust(adjust),
+            (Some(_), Some(_)) => {
+                return Err(String::from(
+                    "specify either 'command' or 'adjust', not both",
+                ))
+            }
+            (None, None) => return Err(String::from("one of 'command' or 'adjust' is required")),
+        };
+
+        Ok(Rule {
+            name: self.name.clone(),
+            priority: self.priority,
+            conditions,
+            action,
+            targets: self.targets.clone(),
+        })
+    }
+}
+
+impl RuleSet {
+    /// Reads a `RuleSet` from a TOML file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<RuleSet, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read rules {}: {}", path.display(), err))?;
+
+        RuleSet::parse_toml(&contents)
+            .map_err(|msg| format!("invalid rules {}:\n{}", path.display(), msg))
+    }
+
+    /// Parses a TOML `RuleSet`, collecting all errors (one per line) rather than stopping at the
+    /// first one.
+    pub fn parse_toml(string: &str) -> Result<RuleSet, String> {
+        let raw: RawRuleSet = toml::from_str(string).map_err(|err| err.to_string())?;
+
+        let mut errors = Vec::new();
+        let mut rule_set = RuleSet::default();
+
+        for (name, members) in raw.groups {
+            if name == "all" {
+                errors.push(String::from("groups: 'all' is reserved for every Actuator"));
+            }
+
+            let members = members.iter().map(|id| Id::new(id)).collect();
+            rule_set.groups.insert(name, members);
+        }
+
+        let mut names = HashSet::new();
+
+        for (index, rule) in raw.rules.iter().enumerate() {
+            if !names.insert(rule.name.as_str()) {
+                errors.push(format!(
+                    "rules[{}]: rule '{}' is defined more than once",
+                    index, rule.name
+                ));
+            }
+
+            match rule.validate() {
+                Ok(rule) => rule_set.rules.push(rule),
+                Err(msg) => errors.push(format!("rules[{}]: {}", index, msg)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(rule_set)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Returns the `Id`s referred to by a group name, or by the `Id` itself.
+    fn members(&self, selector: &str) -> Vec<Id> {
+        match self.groups.get(selector) {
+            Some(members) => members.clone(),
+            None => vec![Id::new(selector)],
+        }
+    }
+
+    /// Returns the `Id`s whose data the conditions of the `rule` depend on.
+    pub fn inputs(&self, rule: &Rule) -> Vec<Id> {
+        let mut inputs: Vec<Id> = Vec::new();
+
+        for condition in rule.conditions.iter() {
+            for id in self.members(&condition.selector) {
+                if !inputs.contains(&id) {
+                    inputs.push(id)
+                }
+            }
+        }
+
+        inputs
+    }
+
+    /// Returns the `Id`s of all targets; "all" refers to every one of the `actuators`.
+    fn targets(&self, rule: &Rule, actuators: &[Id]) -> Vec<Id> {
+        let mut targets: Vec<Id> = Vec::new();
+
+        for target in rule.targets.iter() {
+            let members = match target.as_str() {
+                "all" => actuators.to_vec(),
+                _ => self.members(target),
+            };
+
+            for id in members {
+                if !targets.contains(&id) {
+                    targets.push(id)
+                }
+            }
+        }
+
+        targets
+    }
+
+    /// Evaluates every rule against the `latest` `Datum` of each `Id`.
+    pub fn evaluate(&self, latest: &HashMap<Id, Datum>, actuators: &[Id]) -> Outcome {
+        let mut outcome = Outcome::default();
+        let mut priorities: HashMap<Id, i32> = HashMap::new();
+
+        let firing = self.rules.iter().filter(|rule| {
+            rule.conditions
+                .iter()
+                .all(|condition| condition.holds(&self.members(&condition.selector), latest))
+        });
+
+        for rule in firing {
+            for id in self.targets(rule, actuators) {
+                match &rule.action {
+                    Action::Command(command) => {
+                        // only a strictly higher priority replaces an earlier rule's command
+                        if priorities.get(&id).is_some_and(|p| *p >= rule.priority) {
+                            continue;
+                        }
+
+                        priorities.insert(id.clone(), rule.priority);
+                        let decision = (command.clone(), rule.name.clone());
+                        outcome.commands.insert(id, decision);
+                    }
+                    Action::Adjust(adjust) => {
+                        *outcome.adjustments.entry(id).or_insert(0.0) += adjust;
+                    }
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod rules_tests {
+    use datum::DatumUnit;
+
+    use super::*;
+
+    const RULES: &str = r#"
+        [groups]
+        bedroom = ["bedroom-1", "bedroom-2"]
+
+        [[rules]]
+        name = "cool the bedrooms"
+        when = ["any bedroom > 26", "back_door == true"]
+        command = "CoolTo:22"
+        targets = ["bedroom"]
+
+        [[rules]]
+        name = "frost protection"
+        when = ["outdoor < 0"]
+        adjust = 1.0
+        targets = ["all"]
+    "#;
+
+    fn latest(data: &[(&str, DatumValue)]) -> HashMap<Id, Datum> {
+        data.iter()
+            .map(|(id, value)| (Id::new(id), Datum::new_now(*value, DatumUnit::Unitless)))
+            .collect()
+    }
+
+    fn ids(ids: &[&str]) -> Vec<Id> {
+        ids.iter().map(|id| Id::new(id)).collect()
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let rules = RuleSet::parse_toml(include_str!("../rules/frost.toml")).unwrap();
+        assert_eq!(rules.rules().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let condition = Condition::parse("all bedroom >= 26.5").unwrap();
+
+        assert_eq!(condition.quantifier, Quantifier::All);
+        assert_eq!(condition.selector, "bedroom");
+        assert_eq!(condition.comparison, Comparison::Ge);
+        assert_eq!(condition.value, DatumValue::Float(26.5));
+
+        let condition = Condition::parse("back_door == true").unwrap();
+        assert_eq!(condition.to_string(), "any back_door == true");
+    }
+
+    #[test]
+    fn test_parse_condition_errors() {
+        assert!(Condition::parse("bedroom > ").is_err());
+        assert!(Condition::parse("bedroom => 26").is_err());
+        assert!(Condition::parse("bedroom > warm").is_err());
+        assert!(Condition::parse("some bedroom > 26").is_err());
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let float = DatumValue::Float(26.5);
+        let int = DatumValue::Int(26);
+
+        assert!(Comparison::Gt.holds(&float, &int));
+        assert!(Comparison::Ne.holds(&float, &int));
+        assert!(Comparison::Le.holds(&int, &float));
+        assert!(!Comparison::Eq.holds(&DatumValue::Float(f32::NAN), &float));
+
+        assert!(Comparison::Eq.holds(&DatumValue::Bool(true), &DatumValue::Bool(true)));
+        assert!(!Comparison::Gt.holds(&DatumValue::Bool(true), &DatumValue::Bool(false)));
+        assert!(!Comparison::Eq.holds(&DatumValue::Bool(true), &int));
+    }
+
+    #[test]
+    fn test_command_rule() {
+        let rules = RuleSet::parse_toml(RULES).unwrap();
+        let actuators = ids(&["bedroom-1", "bedroom-2", "kitchen"]);
+
+        let data = latest(&[
+            ("bedroom-1", DatumValue::Float(25.0)),
+            ("bedroom-2", DatumValue::Float(27.0)),
+            ("back_door", DatumValue::Bool(true)),
+            ("outdoor", DatumValue::Float(5.0)),
+        ]);
+
+        let outcome = rules.evaluate(&data, &actuators);
+
+        assert_eq!(outcome.commands.len(), 2);
+        assert_eq!(
+            outcome.commands.get(&Id::new("bedroom-1")),
+            Some(&(
+                GenericCommand::parse("CoolTo:22").unwrap(),
+                String::from("cool the bedrooms")
+            ))
+        );
+        assert!(outcome.adjustments.is_empty());
+    }
+
+    #[test]
+    fn test_all_conditions_must_hold() {
+        let rules = RuleSet::parse_toml(RULES).unwrap();
+
+        let data = latest(&[
+            ("bedroom-2", DatumValue::Float(27.0)),
+            ("back_door", DatumValue::Bool(false)),
+        ]);
+
+        let outcome = rules.evaluate(&data, &[]);
+        assert!(outcome.commands.is_empty());
+    }
+
+    #[test]
+    fn test_quantifier_all() {
+        let rules = RuleSet::parse_toml(
+            r#"
+            [groups]
+            bedroom = ["bedroom-1", "bedroom-2"]
+
+            [[rules]]
+            name = "all hot"
+            when = ["all bedroom > 26"]
+            command = "CoolTo:22"
+            targets = ["bedroom-1"]
+            "#,
+        )
+        .unwrap();
+
+        // one member has no data, so "all" doesn't hold
+        let data = latest(&[("bedroom-1", DatumValue::Float(27.0))]);
+        assert!(rules.evaluate(&data, &[]).commands.is_empty());
+
+        let data = latest(&[
+            ("bedroom-1", DatumValue::Float(27.0)),
+            ("bedroom-2", DatumValue::Float(26.5)),
+        ]);
+        assert_eq!(rules.evaluate(&data, &[]).commands.len(), 1);
+    }
+
+    #[test]
+    fn test_adjust_all() {
+        let rules = RuleSet::parse_toml(RULES).unwrap();
+        let actuators = ids(&["bedroom-1", "kitchen"]);
+
+        let data = latest(&[("outdoor", DatumValue::Float(-3.0))]);
+        let outcome = rules.evaluate(&data, &actuators);
+
+        assert_eq!(outcome.adjustments.get(&Id::new("kitchen")), Some(&1.0));
+        assert_eq!(outcome.adjustments.get(&Id::new("bedroom-1")), Some(&1.0));
+        assert_eq!(outcome.adjustments.get(&Id::new("bedroom-2")), None);
+    }
+
+    #[test]
+    /// The rule with the highest priority wins; on a tie, the rule defined first wins
+    fn test_conflict_resolution() {
+        let rules = RuleSet::parse_toml(
+            r#"
+            [[rules]]
+            name = "first"
+            when = ["kitchen > 20"]
+            command = "CoolTo:20"
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "tie"
+            when = ["kitchen > 20"]
+            command = "CoolTo:19"
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "urgent"
+            priority = 5
+            when = ["kitchen > 30"]
+            command = "CoolTo:18"
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "raise"
+            when = ["kitchen > 20"]
+            adjust = 0.5
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "raise again"
+            when = ["kitchen > 20"]
+            adjust = 0.25
+            targets = ["kitchen"]
+            "#,
+        )
+        .unwrap();
+
+        let kitchen = Id::new("kitchen");
+
+        let outcome = rules.evaluate(&latest(&[("kitchen", DatumValue::Float(25.0))]), &[]);
+        assert_eq!(outcome.commands[&kitchen].1, "first");
+        assert_eq!(outcome.adjustments[&kitchen], 0.75);
+
+        let outcome = rules.evaluate(&latest(&[("kitchen", DatumValue::Float(35.0))]), &[]);
+        assert_eq!(outcome.commands[&kitchen].1, "urgent");
+    }
+
+    #[test]
+    /// All errors are reported at once, and each one says where it is
+    fn test_validation_errors() {
+        let result = RuleSet::parse_toml(
+            r#"
+            [groups]
+            all = ["kitchen"]
+
+            [[rules]]
+            name = "no conditions"
+            when = []
+            command = "CoolTo:20"
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "both"
+            when = ["kitchen > 20"]
+            command = "CoolTo:20"
+            adjust = 1.0
+            targets = ["kitchen"]
+
+            [[rules]]
+            name = "both"
+            when = ["kitchen > 20"]
+            command = "CoolTo:freezing"
+            targets = ["kitchen"]
+            "#,
+        );
+
+        let expected = [
+            "groups: 'all' is reserved for every Actuator",
+            "rules[0]: at least one condition is required in 'when'",
+            "rules[1]: specify either 'command' or 'adjust', not both",
+            "rules[2]: rule 'both' is defined more than once",
+            "rules[2]: cannot parse CoolTo:freezing as Command",
+        ];
+
+        assert_eq!(result, Err(expected.join("\n")));
+    }
+}
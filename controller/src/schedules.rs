@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use device::Id;
+
+use crate::setpoint::{Profile, SetpointSchedule};
+use crate::strategy::{BangBang, ControlStrategy, Pid};
+
+/// The `ControlStrategy` of a `Zone`, as configured in a `Schedules` file.
+#[derive(PartialEq, Debug, Clone)]
+pub enum StrategyConfig {
+    BangBang(BangBang),
+    Pid(Pid),
+}
+
+impl StrategyConfig {
+    pub fn build(&self) -> Box<dyn ControlStrategy> {
+        match self.clone() {
+            StrategyConfig::BangBang(strategy) => Box::new(strategy),
+            StrategyConfig::Pid(strategy) => Box::new(strategy),
+        }
+    }
+}
+
+/// The `SetpointSchedule` and `ControlStrategy` of a single `Zone`; either may be left unset.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ZoneSchedule {
+    /// The name of the `Zone` (for implicit sensor-actuator pairs, their shared `Id`)
+    pub zone: Id,
+    pub schedule: Option<SetpointSchedule>,
+    pub strategy: Option<StrategyConfig>,
+}
+
+/// Configures the setpoint schedule and control strategy of each `Zone`, for example
+///
+/// ```toml
+/// # profiles are evaluated in this time zone [default: UTC]
+/// time_zone = "America/Toronto"
+///
+/// # holidays use the weekend profile
+/// holidays = ["2024-12-25", "2025-01-01"]
+///
+/// [[zones]]
+/// name = "living room"
+/// # each setpoint takes effect at its time of day, and stays in effect until the next one
+/// weekday = { "07:00" = 21.0, "22:00" = 17.0 }
+/// weekend = { "09:00" = 22.0, "23:00" = 18.0 }
+/// strategy = { kind = "pid", kp = 1.0, ki = 0.0005, limit = 3.0 }
+///
+/// [[zones]]
+/// name = "garage"
+/// strategy = { kind = "bang_bang", setpoint = 12.0, hysteresis = 2.0 }
+/// ```
+///
+/// Zones without a schedule are kept at their strategy's own `setpoint` (21°C by default), and
+/// zones without a strategy use `BangBang::default()`. The weekend profile defaults to the weekday
+/// profile.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Schedules {
+    pub zones: Vec<ZoneSchedule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSchedules {
+    time_zone: Option<String>,
+    #[serde(default)]
+    holidays: Vec<String>,
+    #[serde(default)]
+    zones: Vec<RawZoneSchedule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawZoneSchedule {
+    name: String,
+    weekday: Option<BTreeMap<String, f32>>,
+    weekend: Option<BTreeMap<String, f32>>,
+    strategy: Option<RawStrategy>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum RawStrategy {
+    BangBang {
+        #[serde(default = "RawStrategy::default_setpoint")]
+        setpoint: f32,
+        #[serde(default = "RawStrategy::default_hysteresis")]
+        hysteresis: f32,
+    },
+    Pid {
+        #[serde(default = "RawStrategy::default_setpoint")]
+        setpoint: f32,
+        #[serde(default)]
+        kp: f32,
+        #[serde(default)]
+        ki: f32,
+        #[serde(default)]
+        kd: f32,
+        limit: f32,
+        deadband: Option<f32>,
+    },
+}
+
+impl RawStrategy {
+    fn default_setpoint() -> f32 {
+        BangBang::default().setpoint
+    }
+
+    fn default_hysteresis() -> f32 {
+        BangBang::default().hysteresis
+    }
+
+    fn validate(&self) -> Result<StrategyConfig, String> {
+        let non_negative = |name: &str, value: f32| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(value)
+            } else {
+                Err(format!("{} must not be negative, not {}", name, value))
+            }
+        };
+
+        match *self {
+            RawStrategy::BangBang {
+                setpoint,
+                hysteresis,
+            } => Ok(StrategyConfig::BangBang(BangBang::new(
+                setpoint,
+                non_negative("hysteresis", hysteresis)?,
+            ))),
+            RawStrategy::Pid {
+                setpoint,
+                kp,
+                ki,
+                kd,
+                limit,
+                deadband,
+            } => {
+                let mut pid = Pid::new(
+                    setpoint,
+                    non_negative("kp", kp)?,
+                    non_negative("ki", ki)?,
+                    non_negative("kd", kd)?,
+                    non_negative("limit", limit)?,
+                );
+
+                if let Some(deadband) = deadband {
+                    pid.deadband = non_negative("deadband", deadband)?;
+                }
+
+                Ok(StrategyConfig::Pid(pid))
+            }
+        }
+    }
+}
+
+impl RawZoneSchedule {
+    fn validate(&self, time_zone: Tz, holidays: &[NaiveDate]) -> Result<ZoneSchedule, String> {
+        let profile = |name: &str, entries: &BTreeMap<String, f32>| {
+            let entries = entries
+                .iter()
+                .map(|(time, setpoint)| {
+                    NaiveTime::parse_from_str(time, "%H:%M")
+                        .map(|time| (time, *setpoint))
+                        .map_err(|_| {
+                            format!("{}: cannot parse '{}' as a time, e.g. '07:30'", name, time)
+                        })
+                })
+                .collect::<Result<Vec<(NaiveTime, f32)>, String>>()?;
+
+            Ok::<Profile, String>(Profile::new(entries))
+        };
+
+        let schedule = match (&self.weekday, &self.weekend) {
+            (None, None) => None,
+            (None, Some(_)) => {
+                return Err(String::from("a weekend profile needs a weekday profile"))
+            }
+            (Some(weekday), weekend) => {
+                let mut schedule = SetpointSchedule::new(time_zone, profile("weekday", weekday)?);
+
+                if let Some(weekend) = weekend {
+                    schedule = schedule.with_weekend(profile("weekend", weekend)?);
+                }
+
+                for holiday in holidays {
+                    schedule = schedule.with_holiday(*holiday);
+                }
+
+                Some(schedule)
+            }
+        };
+
+        let strategy = self
+            .strategy
+            .as_ref()
+            .map(RawStrategy::validate)
+            .transpose()?;
+
+        Ok(ZoneSchedule {
+            zone: Id::new(&self.name),
+            schedule,
+            strategy,
+        })
+    }
+}
+
+impl Schedules {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Schedules, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read schedules {}: {}", path.display(), err))?;
+
+        Schedules::parse_toml(&contents)
+            .map_err(|msg| format!("invalid schedules {}:\n{}", path.display(), msg))
+    }
+
+    pub fn parse_toml(string: &str) -> Result<Schedules, String> {
+        let raw: RawSchedules = toml::from_str(string).map_err(|err| err.to_string())?;
+
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        let mut schedules = Schedules::default();
+
+        let time_zone = match raw.time_zone.as_deref().map(str::parse::<Tz>) {
+            None => chrono_tz::UTC,
+            Some(Ok(time_zone)) => time_zone,
+            Some(Err(_)) => {
+                let time_zone = raw.time_zone.unwrap_or_default();
+                errors.push(format!("time_zone: unknown time zone '{}'", time_zone));
+                chrono_tz::UTC
+            }
+        };
+
+        let mut holidays = Vec::new();
+
+        for (index, holiday) in raw.holidays.iter().enumerate() {
+            match NaiveDate::parse_from_str(holiday, "%Y-%m-%d") {
+                Ok(date) => holidays.push(date),
+                Err(_) => errors.push(format!(
+                    "holidays[{}]: cannot parse '{}' as a date, e.g. '2024-12-25'",
+                    index, holiday
+                )),
+            }
+        }
+
+        for (index, zone) in raw.zones.iter().enumerate() {
+            if !names.insert(zone.name.as_str()) {
+                errors.push(format!("zones[{}]: duplicate name '{}'", index, zone.name));
+                continue;
+            }
+
+            match zone.validate(time_zone, &holidays) {
+                Ok(zone) => schedules.zones.push(zone),
+                Err(msg) => errors.push(format!("zones[{}]: {}", index, msg)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(schedules)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod schedules_tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::setpoint::Source;
+
+    use super::*;
+
+    /// 2023-11-20 is a Monday
+    fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let schedules = Schedules::parse_toml(
+            r#"
+            time_zone = "America/Toronto"
+            holidays = ["2023-11-22"]
+
+            [[zones]]
+            name = "living room"
+            weekday = { "07:00" = 21.0, "22:00" = 17.0 }
+            weekend = { "09:00" = 22.0 }
+            strategy = { kind = "pid", kp = 1.0, ki = 0.0005, limit = 3.0, deadband = 0.2 }
+
+            [[zones]]
+            name = "garage"
+            strategy = { kind = "bang_bang", setpoint = 12.0 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(schedules.zones.len(), 2);
+
+        let living_room = &schedules.zones[0];
+        assert_eq!(living_room.zone, Id::new("living room"));
+
+        // 12:00 UTC is 7:00 in Toronto
+        let schedule = living_room.schedule.as_ref().unwrap();
+        assert_eq!(schedule.active(utc(21, 11)), Some((17.0, Source::Weekday)));
+        assert_eq!(schedule.active(utc(21, 12)), Some((21.0, Source::Weekday)));
+
+        let holiday = NaiveDate::from_ymd_opt(2023, 11, 22).unwrap();
+        assert_eq!(
+            schedule.active(utc(22, 15)),
+            Some((22.0, Source::Holiday(holiday)))
+        );
+
+        let mut pid = Pid::new(21.0, 1.0, 0.0005, 0.0, 3.0);
+        pid.deadband = 0.2;
+        assert_eq!(living_room.strategy, Some(StrategyConfig::Pid(pid)));
+
+        let garage = &schedules.zones[1];
+        assert_eq!(garage.schedule, None);
+        assert_eq!(
+            garage.strategy,
+            Some(StrategyConfig::BangBang(BangBang::new(12.0, 1.0)))
+        );
+        assert_eq!(garage.strategy.as_ref().unwrap().build().setpoint(), 12.0);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let errors = Schedules::parse_toml(
+            r#"
+            time_zone = "Mars/Olympus_Mons"
+            holidays = ["christmas"]
+
+            [[zones]]
+            name = "kitchen"
+            weekday = { "7am" = 21.0 }
+
+            [[zones]]
+            name = "kitchen"
+
+            [[zones]]
+            name = "hall"
+            weekend = { "09:00" = 22.0 }
+
+            [[zones]]
+            name = "garage"
+            strategy = { kind = "bang_bang", hysteresis = -1.0 }
+            "#,
+        )
+        .unwrap_err();
+
+        let lines: Vec<&str> = errors.lines().collect();
+
+        assert_eq!(lines.len(), 6, "{}", errors);
+        assert!(lines[0].starts_with("time_zone"));
+        assert!(lines[1].starts_with("holidays[0]"));
+        assert!(lines[2].starts_with("zones[0]: weekday"));
+        assert!(lines[3].contains("duplicate name"));
+        assert!(lines[4].starts_with("zones[2]"));
+        assert!(lines[5].starts_with("zones[3]: hysteresis"));
+
+        let unknown = r#"
+            [[zones]]
+            name = "kitchen"
+            strategy = { kind = "fuzzy" }
+        "#;
+        assert!(Schedules::parse_toml(unknown).is_err());
+
+        let unknown = r#"
+            [[zones]]
+            name = "kitchen"
+            strategy = { kind = "pid", limit = 3.0, gain = 1.0 }
+        "#;
+        assert!(Schedules::parse_toml(unknown).is_err());
+    }
+
+    #[test]
+    fn test_example_file() {
+        let schedules = Schedules::parse_toml(include_str!("../schedules.toml")).unwrap();
+
+        assert_eq!(schedules.zones.len(), 3);
+        assert_eq!(schedules.zones[2].schedule, None);
+    }
+}
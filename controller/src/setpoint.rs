@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// A `Profile` is a list of setpoints for a single day, each of which takes effect at a time of day
+/// and stays in effect until the next one.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Profile {
+    entries: Vec<(NaiveTime, f32)>,
+}
+
+impl Profile {
+    pub fn new(mut entries: Vec<(NaiveTime, f32)>) -> Profile {
+        entries.sort_by_key(|(time, _)| *time);
+        Profile { entries }
+    }
+
+    /// A `Profile` with the same setpoint all day.
+    pub fn constant(setpoint: f32) -> Profile {
+        Profile::new(vec![(NaiveTime::MIN, setpoint)])
+    }
+
+    /// Returns the setpoint which took effect at or before `time` on this day, if any.
+    pub fn at(&self, time: NaiveTime) -> Option<f32> {
+        let time = time.with_nanosecond(0)?;
+
+        self.entries
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= time)
+            .map(|(_, setpoint)| *setpoint)
+    }
+
+    /// Returns the setpoint still in effect at the end of this day, if any.
+    pub fn last(&self) -> Option<f32> {
+        self.entries.last().map(|(_, setpoint)| *setpoint)
+    }
+}
+
+/// Where the active setpoint of a sensor-actuator pair came from.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Source {
+    /// A temporary, manual override, which expires at the specified time
+    Override(DateTime<Utc>),
+    /// The schedule's profile for a holiday
+    Holiday(NaiveDate),
+    /// The schedule's profile for Monday through Friday
+    Weekday,
+    /// The schedule's profile for Saturday and Sunday
+    Weekend,
+    /// The `ControlStrategy`'s own setpoint, because there is no schedule
+    Strategy,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Override(until) => write!(f, "override until {}", until.to_rfc3339()),
+            Source::Holiday(date) => write!(f, "holiday {}", date),
+            Source::Weekday => write!(f, "weekday"),
+            Source::Weekend => write!(f, "weekend"),
+            Source::Strategy => write!(f, "strategy"),
+        }
+    }
+}
+
+/// A temporary setpoint, e.g. "hold 23°C for 2 hours".
+#[derive(PartialEq, Debug, Clone)]
+pub struct Override {
+    pub setpoint: f32,
+    pub until: DateTime<Utc>,
+}
+
+impl Override {
+    /// Parses an `Override` of the form "setpoint:duration", e.g. "23:2h", starting `now`.
+    ///
+    /// Durations are as parsed by `device::config::parse_duration`.
+    pub fn parse(string: &str, now: DateTime<Utc>) -> Result<Override, String> {
+        let error = || {
+            format!(
+                "cannot parse '{}' as Override, expected e.g. '23:2h'",
+                string
+            )
+        };
+
+        let (setpoint, duration) = string.trim().split_once(':').ok_or_else(error)?;
+        let setpoint = setpoint.trim().parse::<f32>().map_err(|_| error())?;
+
+        let duration = device::config::parse_duration(duration).map_err(|_| error())?;
+        let duration = Duration::from_std(duration).map_err(|_| error())?;
+
+        if duration <= Duration::zero() {
+            return Err(format!(
+                "Override duration must be positive, not '{}'",
+                string
+            ));
+        }
+
+        Ok(Override {
+            setpoint,
+            until: now + duration,
+        })
+    }
+}
+
+/// The setpoint of a sensor-actuator pair over the course of a week.
+///
+/// Profiles are evaluated in the schedule's `time_zone`, so "7am" means 7am local time, even though
+/// `Datum` timestamps are in UTC. Holidays use the weekend profile. An unexpired `Override` takes
+/// precedence over all profiles.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SetpointSchedule {
+    time_zone: Tz,
+    weekday: Profile,
+    weekend: Profile,
+    holidays: HashSet<NaiveDate>,
+    hold: Option<Override>,
+}
+
+impl SetpointSchedule {
+    /// Creates a schedule which uses the same `profile` every day.
+    pub fn new(time_zone: Tz, profile: Profile) -> SetpointSchedule {
+        SetpointSchedule {
+            time_zone,
+            weekday: profile.clone(),
+            weekend: profile,
+            holidays: HashSet::new(),
+            hold: None,
+        }
+    }
+
+    pub fn with_weekend(mut self, profile: Profile) -> SetpointSchedule {
+        self.weekend = profile;
+        self
+    }
+
+    pub fn with_holiday(mut self, date: NaiveDate) -> SetpointSchedule {
+        self.holidays.insert(date);
+        self
+    }
+
+    /// Temporarily replaces the scheduled setpoint, until the `Override` expires.
+    pub fn hold(&mut self, hold: Override) {
+        self.hold = Some(hold)
+    }
+
+    /// Cancels the `Override`, if any, returning to the scheduled setpoint.
+    pub fn cancel_hold(&mut self) {
+        self.hold = None
+    }
+
+    /// Returns the profile used on the specified (local) date, and its `Source`.
+    fn profile(&self, date: NaiveDate) -> (&Profile, Source) {
+        if self.holidays.contains(&date) {
+            (&self.weekend, Source::Holiday(date))
+        } else if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            (&self.weekend, Source::Weekend)
+        } else {
+            (&self.weekday, Source::Weekday)
+        }
+    }
+
+    /// Returns the setpoint in effect at the specified time, and where it came from.
+    ///
+    /// Returns `None` if the profiles are empty and there is no unexpired `Override`.
+    pub fn active(&self, now: DateTime<Utc>) -> Option<(f32, Source)> {
+        if let Some(hold) = self.hold.as_ref().filter(|hold| hold.until > now) {
+            return Some((hold.setpoint, Source::Override(hold.until)));
+        }
+
+        let local = now.with_timezone(&self.time_zone).naive_local();
+        let (today, source) = self.profile(local.date());
+
+        if let Some(setpoint) = today.at(local.time()) {
+            return Some((setpoint, source));
+        }
+
+        // before the first setpoint of the day, the last setpoint of yesterday is still in effect
+        let (yesterday, source) = self.profile(local.date().pred_opt()?);
+        yesterday.last().map(|setpoint| (setpoint, source))
+    }
+}
+
+#[cfg(test)]
+mod setpoint_tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    /// 2023-11-20 is a Monday
+    fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, day, hour, 0, 0).unwrap()
+    }
+
+    fn schedule() -> SetpointSchedule {
+        let weekday = Profile::new(vec![(time(22), 17.0), (time(7), 21.0)]);
+        let weekend = Profile::new(vec![(time(9), 22.0), (time(23), 18.0)]);
+
+        SetpointSchedule::new(chrono_tz::UTC, weekday).with_weekend(weekend)
+    }
+
+    #[test]
+    fn test_profile_at() {
+        let profile = Profile::new(vec![(time(22), 17.0), (time(7), 21.0)]);
+
+        assert_eq!(profile.at(time(6)), None);
+        assert_eq!(profile.at(time(7)), Some(21.0));
+        assert_eq!(profile.at(time(23)), Some(17.0));
+        assert_eq!(profile.last(), Some(17.0));
+    }
+
+    #[test]
+    fn test_weekday_and_weekend() {
+        let schedule = schedule();
+
+        assert_eq!(schedule.active(utc(20, 8)), Some((21.0, Source::Weekday)));
+        assert_eq!(schedule.active(utc(25, 10)), Some((22.0, Source::Weekend)));
+    }
+
+    #[test]
+    /// Before the first setpoint of the day, the previous day's last setpoint applies
+    fn test_carries_over_from_yesterday() {
+        let schedule = schedule();
+
+        // Saturday morning, before 9am: Friday night's setpoint
+        assert_eq!(schedule.active(utc(25, 8)), Some((17.0, Source::Weekday)));
+
+        // Monday morning, before 7am: Sunday night's setpoint
+        assert_eq!(schedule.active(utc(27, 6)), Some((18.0, Source::Weekend)));
+    }
+
+    #[test]
+    fn test_holiday() {
+        let holiday = NaiveDate::from_ymd_opt(2023, 11, 22).unwrap();
+        let schedule = schedule().with_holiday(holiday);
+
+        assert_eq!(
+            schedule.active(utc(22, 10)),
+            Some((22.0, Source::Holiday(holiday)))
+        );
+    }
+
+    #[test]
+    /// Profiles are evaluated in local time, not UTC
+    fn test_time_zone() {
+        let weekday = Profile::new(vec![(time(22), 17.0), (time(7), 21.0)]);
+        let schedule = SetpointSchedule::new(chrono_tz::America::Toronto, weekday);
+
+        // 11:00 UTC is 6:00 in Toronto
+        assert_eq!(schedule.active(utc(21, 11)), Some((17.0, Source::Weekday)));
+
+        // 12:00 UTC is 7:00 in Toronto
+        assert_eq!(schedule.active(utc(21, 12)), Some((21.0, Source::Weekday)));
+    }
+
+    #[test]
+    fn test_override_expires() {
+        let mut schedule = schedule();
+        let now = utc(20, 8);
+
+        schedule.hold(Override::parse("23:2h", now).unwrap());
+
+        let until = utc(20, 10);
+        assert_eq!(schedule.active(now), Some((23.0, Source::Override(until))));
+        assert_eq!(schedule.active(until), Some((21.0, Source::Weekday)));
+
+        schedule.hold(Override::parse("23:2h", now).unwrap());
+        schedule.cancel_hold();
+        assert_eq!(schedule.active(now), Some((21.0, Source::Weekday)));
+    }
+
+    #[test]
+    fn test_parse_override() {
+        let now = utc(20, 8);
+
+        assert_eq!(
+            Override::parse("19.5:30m", now),
+            Ok(Override {
+                setpoint: 19.5,
+                until: now + Duration::minutes(30)
+            })
+        );
+
+        assert!(Override::parse("23", now).is_err());
+        assert!(Override::parse("warm:2h", now).is_err());
+        assert_eq!(
+            Override::parse("23:2d", now).map(|o| o.until),
+            Ok(now + Duration::days(2))
+        );
+        assert!(Override::parse("23:2w", now).is_err());
+        assert!(Override::parse("23:0h", now).is_err());
+        assert!(Override::parse("23:", now).is_err());
+        assert!(Override::parse("23:2°", now).is_err());
+    }
+}
@@ -1,27 +1,141 @@
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use mdns_sd::ServiceInfo;
+use tracing::{debug, info, trace, warn};
 
-use datum::Datum;
-use device::Id;
+use actuator::{GenericCommand, Schema};
+use datum::{Datum, Reading};
+use device::{protocol, Id};
+use sensor::StreamOptions;
 
+use crate::alert::{
+    Alert, AlertConfig, AlertSink, AlertStream, Alerter, LogSink, Observations, WebhookSink,
+};
+use crate::audit::{AuditLog, Entry, Query, Trigger};
+use crate::capabilities::Capabilities;
+use crate::config::ControllerConfig;
+use crate::metrics::Metrics;
+use crate::pairing::Pairings;
+use crate::rules::RuleSet;
+use crate::setpoint::{Override, Profile, SetpointSchedule, Source};
+use crate::store::{Record, Snapshot, Store};
+use crate::strategy::{self, BangBang, ControlStrategy};
+use crate::stream;
+
+/// The maximum number of `Datum`s kept in memory for each `Sensor`.
+const HISTORY_LENGTH: usize = 100;
+
+/// How long a `Sensor` whose stream dropped is polled before the `Controller` subscribes to it again.
+const RESUBSCRIBE_AFTER: Duration = Duration::from_secs(30);
+
+/// A `Command`, and the name of the rule which sent it (`None` if it came from a `ControlStrategy`)
+type Decision = (GenericCommand, Option<String>);
+
+#[derive(Clone)]
 pub struct State {
-    // histories: HashMap<Id, SensorHistory>,
+    histories: Arc<Mutex<HashMap<Id, SensorHistory>>>,
     sensors: Arc<Mutex<HashMap<Id, ServiceInfo>>>,
     actuators: Arc<Mutex<HashMap<Id, ServiceInfo>>>,
+    /// The `Capabilities` advertised by each discovered `Sensor`
+    sensor_capabilities: Arc<Mutex<HashMap<Id, Capabilities>>>,
+    /// The `Capabilities` advertised by each discovered `Actuator`
+    actuator_capabilities: Arc<Mutex<HashMap<Id, Capabilities>>>,
+    /// The `Schema` of the `Command`s accepted by each `Actuator` which has been asked for it
+    schemas: Arc<Mutex<HashMap<Id, Schema>>>,
+    /// The `ControlStrategy` for each `Zone`; zones without one use `BangBang::default()`
+    strategies: Arc<Mutex<HashMap<Id, Box<dyn ControlStrategy>>>>,
+    /// The last `Command` successfully sent to each `Actuator`, and the rule which sent it (if any)
+    commands: Arc<Mutex<HashMap<Id, Decision>>>,
+    /// The `SetpointSchedule` for each `Zone` which has one
+    schedules: Arc<Mutex<HashMap<Id, SetpointSchedule>>>,
+    rules: Arc<Mutex<RuleSet>>,
+    /// The setpoint adjustment currently applied to the `ControlStrategy` of each `Zone` by the rules
+    adjustments: Arc<Mutex<HashMap<Id, f32>>>,
+    pairings: Arc<Mutex<Pairings>>,
+    /// The last `Command` decided upon by the `ControlStrategy` of each `Zone`
+    intents: Arc<Mutex<HashMap<Id, GenericCommand>>>,
+    /// Where data, `Command`s, and configuration changes are persisted, if anywhere
+    store: Arc<Mutex<Option<Store>>>,
+    audit: Arc<Mutex<AuditLog>>,
+    /// When each `Sensor` last pushed a `Reading` to the `Controller`; see `State::receive`
+    pushes: Arc<Mutex<HashMap<Id, Instant>>>,
+    /// Each `Sensor` the `Controller` has subscribed to, and when its stream dropped (`None` while
+    /// it is still streaming); see `State::stream`
+    streams: Arc<Mutex<HashMap<Id, Option<Instant>>>>,
+    /// When each device which isn't responding first failed to respond
+    failing: Arc<Mutex<HashMap<Id, DateTime<Utc>>>>,
+    /// The setpoint each `Zone` was last commanded to, and its current reading
+    targets: Arc<Mutex<HashMap<Id, (f32, f32)>>>,
+    alerter: Arc<Mutex<Alerter>>,
+    /// Where `Alert`s are sent; see `State::add_alert_sink`
+    sinks: Arc<Mutex<Vec<Box<dyn AlertSink>>>>,
+    /// The webhooks configured in the `AlertConfig`
+    webhooks: Arc<Mutex<Vec<WebhookSink>>>,
+    alert_stream: AlertStream,
+    metrics: Metrics,
+    config: ControllerConfig,
+}
+
+/// Why the `Controller` refused a `Reading` pushed to it by a `Sensor`; see `State::receive`.
+#[derive(PartialEq, Debug)]
+pub enum Rejection {
+    /// The `Sensor` hasn't been discovered
+    Unknown,
+    /// The `Sensor` pushed again before the `min_push_interval` had elapsed, so must wait this long
+    TooSoon(Duration),
+    /// The `Reading` doesn't match what the `Sensor` advertised it measures
+    Invalid(String),
+}
+
+/// A `Command` the `Controller` has decided to send to an `Actuator`, and why.
+struct Dispatch {
+    id: Id,
+    info: ServiceInfo,
+    command: GenericCommand,
+    trigger: Trigger,
+    /// The latest `Datum` from each `Sensor` which drove the decision
+    readings: Vec<(Id, Datum)>,
 }
 
 impl Default for State {
     fn default() -> Self {
+        let alert_stream = AlertStream::default();
+
         Self {
-            // histories: HashMap::new(),
+            histories: Arc::new(Mutex::new(HashMap::new())),
             sensors: Arc::new(Mutex::new(HashMap::new())),
             actuators: Arc::new(Mutex::new(HashMap::new())),
+            sensor_capabilities: Arc::new(Mutex::new(HashMap::new())),
+            actuator_capabilities: Arc::new(Mutex::new(HashMap::new())),
+            schemas: Arc::new(Mutex::new(HashMap::new())),
+            strategies: Arc::new(Mutex::new(HashMap::new())),
+            commands: Arc::new(Mutex::new(HashMap::new())),
+            schedules: Arc::new(Mutex::new(HashMap::new())),
+            rules: Arc::new(Mutex::new(RuleSet::default())),
+            adjustments: Arc::new(Mutex::new(HashMap::new())),
+            pairings: Arc::new(Mutex::new(Pairings::default())),
+            intents: Arc::new(Mutex::new(HashMap::new())),
+            store: Arc::new(Mutex::new(None)),
+            audit: Arc::new(Mutex::new(AuditLog::default())),
+            pushes: Arc::new(Mutex::new(HashMap::new())),
+            streams: Arc::new(Mutex::new(HashMap::new())),
+            failing: Arc::new(Mutex::new(HashMap::new())),
+            targets: Arc::new(Mutex::new(HashMap::new())),
+            alerter: Arc::new(Mutex::new(Alerter::default())),
+            sinks: Arc::new(Mutex::new(vec![
+                Box::new(LogSink),
+                Box::new(alert_stream.clone()),
+            ])),
+            webhooks: Arc::new(Mutex::new(Vec::new())),
+            alert_stream,
+            metrics: Metrics::new(),
+            config: ControllerConfig::default(),
         }
     }
 }
@@ -31,39 +145,59 @@ impl State {
         Self::default()
     }
 
-    fn extract_id(info: &ServiceInfo) -> Id {
-        let id = info.get_property("id").unwrap().to_string();
+    /// Creates a `State` which polls, discovers, and connects to devices as `config` specifies.
+    pub fn with_config(config: ControllerConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
 
-        println!(
-            "[extract_id] found device at {}:{} with {}",
-            info.get_hostname().trim_end_matches('.'),
-            info.get_port(),
-            id
+    /// Returns the `Id` a device registered itself with, if it is a valid device `Id`.
+    fn extract_id(info: &ServiceInfo) -> Result<Id, String> {
+        let id = info.get_property_val_str("id").unwrap().to_string();
+
+        info!(
+            %id,
+            name = State::extract_name(info),
+            host = info.get_hostname().trim_end_matches('.'),
+            port = info.get_port(),
+            "discovered device"
         );
 
-        Id::new(id.as_str())
+        Id::parse(id.as_str())
+    }
+
+    /// Returns the user-friendly `Name` a device registered itself with, e.g. "Kitchen Thermostat".
+    fn extract_name(info: &ServiceInfo) -> String {
+        let name = info.get_fullname().strip_suffix(info.get_type());
+        name.unwrap_or_default().trim_end_matches('.').to_string()
     }
 
     pub fn discover_sensors(&self) -> JoinHandle<()> {
-        self.discover("_sensor")
+        let group = &self.config.sensor_group;
+        self.discover(&self.sensors, &self.sensor_capabilities, group)
     }
 
     pub fn discover_actuators(&self) -> JoinHandle<()> {
-        self.discover("_actuator")
+        let group = &self.config.actuator_group;
+        self.discover(&self.actuators, &self.actuator_capabilities, group)
     }
 
-    /// Creates a new thread to continually discover devices on the network in the specified group.
-    fn discover(&self, group: &str) -> JoinHandle<()> {
-        let devices = match group {
-            "_sensor" => &self.sensors,
-            "_actuator" => &self.actuators,
-            _ => panic!("can only discover _sensor or _actuator, not {}", group),
-        };
-
+    /// Creates a new thread to continually discover devices on the network in the specified group,
+    /// along with the `Capabilities` they advertise.
+    fn discover(
+        &self,
+        devices: &Arc<Mutex<HashMap<Id, ServiceInfo>>>,
+        capabilities: &Arc<Mutex<HashMap<Id, Capabilities>>>,
+        group: &str,
+    ) -> JoinHandle<()> {
         let group = String::from(group);
 
         // clone the Arc<Mutex<>> around the devices so we can update them in multiple threads
         let mutex = Arc::clone(devices);
+        let capabilities = Arc::clone(capabilities);
+        let metrics = self.metrics.clone();
 
         std::thread::spawn(move || {
             let mdns = mdns_sd::ServiceDaemon::new().unwrap();
@@ -72,111 +206,1702 @@ impl State {
 
             while let Ok(event) = receiver.recv() {
                 if let mdns_sd::ServiceEvent::ServiceResolved(info) = event {
-                    let id = State::extract_id(&info);
+                    // the series of each of a device's channels are keyed by "{id}:{channel}", so
+                    // a device whose id contains a ':' could collide with another's channel
+                    let id = match State::extract_id(&info) {
+                        Ok(id) => id,
+                        Err(msg) => {
+                            warn!(error = msg, "ignoring device");
+                            continue;
+                        }
+                    };
+
+                    // devices which advertise malformed capabilities are treated like those
+                    // which advertise none at all
+                    match Capabilities::parse(&info) {
+                        Ok(advertised) => {
+                            debug!(%id, capabilities = %advertised.to_json(), "parsed capabilities");
+
+                            if let Err(msg) = advertised.negotiate() {
+                                warn!(%id, error = msg, "incompatible device");
+                            }
+
+                            capabilities.lock().unwrap().insert(id.clone(), advertised);
+                        }
+                        Err(msg) => {
+                            warn!(%id, error = msg, "cannot parse capabilities");
+                            capabilities.lock().unwrap().remove(&id);
+                        }
+                    }
+
                     let lock_result = mutex.lock();
                     let mut mutex_guard = lock_result.unwrap();
                     mutex_guard.insert(id, info);
+                    metrics.record_devices(&group, mutex_guard.len());
                 }
             }
         })
     }
 
-    /// Connects to an address, sends the specified request, and returns the response
-    #[allow(dead_code)] // remove this ASAP
-    fn send_request(info: &ServiceInfo, request: &str) -> String {
+    /// Sets the `ControlStrategy` used for the `Zone` with the specified name (for implicit
+    /// sensor-actuator pairs, this is their shared `Id`).
+    pub fn set_strategy(&self, id: Id, strategy: Box<dyn ControlStrategy>) {
+        self.strategies.lock().unwrap().insert(id, strategy);
+    }
+
+    /// Sets the `SetpointSchedule` used for the `Zone` with the specified name (for implicit
+    /// sensor-actuator pairs, this is their shared `Id`).
+    pub fn set_schedule(&self, id: Id, schedule: SetpointSchedule) {
+        self.schedules.lock().unwrap().insert(id, schedule);
+    }
+
+    /// Returns `true` if the `Controller` knows of a `Sensor`, `Actuator`, `Zone`, or
+    /// configuration with the specified `Id`.
+    pub fn is_known(&self, id: &Id) -> bool {
+        // take one lock at a time, so we can't deadlock with State::decide
+        let zone = self.pairings.lock().unwrap().get(&id.0).is_some();
+        let sensor = self.sensors.lock().unwrap().contains_key(id);
+        let actuator = self.actuators.lock().unwrap().contains_key(id);
+        let strategy = self.strategies.lock().unwrap().contains_key(id);
+        let schedule = self.schedules.lock().unwrap().contains_key(id);
+
+        zone || sensor || actuator || strategy || schedule
+    }
+
+    /// Returns `true` if the `Controller` has discovered an `Actuator` with the specified `Id`.
+    pub fn is_actuator(&self, id: &Id) -> bool {
+        self.actuators.lock().unwrap().contains_key(id)
+    }
+
+    /// Returns the setpoint currently in effect for the `Zone` with the specified name, and where
+    /// it came from.
+    ///
+    /// The setpoint includes any adjustment made by the rules; see `State::adjustment`.
+    pub fn active_setpoint(&self, id: &Id) -> (f32, Source) {
+        let mut strategies = self.strategies.lock().unwrap();
+        let schedules = self.schedules.lock().unwrap();
+        let adjustment = self.adjustment(id);
+
+        let scheduled = schedules
+            .get(id)
+            .and_then(|schedule| schedule.active(Utc::now()));
+
+        match scheduled {
+            Some((setpoint, source)) => (setpoint + adjustment, source),
+            None => {
+                let strategy = strategies
+                    .entry(id.clone())
+                    .or_insert_with(|| Box::<BangBang>::default());
+
+                (strategy.setpoint(), Source::Strategy)
+            }
+        }
+    }
+
+    /// Temporarily overrides the setpoint of the `Zone` with the specified name.
+    ///
+    /// If the `Zone` has no `SetpointSchedule`, it gets one which returns to its current setpoint
+    /// when the `Override` expires.
+    pub fn hold(&self, id: &Id, hold: Override) {
+        let mut strategies = self.strategies.lock().unwrap();
+        let mut schedules = self.schedules.lock().unwrap();
+        let adjustment = self.adjustment(id);
+
+        let schedule = schedules.entry(id.clone()).or_insert_with(|| {
+            let strategy = strategies
+                .entry(id.clone())
+                .or_insert_with(|| Box::<BangBang>::default());
+
+            let setpoint = strategy.setpoint() - adjustment;
+            SetpointSchedule::new(chrono_tz::UTC, Profile::constant(setpoint))
+        });
+
+        let record = Record::Hold {
+            zone: id.to_string(),
+            setpoint: hold.setpoint,
+            until: hold.until.to_rfc3339(),
+        };
+
+        schedule.hold(hold);
+
+        drop(schedules);
+        drop(strategies);
+        self.record(record)
+    }
+
+    /// Cancels any `Override` of the setpoint of the `Zone` with the specified name.
+    pub fn cancel_hold(&self, id: &Id) {
+        if let Some(schedule) = self.schedules.lock().unwrap().get_mut(id) {
+            schedule.cancel_hold()
+        }
+
+        self.record(Record::CancelHold {
+            zone: id.to_string(),
+        })
+    }
+
+    /// Persists everything the `Controller` learns or does to the `store`, after restoring the
+    /// state in the `snapshot` which was read from it.
+    ///
+    /// The last `HISTORY_LENGTH` `Datum`s of each `Sensor`, the last `Command` sent to each
+    /// `Actuator`, the audit log, and any unexpired setpoint `Override`s are restored. The stored
+    /// `Pairings` are restored only if the current `Pairings` weren't read from a file (which is
+    /// kept up to date).
+    pub fn set_store(&self, store: Store, snapshot: Snapshot) {
+        let Snapshot {
+            histories,
+            commands,
+            holds,
+            pairings,
+            audit,
+        } = snapshot;
+
+        {
+            let mut restored = self.histories.lock().unwrap();
+
+            for (id, data) in histories {
+                let history = restored.entry(id).or_default();
+                let skip = data.len().saturating_sub(HISTORY_LENGTH);
+                data.into_iter()
+                    .skip(skip)
+                    .for_each(|datum| history.push(datum));
+            }
+        }
+
+        {
+            let mut restored = self.commands.lock().unwrap();
+
+            for sent in commands {
+                restored.insert(sent.id, (sent.command, sent.rule));
+            }
+        }
+
+        {
+            let mut restored = self.audit.lock().unwrap();
+            audit.into_iter().for_each(|entry| restored.push(entry));
+        }
+
+        for (id, hold) in holds {
+            self.hold(&id, hold);
+        }
+
+        if let Some(toml) = pairings {
+            let mut current = self.pairings.lock().unwrap();
+
+            if current.path().is_none() {
+                match Pairings::parse_toml(&toml) {
+                    Ok(restored) => *current = restored,
+                    Err(msg) => warn!(error = msg, "cannot restore pairings from store"),
+                }
+            }
+        }
+
+        *self.store.lock().unwrap() = Some(store);
+    }
+
+    /// Appends a `Record` to the `Store`, if there is one.
+    pub(crate) fn record(&self, record: Record) {
+        State::append(&self.store, record)
+    }
+
+    fn append(store: &Mutex<Option<Store>>, record: Record) {
+        if let Some(store) = store.lock().unwrap().as_mut() {
+            if let Err(msg) = store.append(&record) {
+                warn!(error = msg, "could not persist record to store")
+            }
+        }
+    }
+
+    /// Replaces the rules which automate `Actuator`s based on the data from any `Sensor`.
+    pub fn set_rules(&self, rules: RuleSet) {
+        *self.rules.lock().unwrap() = rules;
+    }
+
+    /// Replaces the explicit pairings of `Sensor`s and `Actuator`s.
+    pub fn set_pairings(&self, pairings: Pairings) {
+        *self.pairings.lock().unwrap() = pairings;
+    }
+
+    /// Returns the explicit pairings of `Sensor`s and `Actuator`s, so they can be inspected or edited.
+    pub fn pairings(&self) -> MutexGuard<'_, Pairings> {
+        self.pairings.lock().unwrap()
+    }
+
+    /// Returns the amount the rules currently add to the setpoint of the specified `Zone`.
+    pub fn adjustment(&self, id: &Id) -> f32 {
+        let adjustments = self.adjustments.lock().unwrap();
+        adjustments.get(id).copied().unwrap_or(0.0)
+    }
+
+    /// Connects to a device, giving up if connecting (or any later read or write) takes longer than
+    /// the `timeout`.
+    fn connect(info: &ServiceInfo, timeout: Duration) -> std::io::Result<TcpStream> {
         let address = format!(
             "{}:{}",
             info.get_hostname().trim_end_matches('.'),
             info.get_port()
         );
 
-        println!("[send_request] connecting to url {}", address);
+        trace!(address, "connecting");
+
+        let socket = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, address.clone()))?;
 
-        let mut stream = TcpStream::connect(address).unwrap();
+        let stream = TcpStream::connect_timeout(&socket, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
 
-        stream.write_all(request.as_bytes()).unwrap();
+        Ok(stream)
+    }
+
+    /// Connects to a device, sends the specified request, and returns the response, giving up if
+    /// any step takes longer than the `timeout`.
+    fn send_request(
+        info: &ServiceInfo,
+        request: &str,
+        timeout: Duration,
+    ) -> std::io::Result<String> {
+        let mut stream = Self::connect(info, timeout)?;
+
+        stream.write_all(request.as_bytes())?;
 
         let mut response = Vec::new();
-        stream.read_to_end(&mut response).unwrap();
+        stream.read_to_end(&mut response)?;
 
-        String::from(
+        Ok(String::from(
             std::str::from_utf8(&response)
                 .map(|s| s.trim())
                 .unwrap_or("Failed to read response"),
-        )
+        ))
+    }
+
+    /// Returns the newest protocol version spoken by both the `Controller` and a device with the
+    /// specified `Capabilities`, or an error if they have none in common.
+    ///
+    /// Devices whose `Capabilities` are unknown are assumed to speak the newest version.
+    fn negotiate(capabilities: Option<&Capabilities>) -> Result<u32, String> {
+        match capabilities {
+            Some(capabilities) => capabilities.negotiate(),
+            None => Ok(protocol::PROTOCOL_VERSION),
+        }
     }
 
-    /// Attempts to get the latest `Datum` from the `Sensor` with the specified `Id`.
-    #[allow(dead_code)] // remove this ASAP
-    pub fn read_sensor(info: &ServiceInfo) -> Result<Datum, String> {
+    /// Returns an error if a device responded in a different protocol `version` than it was asked
+    /// to. Devices which predate versioning don't say which version they responded in.
+    pub(crate) fn check_version(response: &str, version: u32) -> Result<(), String> {
+        match device::http::header(response, protocol::HEADER) {
+            Some(responded) if responded != version.to_string() => Err(format!(
+                "asked for protocol version {}, but the device responded in version {}",
+                version, responded
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Attempts to get the latest `Reading` from the `Sensor` with the specified `Id`, in the
+    /// specified protocol `version`.
+    pub fn read_sensor(
+        info: &ServiceInfo,
+        version: u32,
+        timeout: Duration,
+    ) -> Result<Reading, String> {
         // send the minimum possible payload. We basically just want to ping the Sensor
         // see: https://stackoverflow.com/a/9734866
-        let request = "GET / HTTP/1.1\r\n\r\n";
+        let request = format!(
+            "GET / HTTP/1.1\r\n{}: {}\r\n\r\n",
+            protocol::HEADER,
+            version
+        );
 
-        let response = State::send_request(info, request);
+        let response =
+            State::send_request(info, &request, timeout).map_err(|err| err.to_string())?;
 
-        println!(
-            "[read_sensor] response from url {}:{}\n----------\n{}\n----------",
-            info.get_hostname().trim_end_matches('.'),
-            info.get_port(),
-            response
+        trace!(
+            host = info.get_hostname().trim_end_matches('.'),
+            port = info.get_port(),
+            response,
+            "response from sensor"
+        );
+
+        State::check_version(&response, version)?;
+
+        // parse the response and return it; version 1 Sensors respond with a single Datum
+        let body = device::http::body(&response)?;
+
+        match version {
+            1 => Datum::parse(body).map(Reading::from),
+            _ => Reading::parse(body),
+        }
+    }
+
+    /// Returns the `Capabilities` advertised by the `Sensor` with the specified `Id`, if it has
+    /// been discovered.
+    pub fn sensor_capabilities(&self, id: &Id) -> Option<Capabilities> {
+        self.sensor_capabilities.lock().unwrap().get(id).cloned()
+    }
+
+    /// Returns the `Capabilities` advertised by the `Actuator` with the specified `Id`, if it has
+    /// been discovered.
+    pub fn actuator_capabilities(&self, id: &Id) -> Option<Capabilities> {
+        self.actuator_capabilities.lock().unwrap().get(id).cloned()
+    }
+
+    /// Returns the `Id` of every discovered `Sensor` and `Actuator` (in that order, sorted by
+    /// `Id`), whether it is a `sensor` or an `actuator`, and its `Capabilities`.
+    pub fn devices(&self) -> Vec<(Id, &'static str, Option<Capabilities>)> {
+        let mut sensors: Vec<Id> = self.sensors.lock().unwrap().keys().cloned().collect();
+        let mut actuators: Vec<Id> = self.actuators.lock().unwrap().keys().cloned().collect();
+        sensors.sort_by(|a, b| a.0.cmp(&b.0));
+        actuators.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let sensors = sensors.into_iter().map(|id| {
+            let capabilities = self.sensor_capabilities(&id);
+            (id, "sensor", capabilities)
+        });
+
+        let actuators = actuators.into_iter().map(|id| {
+            let capabilities = self.actuator_capabilities(&id);
+            (id, "actuator", capabilities)
+        });
+
+        sensors.chain(actuators).collect()
+    }
+
+    /// Returns the `Schema` of the `Command`s accepted by the `Actuator` with the specified `Id`.
+    ///
+    /// If the `Actuator` didn't advertise its `Schema` when it was discovered, it is asked for it
+    /// the first time.
+    pub fn schema(&self, id: &Id) -> Result<Schema, String> {
+        let capabilities = self.actuator_capabilities(id);
+
+        if let Some(schema) = capabilities.as_ref().and_then(|c| c.commands.clone()) {
+            return Ok(schema);
+        }
+
+        let version = Self::negotiate(capabilities.as_ref())?;
+
+        if version < 2 {
+            return Err(format!(
+                "actuator '{}' speaks protocol version {}, which has no Schemas",
+                id, version
+            ));
+        }
+
+        if let Some(schema) = self.schemas.lock().unwrap().get(id) {
+            return Ok(schema.clone());
+        }
+
+        let info = self.actuators.lock().unwrap().get(id).cloned();
+        let info = info.ok_or_else(|| format!("unknown actuator '{}'", id))?;
+
+        let request = format!(
+            "GET /schema HTTP/1.1\r\n{}: {}\r\n\r\n",
+            protocol::HEADER,
+            version
         );
+        let response = State::send_request(&info, &request, self.config.timeout)
+            .map_err(|err| err.to_string())?;
+        State::check_version(&response, version)?;
+        let schema = Schema::parse(device::http::body(&response)?)?;
+
+        let mut schemas = self.schemas.lock().unwrap();
+        schemas.insert(id.clone(), schema.clone());
 
-        // parse the response and return it
-        Datum::parse(response.lines().last().unwrap_or_default())
+        Ok(schema)
     }
 
-    #[allow(dead_code)] // remove this ASAP
-    pub fn command_actuator(info: &ServiceInfo, command_json: String) -> std::io::Result<()> {
+    /// Sends a `Command` to an `Actuator` in the specified protocol `version`, returning the
+    /// status line of its response, or an error if the `Actuator` rejected the `Command` (or
+    /// failed to carry it out).
+    pub fn command_actuator(
+        info: &ServiceInfo,
+        version: u32,
+        command_json: String,
+        timeout: Duration,
+    ) -> Result<String, String> {
         let content_type = "application/json";
         let content_length = command_json.len();
 
         // Place the serialized command inside the POST payload
         let request = format!(
-            "POST HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
-            content_type, content_length, command_json
+            "POST HTTP/1.1\r\n{}: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+            protocol::HEADER,
+            version,
+            content_type,
+            content_length,
+            command_json
         );
 
-        let response = State::send_request(info, request.as_str());
+        let response =
+            State::send_request(info, request.as_str(), timeout).map_err(|e| e.to_string())?;
 
-        println!(
-            "[command_actuator] response from url {}:{}\n----------\n{}\n----------",
-            info.get_hostname().trim_end_matches('.'),
-            info.get_port(),
-            response
+        trace!(
+            host = info.get_hostname().trim_end_matches('.'),
+            port = info.get_port(),
+            response,
+            "response from actuator"
         );
 
-        Ok(())
+        device::http::body(&response)?;
+        State::check_version(&response, version)?;
+
+        Ok(response.lines().next().unwrap_or_default().to_string())
     }
 
+    /// Creates a new thread to poll all known `Sensor`s for data every `poll_interval`, saving it
+    /// in the histories.
+    ///
+    /// Each channel of a multi-channel `Sensor` is saved as a separate series, keyed by its own
+    /// `Id` (see `Id::channel`), so it can be paired, used in rules, and alerted on like any
+    /// single-channel `Sensor`.
+    ///
+    /// `Sensor`s which have pushed a `Reading` within the last `poll_interval` aren't polled, as
+    /// their data is already fresh; see `State::receive`. Nor are `Sensor`s which stream their
+    /// `Reading`s, unless their stream drops; see `State::stream`.
     pub fn poll(&self) -> JoinHandle<()> {
-        let mutex = Arc::clone(&self.sensors);
+        let state = self.clone();
+        let (interval, timeout) = (self.config.poll_interval, self.config.timeout);
 
         std::thread::spawn(move || {
             loop {
-                // We put the lock_result in this inner scope so the lock is released at the end of the scope
-                {
-                    let lock_result = mutex.lock();
-                    let mutex_guard = lock_result.unwrap();
+                // clone the sensors so the lock isn't held while we wait for them to respond
+                let sensors = state.sensors.lock().unwrap().clone();
+
+                trace!(sensors = sensors.len(), "polling sensors");
 
-                    println!("known sensors: {}", mutex_guard.len());
+                for (id, service_info) in sensors.iter() {
+                    let pushed = state.pushes.lock().unwrap().get(id).copied();
 
-                    for (id, service_info) in mutex_guard.iter() {
-                        println!("[poll] polling sensor with id {}", id);
+                    if pushed.is_some_and(|pushed| pushed.elapsed() < interval) {
+                        trace!(%id, "sensor recently pushed a reading, skipping poll");
+                        continue;
+                    }
+
+                    let advertised = state.sensor_capabilities(id);
+
+                    if state.stream(id, service_info, advertised.as_ref()) {
+                        continue;
+                    }
 
-                        Self::read_sensor(service_info).unwrap();
+                    let started = Instant::now();
+
+                    let result = Self::negotiate(advertised.as_ref()).and_then(|version| {
+                        let reading = Self::read_sensor(service_info, version, timeout)?;
+                        Self::check_reading(advertised.as_ref(), version, reading)
+                    });
+                    let name = Self::extract_name(service_info);
+                    state
+                        .metrics
+                        .record_poll(id, &name, &result, started.elapsed());
+
+                    if let Ok(reading) = &result {
+                        debug!(%id, name, %reading, "polled sensor");
                     }
+
+                    state.record_reading(id, &name, result)
                 }
 
-                // When the lock_result is released, we pause for a second, so self.sensors isn't continually locked
-                std::thread::sleep(Duration::from_secs(1))
+                // pause, so the sensors aren't continually polled
+                std::thread::sleep(interval)
             }
         })
     }
+
+    /// Returns an error if a `Reading` from a `Sensor` doesn't match what it advertised, as a
+    /// `Reading` like that is as good as none.
+    fn check_reading(
+        advertised: Option<&Capabilities>,
+        version: u32,
+        reading: Reading,
+    ) -> Result<Reading, String> {
+        // Sensors which speak version 1 only ever report a single, unnamed channel
+        match advertised
+            .filter(|_| version > 1)
+            .map(|c| c.check(&reading))
+        {
+            Some(Err(msg)) => Err(format!("unexpected reading: {}", msg)),
+            _ => Ok(reading),
+        }
+    }
+
+    /// Saves a polled or streamed `Reading` from the `Sensor` with the specified `Id`, or records
+    /// that it couldn't be read.
+    fn record_reading(&self, id: &Id, name: &str, result: Result<Reading, String>) {
+        match result {
+            Ok(reading) => {
+                Self::save(&self.histories, &self.store, id, &reading);
+                Self::responded(&self.failing, id, true)
+            }
+            Err(msg) => {
+                warn!(%id, name, error = msg, "could not read sensor");
+                Self::responded(&self.failing, id, false)
+            }
+        }
+    }
+
+    /// Subscribes to the stream of `Reading`s from the `Sensor` with the specified `Id`, if it
+    /// speaks a protocol version which supports streaming (see `protocol::STREAM_VERSION`),
+    /// returning `true` if its `Reading`s are being streamed, so it needn't be polled.
+    ///
+    /// Each stream is read on its own thread, over a single connection, rather than opening a new
+    /// one for every `Reading`. If the stream drops, the `Sensor` is polled instead, until it is
+    /// subscribed to again after `RESUBSCRIBE_AFTER`.
+    fn stream(&self, id: &Id, info: &ServiceInfo, advertised: Option<&Capabilities>) -> bool {
+        let version = match Self::negotiate(advertised) {
+            Ok(version) if version >= protocol::STREAM_VERSION => version,
+            _ => return false,
+        };
+
+        let mut streams = self.streams.lock().unwrap();
+
+        match streams.get(id) {
+            Some(None) => return true,
+            Some(Some(dropped)) if dropped.elapsed() < RESUBSCRIBE_AFTER => return false,
+            _ => streams.insert(id.clone(), None),
+        };
+
+        drop(streams);
+
+        let state = self.clone();
+        let (id, info, advertised) = (id.clone(), info.clone(), advertised.cloned());
+
+        std::thread::spawn(move || {
+            let name = Self::extract_name(&info);
+
+            // the Sensor sends its latest Reading at least once per poll interval (or heartbeat),
+            // so if nothing at all arrives for longer than that, the stream has stalled
+            let timeout = state.config.timeout;
+            let options = StreamOptions {
+                deadband: state.config.stream_deadband,
+                interval: state.config.poll_interval,
+            };
+            let connection = Self::connect(&info, timeout)
+                .and_then(|connection| {
+                    connection.set_read_timeout(Some(timeout + sensor::HEARTBEAT))?;
+                    Ok(connection)
+                })
+                .map_err(|err| err.to_string());
+
+            let reason = match connection {
+                Err(msg) => msg,
+                Ok(connection) => {
+                    info!(%id, name, "subscribed to sensor");
+
+                    stream::subscribe(connection, version, options, |result| {
+                        let result = result.and_then(|reading| {
+                            Self::check_reading(advertised.as_ref(), version, reading)
+                        });
+                        state.metrics.record_streamed(&id, &name, &result);
+
+                        if let Ok(reading) = &result {
+                            debug!(%id, name, %reading, "sensor streamed reading");
+                        }
+
+                        state.record_reading(&id, &name, result)
+                    })
+                }
+            };
+
+            warn!(%id, name, reason, "sensor stream dropped, falling back to polling");
+            let mut streams = state.streams.lock().unwrap();
+            streams.insert(id, Some(Instant::now()));
+        });
+
+        true
+    }
+
+    /// Accepts a `Reading` pushed to the `Controller` by the `Sensor` with the specified `Id`, in
+    /// the specified protocol `version`, saving it just like a polled `Reading`.
+    ///
+    /// To keep the same backpressure as polling, each `Sensor` may only push once every
+    /// `min_push_interval`; pushes any sooner are rejected, and the `Sensor` should back off.
+    pub fn receive(&self, id: &Id, version: u32, reading: Reading) -> Result<(), Rejection> {
+        let Some(info) = self.sensors.lock().unwrap().get(id).cloned() else {
+            return Err(Rejection::Unknown);
+        };
+
+        let name = Self::extract_name(&info);
+
+        let result = self.admit(id, version, &reading);
+        self.metrics
+            .record_push(id, &name, result.as_ref().ok().map(|_| &reading));
+
+        if let Err(rejection) = result {
+            debug!(%id, name, ?rejection, "rejected pushed reading");
+            return Err(rejection);
+        }
+
+        debug!(%id, name, %reading, "sensor pushed reading");
+
+        Self::save(&self.histories, &self.store, id, &reading);
+        Self::responded(&self.failing, id, true);
+        Ok(())
+    }
+
+    /// Returns an error if a `Reading` pushed by the `Sensor` with the specified `Id` came too soon
+    /// after its last one, or doesn't match what the `Sensor` advertised.
+    fn admit(&self, id: &Id, version: u32, reading: &Reading) -> Result<(), Rejection> {
+        let now = Instant::now();
+        let mut pushes = self.pushes.lock().unwrap();
+
+        if let Some(pushed) = pushes.get(id) {
+            let elapsed = now.duration_since(*pushed);
+
+            if elapsed < self.config.min_push_interval {
+                return Err(Rejection::TooSoon(self.config.min_push_interval - elapsed));
+            }
+        }
+
+        pushes.insert(id.clone(), now);
+        drop(pushes);
+
+        // as when polling, Sensors which speak version 1 only ever report a single, unnamed channel
+        match self.sensor_capabilities(id).filter(|_| version > 1) {
+            Some(capabilities) => capabilities.check(reading).map_err(Rejection::Invalid),
+            None => Ok(()),
+        }
+    }
+
+    /// Saves each channel of a `Reading` from the `Sensor` with the specified `Id` in its own
+    /// history, and persists it to the `Store` (if there is one).
+    fn save(
+        histories: &Mutex<HashMap<Id, SensorHistory>>,
+        store: &Mutex<Option<Store>>,
+        id: &Id,
+        reading: &Reading,
+    ) {
+        let data = reading.data();
+
+        let mut locked = histories.lock().unwrap();
+        for (channel, datum) in data.iter() {
+            let series = id.channel(channel);
+            locked.entry(series).or_default().push(datum.clone());
+        }
+        drop(locked);
+
+        for (channel, datum) in data {
+            let record = Record::Datum {
+                id: id.channel(channel).to_string(),
+                datum: datum.to_string(),
+            };
+
+            State::append(store, record)
+        }
+    }
+
+    /// Records whether a device responded, so devices which have gone offline can be detected.
+    fn responded(failing: &Mutex<HashMap<Id, DateTime<Utc>>>, id: &Id, responded: bool) {
+        let mut failing = failing.lock().unwrap();
+
+        if responded {
+            failing.remove(id);
+        } else {
+            failing.entry(id.clone()).or_insert_with(Utc::now);
+        }
+    }
+
+    /// Records whether the device with the specified `Id` responded to the `Controller`.
+    pub(crate) fn record_response(&self, id: &Id, responded: bool) {
+        Self::responded(&self.failing, id, responded)
+    }
+
+    /// Decides which `Command` (if any) should be sent to each known `Actuator`.
+    ///
+    /// A `Command` from the rules takes precedence over the `ControlStrategy` of the `Zone` the
+    /// `Actuator` belongs to; see `Pairings` for how `Sensor`s and `Actuator`s are grouped.
+    /// `Command`s which an `Actuator` has advertised it doesn't accept are never sent to it, and
+    /// `Zone`s whose `Actuator`s can't heat or cool have no `ControlStrategy`.
+    fn decide(&self, now: DateTime<Utc>) -> Vec<Dispatch> {
+        let histories = self.histories.lock().unwrap();
+        let actuators = self.actuators.lock().unwrap();
+        let commands = self.commands.lock().unwrap();
+        let mut strategies = self.strategies.lock().unwrap();
+        let schedules = self.schedules.lock().unwrap();
+        let rules = self.rules.lock().unwrap();
+        let mut adjustments = self.adjustments.lock().unwrap();
+        let pairings = self.pairings.lock().unwrap();
+        let mut intents = self.intents.lock().unwrap();
+        let mut targets = self.targets.lock().unwrap();
+        let capabilities = self.actuator_capabilities.lock().unwrap();
+        let schemas = self.schemas.lock().unwrap();
+
+        let latest: HashMap<Id, Datum> = histories
+            .iter()
+            .filter_map(|(id, history)| Some((id.clone(), history.data.last()?.clone())))
+            .collect();
+
+        let ids: Vec<Id> = actuators.keys().cloned().collect();
+        let outcome = rules.evaluate(&latest, &ids);
+
+        let readings = |ids: &[Id]| -> Vec<(Id, Datum)> {
+            ids.iter()
+                .filter_map(|id| Some((id.clone(), latest.get(id)?.clone())))
+                .collect()
+        };
+
+        // Commands an Actuator has said it won't accept (e.g. HeatTo for a smart plug) aren't sent
+        let accepts = |id: &Id, command: &GenericCommand| {
+            let Some(Err(msg)) = capabilities.get(id).map(|c| c.accepts(command)) else {
+                return true;
+            };
+
+            debug!(%id, %command, error = msg, "actuator does not accept command");
+            false
+        };
+
+        // Actuators which don't describe their Commands predate Schemas, so control temperature
+        let schema = |id: &Id| {
+            capabilities
+                .get(id)
+                .and_then(|c| c.commands.clone())
+                .or_else(|| schemas.get(id).cloned())
+                .unwrap_or_else(strategy::temperature_schema)
+        };
+
+        let mut decisions = Vec::new();
+
+        for (id, (command, rule)) in outcome.commands.iter() {
+            let Some(info) = actuators.get(id) else {
+                continue;
+            };
+
+            if commands.get(id).map(|(last, _)| last) != Some(command) && accepts(id, command) {
+                let inputs = rules
+                    .rules()
+                    .iter()
+                    .find(|r| r.name == *rule)
+                    .map(|r| rules.inputs(r))
+                    .unwrap_or_default();
+
+                decisions.push(Dispatch {
+                    id: id.clone(),
+                    info: info.clone(),
+                    command: command.clone(),
+                    trigger: Trigger::Rule(rule.clone()),
+                    readings: readings(&inputs),
+                });
+            }
+        }
+
+        for zone in pairings.resolve(&ids) {
+            let key = zone.key();
+
+            let members: Vec<&[Datum]> = zone
+                .sensors
+                .iter()
+                .filter_map(|id| histories.get(id))
+                .map(|history| history.data.as_slice())
+                .collect();
+
+            let history = zone.aggregate(&members);
+
+            if history.is_empty() {
+                continue;
+            }
+
+            // Zones whose Actuators can't heat or cool (e.g. lights) have no temperature to keep
+            let Some(schema) = zone.actuators.iter().map(schema).find(strategy::controls) else {
+                continue;
+            };
+
+            let strategy = strategies
+                .entry(key.clone())
+                .or_insert_with(|| Box::<BangBang>::default());
+
+            // the schedule (or an override) determines the base setpoint; rules may adjust it
+            let applied = adjustments.remove(&key).unwrap_or(0.0);
+            let scheduled = schedules.get(&key).and_then(|s| s.active(now));
+
+            let base = match scheduled {
+                Some((setpoint, _)) => setpoint,
+                None => strategy.setpoint() - applied,
+            };
+
+            let adjustment = std::iter::once(&key)
+                .chain(zone.actuators.iter())
+                .find_map(|id| outcome.adjustments.get(id))
+                .copied()
+                .unwrap_or(0.0);
+
+            // otherwise, the strategy keeps its own setpoint (e.g. a strategy::Schedule's profile)
+            if scheduled.is_some() || adjustment != 0.0 || applied != 0.0 {
+                strategy.set_setpoint(base + adjustment);
+            }
+
+            if adjustment != 0.0 {
+                adjustments.insert(key.clone(), adjustment);
+            }
+
+            if let Some(command) = strategy.decide(&history, intents.get(&key), &schema) {
+                intents.insert(key.clone(), command);
+            }
+
+            let Some(intent) = intents.get(&key) else {
+                continue;
+            };
+
+            let current = history.last().and_then(|datum| datum.get_as_float());
+
+            if let (Some(target), Some(current)) = (strategy::target(intent), current) {
+                targets.insert(key.clone(), (target, current));
+            }
+
+            // Actuators commanded by a rule are left alone; once a rule no longer applies (or if a
+            // Command couldn't be sent), the Actuator is sent the intent of its Zone again
+            for id in zone.actuators.iter() {
+                let Some(info) = actuators.get(id) else {
+                    continue;
+                };
+
+                if outcome.commands.contains_key(id) {
+                    continue;
+                }
+
+                let command = intent.clone();
+
+                if commands.get(id) != Some(&(command.clone(), None)) && accepts(id, &command) {
+                    decisions.push(Dispatch {
+                        id: id.clone(),
+                        info: info.clone(),
+                        command,
+                        trigger: Trigger::Strategy(zone.name.clone()),
+                        readings: readings(&zone.sensors),
+                    });
+                }
+            }
+        }
+
+        decisions
+    }
+
+    /// Sends the `Command` decided upon for each `Actuator`; see `State::decide`.
+    pub fn control(&self) {
+        let decisions = self.decide(Utc::now());
+
+        // send commands after releasing the locks, so the other threads aren't blocked
+        // (failures are logged and audited, and the Command is retried on the next iteration)
+        for dispatch in decisions {
+            let _ = self.dispatch(dispatch);
+        }
+    }
+
+    /// Sends a `Command` to the `Actuator` with the specified `Id` on behalf of a user, returning
+    /// the status line of the `Actuator`'s response.
+    ///
+    /// If the `Actuator` belongs to a `Zone`, its `ControlStrategy` takes over again on the next
+    /// iteration of the control loop; to change the setpoint for a while, use `State::hold`.
+    pub fn command_manually(&self, id: &Id, command: GenericCommand) -> Result<String, String> {
+        let info = self.actuators.lock().unwrap().get(id).cloned();
+        let info = info.ok_or_else(|| format!("unknown actuator '{}'", id))?;
+
+        self.dispatch(Dispatch {
+            id: id.clone(),
+            info,
+            command,
+            trigger: Trigger::Manual,
+            readings: Vec::new(),
+        })
+    }
+
+    /// Sends a `Command` to an `Actuator`, recording it (and the outcome) in the audit log.
+    fn dispatch(&self, dispatch: Dispatch) -> Result<String, String> {
+        let Dispatch {
+            id,
+            info,
+            command,
+            trigger,
+            readings,
+        } = dispatch;
+
+        info!(%id, %trigger, %command, "commanding actuator");
+
+        let at = Utc::now();
+        let started = std::time::Instant::now();
+        let status =
+            Self::negotiate(self.actuator_capabilities(&id).as_ref()).and_then(|version| {
+                Self::command_actuator(&info, version, command.to_string(), self.config.timeout)
+            });
+        let latency = started.elapsed();
+
+        self.record_response(&id, status.is_ok());
+        self.metrics.record_command(&id, status.is_ok());
+
+        match &status {
+            Ok(_) => {
+                let rule = trigger.rule().map(String::from);
+
+                self.record(Record::Command {
+                    id: id.to_string(),
+                    command: command.to_string(),
+                    rule: rule.clone(),
+                    at: at.to_rfc3339(),
+                });
+
+                let mut commands = self.commands.lock().unwrap();
+                commands.insert(id.clone(), (command.clone(), rule));
+            }
+            Err(err) => warn!(%id, %trigger, %command, error = err, "could not command actuator"),
+        }
+
+        let entry = Entry {
+            at,
+            actuator: id,
+            command,
+            trigger,
+            readings,
+            status: status.clone(),
+            latency,
+        };
+
+        self.record(Record::from(&entry));
+        self.audit.lock().unwrap().push(entry);
+
+        status
+    }
+
+    /// Replaces the `AlertConfig`, forgetting any `Alert`s which are firing.
+    pub fn set_alerts(&self, config: AlertConfig) {
+        let webhooks = config.webhooks.iter();
+        *self.webhooks.lock().unwrap() = webhooks.flat_map(|url| WebhookSink::new(url)).collect();
+        *self.alerter.lock().unwrap() = Alerter::new(config);
+    }
+
+    /// Sends `Alert`s to the `sink` (as well as the log, the API, and any webhooks).
+    pub fn add_alert_sink(&self, sink: Box<dyn AlertSink>) {
+        self.sinks.lock().unwrap().push(sink)
+    }
+
+    /// Returns the `Alert`s which are currently firing, oldest first.
+    pub fn alerts(&self) -> Vec<Alert> {
+        let alerter = self.alerter.lock().unwrap();
+        alerter.firing().into_iter().cloned().collect()
+    }
+
+    /// Returns a stream of `Alert`s (as JSON), as they fire or are resolved.
+    pub fn subscribe_to_alerts(&self) -> std::sync::mpsc::Receiver<String> {
+        self.alert_stream.subscribe()
+    }
+
+    /// Checks for problems, sending every `Alert` which fires or is resolved to the sinks.
+    pub fn check_alerts(&self, now: DateTime<Utc>) {
+        let latest = self
+            .histories
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, history)| Some((id.clone(), history.data.last()?.clone())))
+            .collect();
+
+        let observations = Observations {
+            latest,
+            failing: self.failing.lock().unwrap().clone(),
+            zones: self.targets.lock().unwrap().clone(),
+        };
+
+        let changes = self.alerter.lock().unwrap().evaluate(now, &observations);
+
+        // notify the sinks after releasing the other locks; webhooks only queue each alert, and
+        // deliver it on their own threads
+        let mut sinks = self.sinks.lock().unwrap();
+        let mut webhooks = self.webhooks.lock().unwrap();
+
+        for alert in changes.iter() {
+            let sinks = sinks
+                .iter_mut()
+                .map(|sink| sink.as_mut() as &mut dyn AlertSink);
+            let webhooks = webhooks.iter_mut().map(|sink| sink as &mut dyn AlertSink);
+
+            for sink in sinks.chain(webhooks) {
+                if let Err(msg) = sink.notify(alert) {
+                    warn!(key = alert.key, error = msg, "could not send alert")
+                }
+            }
+        }
+    }
+
+    /// Renders the `Controller`'s metrics in the Prometheus text exposition format.
+    pub fn metrics(&self) -> String {
+        self.metrics.render()
+    }
+
+    /// Returns the audit log entries which match the `query`, oldest first.
+    pub fn audit(&self, query: &Query) -> Vec<Entry> {
+        let audit = self.audit.lock().unwrap();
+        audit.query(query).into_iter().cloned().collect()
+    }
 }
 
-#[allow(dead_code)] // remove ASAP
+/// The most recent `Datum`s received from a single `Sensor`, oldest first.
+#[derive(Default)]
 struct SensorHistory {
-    id: Id,
     data: Vec<Datum>,
 }
+
+impl SensorHistory {
+    /// Appends a `Datum`, discarding the oldest one if the history is full.
+    fn push(&mut self, datum: Datum) {
+        if self.data.len() >= HISTORY_LENGTH {
+            self.data.remove(0);
+        }
+        self.data.push(datum)
+    }
+}
+
+#[cfg(test)]
+mod state_tests {
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use chrono::{NaiveTime, TimeZone};
+
+    use datum::{DatumUnit, DatumValue};
+
+    use super::*;
+
+    /// Creates the `ServiceInfo` of a device in the mDNS `group` which listens on the `port` of
+    /// localhost, and advertises the `properties`.
+    fn service_info(group: &str, port: u16, properties: &[(&str, &str)]) -> ServiceInfo {
+        let properties: HashMap<String, String> = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        ServiceInfo::new(group, "test", "localhost.", "127.0.0.1", port, properties).unwrap()
+    }
+
+    /// Starts a stand-in for a device on a free port, which answers one request with each of the
+    /// `responses` in turn. Returns the port, and a handle which returns the requests it received.
+    fn stand_in(responses: &[&'static str]) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let responses = responses.to_vec();
+
+        let device = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut buffer = [0; 1024];
+                    let read = stream.read(&mut buffer).unwrap();
+                    stream.write_all(response.as_bytes()).unwrap();
+                    String::from_utf8_lossy(&buffer[..read]).to_string()
+                })
+                .collect()
+        });
+
+        (port, device)
+    }
+
+    /// Advertises the `Capabilities` in the `info` as those of the `Actuator` with the `id`.
+    fn advertise(state: &State, id: &Id, info: &ServiceInfo) {
+        let capabilities = Capabilities::parse(info).unwrap();
+        let mut advertised = state.actuator_capabilities.lock().unwrap();
+        advertised.insert(id.clone(), capabilities);
+    }
+
+    fn heat(target: f32) -> GenericCommand {
+        GenericCommand::new(strategy::HEAT_TO, Some(DatumValue::Float(target)))
+    }
+
+    /// Creates a `State` with a single sensor-actuator pair, which has one temperature reading.
+    fn state(id: &Id, temperature: f32) -> State {
+        let state = State::new();
+
+        let info = service_info("_actuator._tcp.local.", 9898, &[]);
+        state.actuators.lock().unwrap().insert(id.clone(), info);
+
+        let datum = Datum::new_now(temperature, DatumUnit::DegreesC);
+        let mut histories = state.histories.lock().unwrap();
+        histories.entry(id.clone()).or_default().push(datum);
+        drop(histories);
+
+        state
+    }
+
+    fn commands(decisions: Vec<Dispatch>) -> Vec<Decision> {
+        decisions
+            .into_iter()
+            .map(|dispatch| {
+                let rule = dispatch.trigger.rule().map(String::from);
+                (dispatch.command, rule)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_name() {
+        let info = ServiceInfo::new(
+            "_sensor._tcp.local.",
+            "Kitchen Thermostat",
+            "localhost.local.",
+            "127.0.0.1",
+            8787,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(State::extract_name(&info), "Kitchen Thermostat");
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = SensorHistory::default();
+
+        for value in 0..(HISTORY_LENGTH + 5) {
+            history.push(Datum::new_now(value as i32, DatumUnit::Unitless));
+        }
+
+        assert_eq!(history.data.len(), HISTORY_LENGTH);
+        assert_eq!(history.data[0].get_as_int(), Some(5));
+    }
+
+    #[test]
+    /// A device can't use an `Id` which could be mistaken for another device's channel
+    fn test_extract_id() {
+        let info = service_info("_sensor._tcp.local.", 8787, &[("id", "probe")]);
+        assert_eq!(State::extract_id(&info), Ok(Id::new("probe")));
+
+        let info = service_info("_sensor._tcp.local.", 8787, &[("id", "probe:co2")]);
+        assert!(State::extract_id(&info).is_err());
+    }
+
+    #[test]
+    /// Each channel of a multi-channel `Sensor` is saved as its own series
+    fn test_save_channels() {
+        let state = State::new();
+        let probe = Id::new("probe");
+
+        let reading = Reading::new(Utc::now())
+            .with("temperature", 18.5, DatumUnit::DegreesC)
+            .and_then(|r| r.with("co2", 700, DatumUnit::PartsPerMillion))
+            .unwrap();
+
+        State::save(&state.histories, &state.store, &probe, &reading);
+        State::save(&state.histories, &state.store, &probe, &reading);
+
+        let histories = state.histories.lock().unwrap();
+        let temperature = &histories[&Id::new("probe:temperature")].data;
+        let co2 = &histories[&Id::new("probe:co2")].data;
+
+        assert!(!histories.contains_key(&probe));
+        assert_eq!(temperature.len(), 2);
+        assert_eq!(temperature[0].get_as_float(), Some(18.5));
+        assert_eq!(co2[1].unit, DatumUnit::PartsPerMillion);
+        assert_eq!(co2[1].timestamp, temperature[1].timestamp);
+    }
+
+    #[test]
+    /// Pushed `Reading`s are saved like polled ones, but each `Sensor` is rate-limited
+    fn test_receive() {
+        let state = State::with_config(ControllerConfig {
+            min_push_interval: Duration::from_secs(60),
+            ..ControllerConfig::default()
+        });
+
+        let probe = Id::new("probe");
+
+        let measures = "temperature:float@°C;co2:float@ppm";
+        let info = service_info("_sensor._tcp.local.", 8787, &[("measures", measures)]);
+
+        let capabilities = Capabilities::parse(&info).unwrap();
+        state.sensors.lock().unwrap().insert(probe.clone(), info);
+        let mut advertised = state.sensor_capabilities.lock().unwrap();
+        advertised.insert(probe.clone(), capabilities);
+        drop(advertised);
+
+        let reading = Reading::new(Utc::now())
+            .with("temperature", 18.5, DatumUnit::DegreesC)
+            .and_then(|r| r.with("co2", 700, DatumUnit::PartsPerMillion))
+            .unwrap();
+
+        assert_eq!(state.receive(&probe, 2, reading.clone()), Ok(()));
+
+        let histories = state.histories.lock().unwrap();
+        assert_eq!(histories[&Id::new("probe:co2")].data.len(), 1);
+        drop(histories);
+
+        assert!(matches!(
+            state.receive(&probe, 2, reading.clone()),
+            Err(Rejection::TooSoon(wait)) if wait <= Duration::from_secs(60)
+        ));
+
+        // a Reading which doesn't match what the Sensor advertised is rejected
+        state.pushes.lock().unwrap().clear();
+        let partial = Reading::new(Utc::now())
+            .with("temperature", 18.5, DatumUnit::DegreesC)
+            .unwrap();
+
+        assert_eq!(
+            state.receive(&probe, 2, partial),
+            Err(Rejection::Invalid(String::from("missing co2:float@ppm")))
+        );
+
+        assert_eq!(
+            state.receive(&Id::new("stranger"), 2, reading),
+            Err(Rejection::Unknown)
+        );
+    }
+
+    #[test]
+    fn test_decide_with_strategy() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let decisions = commands(state.decide(Utc::now()));
+        assert_eq!(decisions, vec![(heat(21.0), None)]);
+    }
+
+    #[test]
+    /// Zones whose Actuators can't heat or cool aren't given a strategy, or a target temperature
+    fn test_decide_without_temperature_commands() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let properties = [
+            ("kind", "light"),
+            ("commands", "On;Off;DimTo:float@@0..100"),
+        ];
+        advertise(
+            &state,
+            &id,
+            &service_info("_actuator._tcp.local.", 9898, &properties),
+        );
+
+        assert!(state.decide(Utc::now()).is_empty());
+
+        assert!(!state.strategies.lock().unwrap().contains_key(&id));
+        assert!(state.intents.lock().unwrap().is_empty());
+        assert!(state.targets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    /// Actuators aren't sent commands they have advertised they don't accept
+    fn test_decide_with_capabilities() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let properties = [("kind", "plug"), ("commands", "On;Off;Toggle")];
+        advertise(
+            &state,
+            &id,
+            &service_info("_actuator._tcp.local.", 9898, &properties),
+        );
+
+        assert!(state.decide(Utc::now()).is_empty());
+
+        // but they are still sent commands they do accept
+        let rules = RuleSet::parse_toml(
+            r#"
+            [[rules]]
+            name = "too cold"
+            when = ["kitchen < 16"]
+            command = "On"
+            targets = ["kitchen"]
+            "#,
+        )
+        .unwrap();
+
+        state.set_rules(rules);
+
+        let decisions = commands(state.decide(Utc::now()));
+        let rule = Some(String::from("too cold"));
+        assert_eq!(decisions, vec![(GenericCommand::new("On", None), rule)]);
+    }
+
+    #[test]
+    /// A command from a rule takes precedence over the pair's strategy
+    fn test_decide_with_rule() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let rules = RuleSet::parse_toml(
+            r#"
+            [[rules]]
+            name = "too cold"
+            when = ["kitchen < 16"]
+            command = "HeatTo:25"
+            targets = ["kitchen"]
+            "#,
+        )
+        .unwrap();
+
+        state.set_rules(rules);
+
+        let decisions = commands(state.decide(Utc::now()));
+        let rule = Some(String::from("too cold"));
+        assert_eq!(
+            decisions,
+            vec![(GenericCommand::parse("HeatTo:25").unwrap(), rule)]
+        );
+    }
+
+    #[test]
+    /// Many sensors can drive many actuators through an explicit zone
+    fn test_decide_with_zone() {
+        let state = State::new();
+
+        for (id, temperature) in [("t1", 15.0), ("t2", 17.0), ("t3", 30.0)] {
+            let datum = Datum::new_now(temperature, DatumUnit::DegreesC);
+            let mut histories = state.histories.lock().unwrap();
+            histories.entry(Id::new(id)).or_default().push(datum);
+        }
+
+        for id in ["furnace", "fan"] {
+            let info = service_info("_actuator._tcp.local.", 9898, &[]);
+            state.actuators.lock().unwrap().insert(Id::new(id), info);
+        }
+
+        let pairings = Pairings::parse_toml(
+            r#"
+            [[zones]]
+            name = "living room"
+            sensors = ["t1", "t2"]
+            actuators = ["furnace", "fan"]
+            "#,
+        )
+        .unwrap();
+
+        state.set_pairings(pairings);
+
+        // the mean of t1 and t2 is 16°C, so both actuators are told to heat
+        let mut ids: Vec<String> = state
+            .decide(Utc::now())
+            .into_iter()
+            .map(|dispatch| {
+                assert_eq!(dispatch.command, heat(21.0));
+                dispatch.id.to_string()
+            })
+            .collect();
+
+        ids.sort();
+        assert_eq!(ids, vec!["fan", "furnace"]);
+    }
+
+    #[test]
+    /// A strategy::Schedule follows its own profile when the Zone has no SetpointSchedule
+    fn test_decide_with_schedule_strategy() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 19.0);
+
+        let entries = vec![
+            (NaiveTime::from_hms_opt(22, 0, 0).unwrap(), 17.0),
+            (NaiveTime::from_hms_opt(7, 0, 0).unwrap(), 21.0),
+        ];
+
+        let schedule = strategy::Schedule::new(entries, BangBang::new(0.0, 1.0));
+        state.set_strategy(id.clone(), Box::new(schedule));
+
+        let at = |hour: u32| Utc.with_ymd_and_hms(2024, 1, 15, hour, 0, 0).unwrap();
+
+        let reading = |temperature: f32, hour: u32| {
+            let datum = Datum::new(temperature, DatumUnit::DegreesC, at(hour));
+            let mut histories = state.histories.lock().unwrap();
+            histories.entry(id.clone()).or_default().push(datum);
+        };
+
+        // during the day, the setpoint is 21
+        reading(19.0, 8);
+        let decisions = commands(state.decide(at(8)));
+        assert_eq!(decisions, vec![(heat(21.0), None)]);
+
+        // at night, it's 17, so the same room is too warm
+        reading(19.0, 23);
+        let decisions = commands(state.decide(at(23)));
+        let cool = GenericCommand::new(strategy::COOL_TO, Some(DatumValue::Float(17.0)));
+        assert_eq!(decisions, vec![(cool, None)]);
+    }
+
+    #[test]
+    /// Adjustments are applied to the base setpoint, so they don't accumulate
+    fn test_adjustments_do_not_accumulate() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let rules = RuleSet::parse_toml(
+            r#"
+            [[rules]]
+            name = "frost"
+            when = ["kitchen < 16"]
+            adjust = 1.0
+            targets = ["all"]
+            "#,
+        )
+        .unwrap();
+
+        state.set_rules(rules);
+
+        for _ in 0..3 {
+            state.decide(Utc::now());
+        }
+
+        assert_eq!(state.adjustment(&id), 1.0);
+        assert_eq!(state.active_setpoint(&id), (22.0, Source::Strategy));
+
+        // when the rule no longer fires, we return to the base setpoint
+        state.set_rules(RuleSet::default());
+        state.decide(Utc::now());
+
+        assert_eq!(state.active_setpoint(&id), (21.0, Source::Strategy));
+    }
+
+    #[test]
+    /// Data, commands, overrides, and the audit log survive a restart of the Controller
+    fn test_restore_from_store() {
+        let path =
+            std::env::temp_dir().join(format!("controller-state-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let id = Id::new("kitchen");
+        let until = Utc::now() + chrono::Duration::hours(2);
+
+        {
+            let state = state(&id, 15.0);
+            let (store, _) = Store::open(&path, Default::default()).unwrap();
+            state.set_store(store, Snapshot::default());
+
+            let datum = Datum::new_now(16.0, DatumUnit::DegreesC);
+            state.record(Record::Datum {
+                id: id.to_string(),
+                datum: datum.to_string(),
+            });
+
+            state.hold(
+                &id,
+                Override {
+                    setpoint: 23.0,
+                    until,
+                },
+            );
+
+            // the furnace can't be commanded, but the attempt is still audited
+            let furnace = Id::new("furnace");
+            let info = service_info("_actuator._tcp.local.", 9, &[("protocol", "4..5")]);
+            advertise(&state, &furnace, &info);
+            state
+                .actuators
+                .lock()
+                .unwrap()
+                .insert(furnace.clone(), info);
+
+            let command = GenericCommand::parse("HeatTo:23").unwrap();
+            assert!(state.command_manually(&furnace, command).is_err());
+        }
+
+        let state = State::new();
+        let (store, snapshot) = Store::open(&path, Default::default()).unwrap();
+        state.set_store(store, snapshot);
+
+        let histories = state.histories.lock().unwrap();
+        assert_eq!(histories[&id].data[0].get_as_float(), Some(16.0));
+        drop(histories);
+
+        assert_eq!(state.active_setpoint(&id), (23.0, Source::Override(until)));
+
+        let entries = state.audit(&Query::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actuator, Id::new("furnace"));
+        assert_eq!(entries[0].trigger, Trigger::Manual);
+        assert!(entries[0].status.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    /// Sensors are asked for data in the negotiated protocol version, and must respond in it
+    fn test_read_sensor() {
+        // a stand-in for a Sensor, which responds in whichever version it pleases
+        let (port, sensor) = stand_in(&[
+            "HTTP/1.1 200 OK\r\n\r\n21.5@°C@2024-01-01T00:00:00Z",
+            "HTTP/1.1 200 OK\r\nProtocol-Version: 2\r\n\r\nco2=612@ppm@2024-01-01T00:00:00Z",
+            "HTTP/1.1 200 OK\r\nProtocol-Version: 1\r\n\r\n21.5@°C@2024-01-01T00:00:00Z",
+        ]);
+
+        let info = service_info("_sensor._tcp.local.", port, &[]);
+
+        let timeout = Duration::from_secs(1);
+
+        let reading = State::read_sensor(&info, 1, timeout).unwrap();
+        assert_eq!(reading.get("").unwrap().get_as_float(), Some(21.5));
+
+        let reading = State::read_sensor(&info, 2, timeout).unwrap();
+        assert_eq!(reading.get("co2").unwrap().get_as_int(), Some(612));
+
+        assert_eq!(
+            State::read_sensor(&info, 2, timeout),
+            Err(String::from(
+                "asked for protocol version 2, but the device responded in version 1"
+            ))
+        );
+
+        let versions: Vec<Option<String>> = sensor
+            .join()
+            .unwrap()
+            .iter()
+            .map(|request| device::http::header(request, protocol::HEADER).map(String::from))
+            .collect();
+
+        let expected = ["1", "2", "2"].map(|version| Some(String::from(version)));
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    /// Sensors which stream their Readings aren't polled, until their stream drops
+    fn test_stream() {
+        // a stand-in for a Sensor, which streams a single Reading and then hangs up
+        let (port, sensor) = stand_in(&[concat!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nProtocol-Version: 3\r\n\r\n",
+            "event: reading\ndata: 21.5@°C@2024-01-01T00:00:00Z\n\n",
+        )]);
+
+        let info = service_info("_sensor._tcp.local.", port, &[("protocol", "1..3")]);
+
+        let state = State::new();
+        let id = Id::new("kitchen");
+        let capabilities = Capabilities::parse(&info).unwrap();
+
+        assert!(state.stream(&id, &info, Some(&capabilities)));
+
+        // wait for the stream to drop
+        let started = Instant::now();
+        while state.streams.lock().unwrap()[&id].is_none() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let requests = sensor.join().unwrap();
+        // the Sensor is asked to send its latest Reading at least once per poll interval
+        assert!(requests[0].starts_with(
+            "GET /stream?deadband=0.5&interval=1000ms HTTP/1.1\r\nProtocol-Version: 3\r\n"
+        ));
+
+        let histories = state.histories.lock().unwrap();
+        assert_eq!(histories[&id].data[0].get_as_float(), Some(21.5));
+        drop(histories);
+
+        // so the Sensor is polled instead
+        assert!(!state.stream(&id, &info, Some(&capabilities)));
+
+        // Sensors which predate streaming are always polled
+        let mut legacy = capabilities.clone();
+        legacy.protocol = protocol::Versions::new(1, 2);
+        assert!(!state.stream(&Id::new("legacy"), &info, Some(&legacy)));
+    }
+
+    #[test]
+    /// Actuators which speak no protocol version in common with the Controller aren't commanded
+    fn test_command_incompatible_actuator() {
+        let state = State::new();
+        let id = Id::new("furnace");
+
+        // nothing is listening on this port, but the Controller shouldn't try to connect anyway
+        let info = service_info("_actuator._tcp.local.", 9, &[("protocol", "4..5")]);
+        advertise(&state, &id, &info);
+        state.actuators.lock().unwrap().insert(id.clone(), info);
+
+        let command = GenericCommand::parse("HeatTo:23").unwrap();
+        let expected = "no common protocol version (this end speaks 1..3, the other speaks 4..5)";
+
+        assert_eq!(
+            state.command_manually(&id, command),
+            Err(String::from(expected))
+        );
+        assert_eq!(state.schema(&id), Err(String::from(expected)));
+
+        let entries = state.audit(&Query::default());
+        assert_eq!(entries[0].status, Err(String::from(expected)));
+    }
+
+    #[test]
+    /// Every command is audited, with its trigger, the actuator's response, and its latency
+    fn test_audit_manual_command() {
+        // a stand-in for an Actuator, which acknowledges a single command
+        let (port, actuator) = stand_in(&["HTTP/1.1 200 OK\r\n\r\n"]);
+
+        let state = State::new();
+        let id = Id::new("furnace");
+        let info = service_info("_actuator._tcp.local.", port, &[]);
+        state.actuators.lock().unwrap().insert(id.clone(), info);
+
+        let command = GenericCommand::parse("HeatTo:23").unwrap();
+        let status = state.command_manually(&id, command.clone());
+        actuator.join().unwrap();
+
+        assert_eq!(status, Ok(String::from("HTTP/1.1 200 OK")));
+
+        let entries = state.audit(&Query::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].trigger, Trigger::Manual);
+        assert_eq!(entries[0].command, command);
+        assert_eq!(entries[0].status, status);
+
+        let commands = state.commands.lock().unwrap();
+        assert_eq!(commands[&id], (command, None));
+    }
+
+    #[test]
+    /// Commands which an Actuator rejects are failures, so they're retried, and not remembered
+    fn test_command_rejected() {
+        // a stand-in for an Actuator, which rejects a single command
+        let (port, actuator) =
+            stand_in(&["HTTP/1.1 400 Bad Request\r\n\r\n99 is not in the range 5..30"]);
+
+        let state = State::new();
+        let id = Id::new("furnace");
+        let info = service_info("_actuator._tcp.local.", port, &[]);
+        state.actuators.lock().unwrap().insert(id.clone(), info);
+
+        let command = GenericCommand::parse("HeatTo:99").unwrap();
+        let status = state.command_manually(&id, command);
+        actuator.join().unwrap();
+
+        let expected = "HTTP/1.1 400 Bad Request: 99 is not in the range 5..30";
+        assert_eq!(status, Err(String::from(expected)));
+
+        let entries = state.audit(&Query::default());
+        assert_eq!(entries[0].status, status);
+
+        assert!(state.commands.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    /// An Actuator is asked for its Schema once (unless it advertised it), after which the Schema
+    /// is remembered
+    fn test_schema() {
+        // a stand-in for an Actuator, which describes its commands once
+        let (port, actuator) = stand_in(&["HTTP/1.1 200 OK\r\n\r\nOn;Off;Toggle"]);
+
+        let state = State::new();
+        let id = Id::new("kettle");
+        let info = service_info("_actuator._tcp.local.", port, &[]);
+        state.actuators.lock().unwrap().insert(id.clone(), info);
+
+        let schema = state.schema(&id).unwrap();
+        assert!(actuator.join().unwrap()[0].starts_with("GET /schema "));
+
+        assert_eq!(schema.to_string(), "On;Off;Toggle");
+        assert_eq!(state.schema(&id), Ok(schema));
+        assert!(state.schema(&Id::new("unknown")).is_err());
+
+        // an Actuator which advertised its Schema is never asked for it
+        let lamp = Id::new("lamp");
+        let info = service_info("_actuator._tcp.local.", port, &[("commands", "On;Off")]);
+        advertise(&state, &lamp, &info);
+
+        assert_eq!(state.schema(&lamp).unwrap().to_string(), "On;Off");
+    }
+
+    #[test]
+    /// A strategy's decision is audited along with the readings of the sensors in its zone
+    fn test_audit_strategy_readings() {
+        let id = Id::new("kitchen");
+        let state = state(&id, 15.0);
+
+        let dispatch = state.decide(Utc::now()).remove(0);
+
+        assert_eq!(dispatch.trigger, Trigger::Strategy(String::from("kitchen")));
+        assert_eq!(dispatch.readings.len(), 1);
+        assert_eq!(dispatch.readings[0].1.get_as_float(), Some(15.0));
+    }
+}
@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use actuator::GenericCommand;
+use datum::Datum;
+use device::Id;
+
+use crate::audit::{Entry, Trigger, AUDIT_LENGTH};
+use crate::setpoint::Override;
+
+/// One line of the `Store`'s append-only log.
+///
+/// `Datum`s, `Command`s, and timestamps are stored in the same text formats used on the wire.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Record {
+    /// A `Datum` received from the `Sensor` with this `Id`
+    Datum { id: String, datum: String },
+    /// A `Command` successfully sent to the `Actuator` with this `Id`
+    Command {
+        id: String,
+        command: String,
+        rule: Option<String>,
+        at: String,
+    },
+    /// A temporary override of the setpoint of a `Zone`
+    Hold {
+        zone: String,
+        setpoint: f32,
+        until: String,
+    },
+    /// The override of the setpoint of a `Zone` was cancelled
+    CancelHold { zone: String },
+    /// The explicit `Pairings` were changed, and now look like this
+    Pairings { toml: String },
+    /// An attempt to send a `Command` to an `Actuator`, as recorded in the `AuditLog`
+    Audit {
+        at: String,
+        actuator: String,
+        command: String,
+        trigger: String,
+        readings: Vec<(String, String)>,
+        status: Option<String>,
+        error: Option<String>,
+        latency_ms: f64,
+    },
+}
+
+impl From<&Entry> for Record {
+    fn from(entry: &Entry) -> Record {
+        let (status, error) = match &entry.status {
+            Ok(status) => (Some(status.clone()), None),
+            Err(error) => (None, Some(error.clone())),
+        };
+
+        Record::Audit {
+            at: entry.at.to_rfc3339(),
+            actuator: entry.actuator.to_string(),
+            command: entry.command.to_string(),
+            trigger: entry.trigger.to_string(),
+            readings: (entry.readings.iter())
+                .map(|(id, datum)| (id.to_string(), datum.to_string()))
+                .collect(),
+            status,
+            error,
+            latency_ms: entry.latency.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Limits how much the `Store` keeps, so that it doesn't grow forever.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Retention {
+    /// `Datum`s and `Command`s older than this are discarded
+    pub max_age: Duration,
+    /// At most this many `Datum`s are kept for each `Sensor`
+    pub max_data_per_id: usize,
+    /// The log is compacted after this many records have been appended to it
+    pub compact_after: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::days(7),
+            max_data_per_id: 10_000,
+            compact_after: 50_000,
+        }
+    }
+}
+
+/// A `Command` sent to an `Actuator`, and the rule which sent it (if any).
+#[derive(PartialEq, Debug, Clone)]
+pub struct SentCommand {
+    pub at: DateTime<Utc>,
+    pub id: Id,
+    pub command: GenericCommand,
+    pub rule: Option<String>,
+}
+
+/// Everything the `Store` knows, after replaying its log.
+#[derive(PartialEq, Debug, Default)]
+pub struct Snapshot {
+    /// The `Datum`s received from each `Sensor`, oldest first
+    pub histories: HashMap<Id, Vec<Datum>>,
+    /// Every `Command` sent to any `Actuator`, oldest first
+    pub commands: Vec<SentCommand>,
+    /// The unexpired override of the setpoint of each `Zone`
+    pub holds: HashMap<Id, Override>,
+    /// The latest explicit `Pairings`, in TOML, if they were ever changed
+    pub pairings: Option<String>,
+    /// The most recent `AuditLog` `Entry`s, oldest first
+    pub audit: Vec<Entry>,
+}
+
+impl Snapshot {
+    /// Applies a `Record` to this `Snapshot`.
+    fn apply(&mut self, record: Record) -> Result<(), String> {
+        match record {
+            Record::Datum { id, datum } => {
+                let datum = Datum::parse(&datum)?;
+                self.histories.entry(Id::new(&id)).or_default().push(datum);
+            }
+            Record::Command {
+                id,
+                command,
+                rule,
+                at,
+            } => self.commands.push(SentCommand {
+                at: parse_timestamp(&at)?,
+                id: Id::new(&id),
+                command: GenericCommand::parse(&command)?,
+                rule,
+            }),
+            Record::Hold {
+                zone,
+                setpoint,
+                until,
+            } => {
+                let until = parse_timestamp(&until)?;
+                self.holds
+                    .insert(Id::new(&zone), Override { setpoint, until });
+            }
+            Record::CancelHold { zone } => {
+                self.holds.remove(&Id::new(&zone));
+            }
+            Record::Pairings { toml } => self.pairings = Some(toml),
+            Record::Audit {
+                at,
+                actuator,
+                command,
+                trigger,
+                readings,
+                status,
+                error,
+                latency_ms,
+            } => {
+                let readings = readings
+                    .iter()
+                    .map(|(id, datum)| Ok((Id::new(id), Datum::parse(datum)?)))
+                    .collect::<Result<Vec<(Id, Datum)>, String>>()?;
+
+                let status = match (status, error) {
+                    (Some(status), _) => Ok(status),
+                    (None, error) => Err(error.unwrap_or_default()),
+                };
+
+                self.audit.push(Entry {
+                    at: parse_timestamp(&at)?,
+                    actuator: Id::new(&actuator),
+                    command: GenericCommand::parse(&command)?,
+                    trigger: Trigger::parse(&trigger)?,
+                    readings,
+                    status,
+                    latency: std::time::Duration::from_secs_f64(latency_ms.max(0.0) / 1000.0),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discards everything which is outside of the `retention` policy, as of `now`.
+    fn retain(&mut self, retention: &Retention, now: DateTime<Utc>) {
+        let oldest = now - retention.max_age;
+
+        for history in self.histories.values_mut() {
+            history.retain(|datum| datum.timestamp >= oldest);
+
+            let excess = history.len().saturating_sub(retention.max_data_per_id);
+            history.drain(..excess);
+        }
+
+        self.histories.retain(|_, history| !history.is_empty());
+        self.commands.retain(|sent| sent.at >= oldest);
+        self.audit.retain(|entry| entry.at >= oldest);
+
+        let excess = self.audit.len().saturating_sub(AUDIT_LENGTH);
+        self.audit.drain(..excess);
+        self.holds.retain(|_, hold| hold.until > now);
+    }
+
+    /// Returns the smallest list of `Record`s which recreates this `Snapshot`.
+    fn records(&self) -> Vec<Record> {
+        let data = self.histories.iter().flat_map(|(id, history)| {
+            history.iter().map(|datum| Record::Datum {
+                id: id.to_string(),
+                datum: datum.to_string(),
+            })
+        });
+
+        let commands = self.commands.iter().map(|sent| Record::Command {
+            id: sent.id.to_string(),
+            command: sent.command.to_string(),
+            rule: sent.rule.clone(),
+            at: sent.at.to_rfc3339(),
+        });
+
+        let holds = self.holds.iter().map(|(zone, hold)| Record::Hold {
+            zone: zone.to_string(),
+            setpoint: hold.setpoint,
+            until: hold.until.to_rfc3339(),
+        });
+
+        let pairings = self
+            .pairings
+            .iter()
+            .map(|toml| Record::Pairings { toml: toml.clone() });
+
+        let audit = self.audit.iter().map(Record::from);
+
+        data.chain(commands)
+            .chain(holds)
+            .chain(pairings)
+            .chain(audit)
+            .collect()
+    }
+}
+
+fn parse_timestamp(string: &str) -> Result<DateTime<Utc>, String> {
+    string
+        .parse::<DateTime<Utc>>()
+        .map_err(|err| format!("{} ('{}')", err, string))
+}
+
+/// An embedded, append-only, on-disk store for the `Controller`'s state.
+///
+/// Every change is appended to a log file, one JSON `Record` per line. When the `Store` is
+/// opened, the log is replayed to recover the state, then compacted: everything outside of the
+/// `Retention` policy is discarded, and the log is rewritten with only what remains. The log is
+/// compacted again every `Retention::compact_after` records.
+pub struct Store {
+    path: PathBuf,
+    file: File,
+    retention: Retention,
+    /// The number of records appended since the log was last compacted
+    appended: usize,
+}
+
+impl Store {
+    /// Opens (or creates) the `Store` at `path`, returning it along with the state it contains.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        retention: Retention,
+    ) -> Result<(Store, Snapshot), String> {
+        let path = path.as_ref().to_path_buf();
+        let snapshot = Store::read(&path, &retention)?;
+
+        Store::write(&path, &snapshot)?;
+
+        let store = Store {
+            file: Store::append_to(&path)?,
+            path,
+            retention,
+            appended: 0,
+        };
+
+        Ok((store, snapshot))
+    }
+
+    /// Replays the log at `path` (if it exists), applying the `retention` policy.
+    fn read(path: &Path, retention: &Retention) -> Result<Snapshot, String> {
+        let mut snapshot = Snapshot::default();
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(snapshot),
+            Err(err) => return Err(format!("cannot read store {}: {}", path.display(), err)),
+        };
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|err| format!("cannot read store {}: {}", path.display(), err))?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // a line can be truncated if the Controller stopped in the middle of writing it, so
+            // bad lines are skipped rather than making the whole store unreadable
+            let result = serde_json::from_str::<Record>(&line)
+                .map_err(|err| err.to_string())
+                .and_then(|record| snapshot.apply(record));
+
+            if let Err(msg) = result {
+                warn!(
+                    line = index + 1,
+                    path = %path.display(),
+                    error = msg,
+                    "skipping unreadable line of store"
+                );
+            }
+        }
+
+        snapshot.retain(retention, Utc::now());
+        Ok(snapshot)
+    }
+
+    /// Atomically replaces the log at `path` with the minimal log for the `snapshot`.
+    fn write(path: &Path, snapshot: &Snapshot) -> Result<(), String> {
+        let error = |err: std::io::Error| format!("cannot write store {}: {}", path.display(), err);
+
+        let temporary = path.with_extension("compacting");
+        let mut file = File::create(&temporary).map_err(error)?;
+
+        for record in snapshot.records() {
+            let line = serde_json::to_string(&record).map_err(|err| err.to_string())?;
+            writeln!(file, "{}", line).map_err(error)?;
+        }
+
+        file.sync_all().map_err(error)?;
+        std::fs::rename(&temporary, path).map_err(error)
+    }
+
+    fn append_to(path: &Path) -> Result<File, String> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("cannot open store {}: {}", path.display(), err))
+    }
+
+    /// Appends a `Record` to the log, compacting the log if it has grown too large.
+    pub(crate) fn append(&mut self, record: &Record) -> Result<(), String> {
+        let line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+
+        writeln!(self.file, "{}", line)
+            .map_err(|err| format!("cannot write store {}: {}", self.path.display(), err))?;
+
+        self.appended += 1;
+
+        if self.appended >= self.retention.compact_after {
+            self.compact()?;
+        }
+
+        Ok(())
+    }
+
+    /// Discards everything outside of the `Retention` policy, and rewrites the log.
+    pub fn compact(&mut self) -> Result<(), String> {
+        let snapshot = Store::read(&self.path, &self.retention)?;
+        Store::write(&self.path, &snapshot)?;
+
+        self.file = Store::append_to(&self.path)?;
+        self.appended = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod store_tests {
+    use datum::DatumUnit;
+
+    use super::*;
+
+    /// Returns a path to a new, empty store in the system's temporary directory.
+    fn temporary(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "controller-store-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn datum(id: &str, value: f32, timestamp: DateTime<Utc>) -> Record {
+        Record::Datum {
+            id: String::from(id),
+            datum: Datum::new(value, DatumUnit::DegreesC, timestamp).to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = temporary("reload");
+        let now = Utc::now();
+
+        {
+            let (mut store, snapshot) = Store::open(&path, Retention::default()).unwrap();
+            assert_eq!(snapshot, Snapshot::default());
+
+            store.append(&datum("kitchen", 20.5, now)).unwrap();
+            store
+                .append(&Record::Command {
+                    id: String::from("kitchen"),
+                    command: String::from("HeatTo:21"),
+                    rule: None,
+                    at: now.to_rfc3339(),
+                })
+                .unwrap();
+            store
+                .append(&Record::Hold {
+                    zone: String::from("kitchen"),
+                    setpoint: 23.0,
+                    until: (now + Duration::hours(2)).to_rfc3339(),
+                })
+                .unwrap();
+            store
+                .append(&Record::Pairings {
+                    toml: String::from("zones = []"),
+                })
+                .unwrap();
+        }
+
+        let (_, snapshot) = Store::open(&path, Retention::default()).unwrap();
+        let kitchen = Id::new("kitchen");
+
+        let history = &snapshot.histories[&kitchen];
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].get_as_float(), Some(20.5));
+
+        assert_eq!(snapshot.commands[0].command.to_string(), "HeatTo:21");
+        assert_eq!(snapshot.holds[&kitchen].setpoint, 23.0);
+        assert_eq!(snapshot.pairings, Some(String::from("zones = []")));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_retention() {
+        let path = temporary("retention");
+        let now = Utc::now();
+
+        let retention = Retention {
+            max_age: Duration::hours(1),
+            max_data_per_id: 2,
+            compact_after: 1000,
+        };
+
+        {
+            let (mut store, _) = Store::open(&path, retention).unwrap();
+
+            store
+                .append(&datum("old", 1.0, now - Duration::hours(2)))
+                .unwrap();
+
+            for minute in 0..5 {
+                let record = datum("new", minute as f32, now - Duration::minutes(5 - minute));
+                store.append(&record).unwrap();
+            }
+
+            store
+                .append(&Record::Hold {
+                    zone: String::from("expired"),
+                    setpoint: 23.0,
+                    until: (now - Duration::minutes(1)).to_rfc3339(),
+                })
+                .unwrap();
+        }
+
+        let (_, snapshot) = Store::open(&path, retention).unwrap();
+
+        assert!(!snapshot.histories.contains_key(&Id::new("old")));
+        assert!(snapshot.holds.is_empty());
+
+        let values: Vec<f32> = snapshot.histories[&Id::new("new")]
+            .iter()
+            .filter_map(|datum| datum.get_as_float())
+            .collect();
+
+        assert_eq!(values, vec![3.0, 4.0]);
+
+        // the log was rewritten with only what was retained
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compact_after() {
+        let path = temporary("compact");
+        let now = Utc::now();
+
+        let retention = Retention {
+            max_data_per_id: 1,
+            compact_after: 3,
+            ..Retention::default()
+        };
+
+        let (mut store, _) = Store::open(&path, retention).unwrap();
+
+        for second in 0..3 {
+            let record = datum("kitchen", second as f32, now + Duration::seconds(second));
+            store.append(&record).unwrap();
+        }
+
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    /// A truncated or otherwise unreadable line doesn't prevent the rest of the store being read
+    fn test_skips_bad_lines() {
+        let path = temporary("bad");
+        let now = Utc::now();
+
+        let good = serde_json::to_string(&datum("kitchen", 20.0, now)).unwrap();
+        std::fs::write(&path, format!("{}\n{{\"type\":\"dat\n", good)).unwrap();
+
+        let (_, snapshot) = Store::open(&path, Retention::default()).unwrap();
+        assert_eq!(snapshot.histories[&Id::new("kitchen")].len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
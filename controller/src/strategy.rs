@@ -0,0 +1,521 @@
+use chrono::{DateTime, NaiveTime, Utc};
+
+use actuator::{GenericCommand, Parameter, Schema};
+use datum::{Datum, DatumUnit, DatumValue, DatumValueType};
+
+use crate::setpoint::Profile;
+
+/// The name of the `Command` which tells an `Actuator` to heat to a target temperature (in °C).
+pub const HEAT_TO: &str = "HeatTo";
+
+/// The name of the `Command` which tells an `Actuator` to cool to a target temperature (in °C).
+pub const COOL_TO: &str = "CoolTo";
+
+/// The `Schema` assumed for `Actuator`s which don't describe their `Command`s.
+///
+/// `Schema`s were introduced in version 2 of the protocol, before which every `Actuator` was a
+/// temperature actuator.
+pub fn temperature_schema() -> Schema {
+    let temperature = Parameter::new(DatumValueType::Float, DatumUnit::DegreesC);
+
+    Schema::new()
+        .command_with(COOL_TO, temperature)
+        .command_with(HEAT_TO, temperature)
+}
+
+/// Returns `true` if an `Actuator` with the specified `Schema` accepts any of the `Command`s a
+/// `ControlStrategy` sends; `Actuator`s which don't (e.g. lights) can't maintain a temperature.
+pub fn controls(schema: &Schema) -> bool {
+    schema.get(HEAT_TO).is_some() || schema.get(COOL_TO).is_some()
+}
+
+/// Returns the target temperature of a `HeatTo` or `CoolTo` `Command`.
+pub fn target(command: &GenericCommand) -> Option<f32> {
+    match command.name.as_str() {
+        HEAT_TO | COOL_TO => command.float().ok(),
+        _ => None,
+    }
+}
+
+/// Builds the `Command` with the specified `name` and `target` temperature, if the `schema` of the
+/// `Actuator` accepts it.
+fn build(schema: &Schema, name: &str, target: f32) -> Option<GenericCommand> {
+    schema.build(name, Some(DatumValue::Float(target))).ok()
+}
+
+/// A `ControlStrategy` decides how an `Actuator` should respond to the data from its `Sensor`.
+///
+/// Each sensor-actuator pair has its own strategy, so (for example) radiators can be controlled
+/// differently from heat pumps.
+pub trait ControlStrategy: Send {
+    /// Given a `Sensor`'s recent `history` (oldest first), the `last` command sent to its
+    /// `Actuator`, and the `Schema` of the commands the `Actuator` accepts, returns the next
+    /// command to send, or `None` if nothing needs to change (or the `Actuator` can't do it).
+    fn decide(
+        &mut self,
+        history: &[Datum],
+        last: Option<&GenericCommand>,
+        schema: &Schema,
+    ) -> Option<GenericCommand>;
+
+    /// Returns the temperature this strategy is trying to maintain.
+    fn setpoint(&self) -> f32;
+
+    /// Changes the temperature this strategy is trying to maintain.
+    fn set_setpoint(&mut self, setpoint: f32);
+}
+
+/// Returns the latest temperature in the `history`, and when it was measured.
+fn latest(history: &[Datum]) -> Option<(DateTime<Utc>, f32)> {
+    let datum = history.last()?;
+    Some((datum.timestamp, datum.get_as_float()?))
+}
+
+/// Returns the `command` (if it could be built) only if it is different from the `last` command.
+fn if_changed(
+    command: Option<GenericCommand>,
+    last: Option<&GenericCommand>,
+) -> Option<GenericCommand> {
+    if last == command.as_ref() {
+        None
+    } else {
+        command
+    }
+}
+
+/// Switches between heating and cooling only when the temperature leaves a band around the
+/// setpoint, so the `Actuator` doesn't flip back and forth when the temperature is close to it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BangBang {
+    pub setpoint: f32,
+    /// How far the temperature may drift from the setpoint (in either direction) before we react.
+    pub hysteresis: f32,
+}
+
+impl Default for BangBang {
+    fn default() -> Self {
+        Self {
+            setpoint: 21.0,
+            hysteresis: 1.0,
+        }
+    }
+}
+
+impl BangBang {
+    pub fn new(setpoint: f32, hysteresis: f32) -> BangBang {
+        BangBang {
+            setpoint,
+            hysteresis,
+        }
+    }
+}
+
+impl ControlStrategy for BangBang {
+    fn decide(
+        &mut self,
+        history: &[Datum],
+        last: Option<&GenericCommand>,
+        schema: &Schema,
+    ) -> Option<GenericCommand> {
+        let (_, temperature) = latest(history)?;
+
+        if temperature < self.setpoint - self.hysteresis {
+            if_changed(build(schema, HEAT_TO, self.setpoint), last)
+        } else if temperature > self.setpoint + self.hysteresis {
+            if_changed(build(schema, COOL_TO, self.setpoint), last)
+        } else if last.is_none() {
+            // we're within the band, but the Actuator has never been told what to do
+            if_changed(build(schema, HEAT_TO, self.setpoint), last)
+        } else {
+            None
+        }
+    }
+
+    fn setpoint(&self) -> f32 {
+        self.setpoint
+    }
+
+    fn set_setpoint(&mut self, setpoint: f32) {
+        self.setpoint = setpoint
+    }
+}
+
+/// A proportional-integral-derivative controller.
+///
+/// The output of the controller shifts the target sent to the `Actuator` away from the setpoint:
+/// when the room is persistently too cold, the `Actuator` is told to heat to a higher temperature,
+/// and vice versa. The shift is limited to `±limit`, and the integral term stops accumulating while
+/// the output is saturated (anti-windup), so the controller recovers quickly from large errors.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pid {
+    pub setpoint: f32,
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    /// The largest shift (in °C) the controller may apply to the setpoint.
+    pub limit: f32,
+    /// Don't send a new command unless its target differs from the last one by at least this much.
+    pub deadband: f32,
+    integral: f32,
+    previous: Option<(DateTime<Utc>, f32)>,
+}
+
+impl Pid {
+    pub fn new(setpoint: f32, kp: f32, ki: f32, kd: f32, limit: f32) -> Pid {
+        Pid {
+            setpoint,
+            kp,
+            ki,
+            kd,
+            limit,
+            deadband: 0.1,
+            integral: 0.0,
+            previous: None,
+        }
+    }
+
+    fn output(&self, error: f32, integral: f32, derivative: f32) -> f32 {
+        self.kp * error + self.ki * integral + self.kd * derivative
+    }
+}
+
+impl ControlStrategy for Pid {
+    fn decide(
+        &mut self,
+        history: &[Datum],
+        last: Option<&GenericCommand>,
+        schema: &Schema,
+    ) -> Option<GenericCommand> {
+        let (now, temperature) = latest(history)?;
+        let error = self.setpoint - temperature;
+
+        let (seconds, derivative) = match self.previous {
+            // we've already seen this Datum, so there's nothing new to react to
+            Some((then, _)) if then >= now => return None,
+            Some((then, previous)) => {
+                let seconds = (now - then).num_milliseconds() as f32 / 1000.0;
+                (seconds, (error - previous) / seconds)
+            }
+            None => (0.0, 0.0),
+        };
+
+        self.previous = Some((now, error));
+
+        // anti-windup: only integrate if it doesn't push an already-saturated output further
+        let integral = self.integral + error * seconds;
+        let unclamped = self.output(error, integral, derivative);
+
+        if unclamped.abs() <= self.limit || unclamped.signum() != error.signum() {
+            self.integral = integral;
+        }
+
+        let shift = self
+            .output(error, self.integral, derivative)
+            .clamp(-self.limit, self.limit);
+
+        // round to the nearest 0.1°C
+        let target = ((self.setpoint + shift) * 10.0).round() / 10.0;
+
+        let name = if shift >= 0.0 { HEAT_TO } else { COOL_TO };
+        let command = build(schema, name, target)?;
+
+        match last
+            .filter(|last| last.name == command.name)
+            .and_then(self::target)
+        {
+            Some(previous) if (previous - target).abs() < self.deadband => None,
+            _ => Some(command),
+        }
+    }
+
+    fn setpoint(&self) -> f32 {
+        self.setpoint
+    }
+
+    fn set_setpoint(&mut self, setpoint: f32) {
+        self.setpoint = setpoint
+    }
+}
+
+/// Changes the setpoint of an `inner` strategy depending on the time of day (in UTC).
+///
+/// Each entry is the time of day at which a setpoint takes effect; it stays in effect until the
+/// next entry. Before the first entry of the day, the last entry (from the day before) applies.
+///
+/// For schedules which differ between weekdays and weekends, or which should be evaluated in a
+/// local time zone, see `SetpointSchedule`.
+pub struct Schedule<S: ControlStrategy> {
+    profile: Profile,
+    inner: S,
+}
+
+impl<S: ControlStrategy> Schedule<S> {
+    pub fn new(entries: Vec<(NaiveTime, f32)>, inner: S) -> Schedule<S> {
+        Schedule {
+            profile: Profile::new(entries),
+            inner,
+        }
+    }
+
+    /// Returns the setpoint in effect at the specified time.
+    pub fn setpoint_at(&self, time: DateTime<Utc>) -> Option<f32> {
+        self.profile.at(time.time()).or(self.profile.last())
+    }
+}
+
+impl<S: ControlStrategy> ControlStrategy for Schedule<S> {
+    fn decide(
+        &mut self,
+        history: &[Datum],
+        last: Option<&GenericCommand>,
+        schema: &Schema,
+    ) -> Option<GenericCommand> {
+        let (now, _) = latest(history)?;
+
+        if let Some(setpoint) = self.setpoint_at(now) {
+            self.inner.set_setpoint(setpoint)
+        }
+
+        self.inner.decide(history, last, schema)
+    }
+
+    fn setpoint(&self) -> f32 {
+        self.inner.setpoint()
+    }
+
+    /// Replaces the schedule with a single, constant setpoint.
+    fn set_setpoint(&mut self, setpoint: f32) {
+        self.profile = Profile::constant(setpoint);
+        self.inner.set_setpoint(setpoint)
+    }
+}
+
+#[cfg(test)]
+mod strategy_tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{Duration, TimeZone};
+
+    use environment::generator;
+    use environment::thermal::{ThermalModel, ZoneParameters};
+
+    use super::*;
+
+    fn reading(temperature: f32, timestamp: DateTime<Utc>) -> Vec<Datum> {
+        vec![Datum::new(temperature, DatumUnit::DegreesC, timestamp)]
+    }
+
+    fn heat(target: f32) -> GenericCommand {
+        GenericCommand::new(HEAT_TO, Some(DatumValue::Float(target)))
+    }
+
+    fn cool(target: f32) -> GenericCommand {
+        GenericCommand::new(COOL_TO, Some(DatumValue::Float(target)))
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, 20, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_bang_bang() {
+        let mut strategy = BangBang::new(21.0, 1.0);
+        let now = Utc::now();
+
+        let schema = temperature_schema();
+
+        let heat = heat(21.0);
+        let cool = cool(21.0);
+
+        assert_eq!(
+            strategy.decide(&reading(19.0, now), None, &schema),
+            Some(heat.clone())
+        );
+        assert_eq!(
+            strategy.decide(&reading(19.0, now), Some(&heat), &schema),
+            None
+        );
+
+        // within the band, we don't switch from heating to cooling
+        assert_eq!(
+            strategy.decide(&reading(21.9, now), Some(&heat), &schema),
+            None
+        );
+        assert_eq!(
+            strategy.decide(&reading(22.1, now), Some(&heat), &schema),
+            Some(cool.clone())
+        );
+
+        // ...or back again
+        assert_eq!(
+            strategy.decide(&reading(20.1, now), Some(&cool), &schema),
+            None
+        );
+        assert_eq!(
+            strategy.decide(&reading(19.9, now), Some(&cool), &schema),
+            Some(heat)
+        );
+    }
+
+    #[test]
+    fn test_bang_bang_no_data() {
+        let schema = temperature_schema();
+        let mut strategy = BangBang::default();
+        assert_eq!(strategy.decide(&[], None, &schema), None);
+
+        let not_a_float = vec![Datum::new_now(DatumValue::Bool(true), DatumUnit::PoweredOn)];
+        assert_eq!(strategy.decide(&not_a_float, None, &schema), None);
+    }
+
+    #[test]
+    fn test_pid_proportional() {
+        let schema = temperature_schema();
+        let mut strategy = Pid::new(21.0, 2.0, 0.0, 0.0, 5.0);
+        let now = Utc::now();
+
+        assert_eq!(
+            strategy.decide(&reading(20.0, now), None, &schema),
+            Some(heat(23.0))
+        );
+        assert_eq!(
+            strategy.decide(&reading(22.0, now + Duration::seconds(1)), None, &schema),
+            Some(cool(19.0))
+        );
+    }
+
+    #[test]
+    fn test_pid_ignores_repeated_data() {
+        let schema = temperature_schema();
+        let mut strategy = Pid::new(21.0, 2.0, 0.0, 0.0, 5.0);
+        let now = Utc::now();
+
+        assert!(strategy
+            .decide(&reading(20.0, now), None, &schema)
+            .is_some());
+        assert_eq!(strategy.decide(&reading(20.0, now), None, &schema), None);
+    }
+
+    #[test]
+    fn test_pid_deadband() {
+        let schema = temperature_schema();
+        let mut strategy = Pid::new(21.0, 2.0, 0.0, 0.0, 5.0);
+        let now = Utc::now();
+
+        let last = strategy.decide(&reading(20.0, now), None, &schema).unwrap();
+        let next = strategy.decide(
+            &reading(20.01, now + Duration::seconds(1)),
+            Some(&last),
+            &schema,
+        );
+
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_pid_anti_windup() {
+        let schema = temperature_schema();
+        let mut strategy = Pid::new(21.0, 1.0, 0.01, 0.0, 5.0);
+        let start = Utc::now();
+
+        // a long time far below the setpoint saturates the output...
+        for minute in 0..600 {
+            let now = start + Duration::minutes(minute);
+            strategy.decide(&reading(10.0, now), None, &schema);
+        }
+
+        // ...but the integral doesn't wind up while saturated, so as soon as we overshoot, we cool
+        let now = start + Duration::minutes(601);
+        let command = strategy.decide(&reading(23.0, now), None, &schema);
+
+        assert_eq!(command.map(|c| c.name), Some(String::from(COOL_TO)));
+    }
+
+    #[test]
+    fn test_schedule_setpoint_at() {
+        let entries = vec![
+            (NaiveTime::from_hms_opt(22, 0, 0).unwrap(), 17.0),
+            (NaiveTime::from_hms_opt(7, 0, 0).unwrap(), 21.0),
+        ];
+
+        let schedule = Schedule::new(entries, BangBang::default());
+
+        assert_eq!(schedule.setpoint_at(at(6, 59)), Some(17.0));
+        assert_eq!(schedule.setpoint_at(at(7, 0)), Some(21.0));
+        assert_eq!(schedule.setpoint_at(at(21, 59)), Some(21.0));
+        assert_eq!(schedule.setpoint_at(at(23, 0)), Some(17.0));
+    }
+
+    #[test]
+    fn test_schedule_decide() {
+        let schema = temperature_schema();
+        let entries = vec![
+            (NaiveTime::from_hms_opt(22, 0, 0).unwrap(), 17.0),
+            (NaiveTime::from_hms_opt(7, 0, 0).unwrap(), 21.0),
+        ];
+
+        let mut schedule = Schedule::new(entries, BangBang::new(0.0, 1.0));
+
+        let heat = schedule.decide(&reading(18.0, at(8, 0)), None, &schema);
+        assert_eq!(heat, Some(self::heat(21.0)));
+
+        let cool = schedule.decide(&reading(18.5, at(23, 0)), heat.as_ref(), &schema);
+        assert_eq!(cool, Some(self::cool(17.0)));
+    }
+
+    /// Simulates a sensor-actuator pair in a `ThermalModel` for `hours`, polling once a minute.
+    fn simulate(strategy: &mut dyn ControlStrategy, initial: f32, hours: i64) -> Vec<f32> {
+        let start = Utc::now();
+        let outside = generator::constant(DatumValue::Float(5.0), DatumUnit::DegreesC);
+        let model = ThermalModel::new(initial, ZoneParameters::default(), outside, start);
+        let model = Arc::new(Mutex::new(model));
+
+        let schema = temperature_schema();
+        let mut last = None;
+        let mut temperatures = Vec::new();
+
+        for minute in 0..(hours * 60) {
+            let now = start + Duration::minutes(minute);
+            let temperature = model.lock().unwrap().advance(now);
+            temperatures.push(temperature);
+
+            let history = reading(temperature, now);
+
+            if let Some(command) = strategy.decide(&history, last.as_ref(), &schema) {
+                // the environment only understands the Commands of a temperature actuator
+                let parsed = actuator::Command::parse(&command.to_string()).unwrap();
+                model.lock().unwrap().apply(parsed, now);
+                last = Some(command);
+            }
+        }
+
+        temperatures
+    }
+
+    #[test]
+    /// Offline regression test: bang-bang control holds a cold room within its band
+    fn test_bang_bang_in_thermal_model() {
+        let mut strategy = BangBang::new(21.0, 1.0);
+        let temperatures = simulate(&mut strategy, 15.0, 12);
+
+        let settled = &temperatures[temperatures.len() - 60..];
+        assert!(
+            settled.iter().all(|t| (20.0..=22.0).contains(t)),
+            "{:?}",
+            settled
+        );
+    }
+
+    #[test]
+    /// Offline regression test: PID control holds a hot room close to its setpoint
+    fn test_pid_in_thermal_model() {
+        let mut strategy = Pid::new(21.0, 1.0, 0.0005, 0.0, 3.0);
+        let temperatures = simulate(&mut strategy, 28.0, 12);
+
+        let settled = &temperatures[temperatures.len() - 60..];
+        assert!(
+            settled.iter().all(|t| (t - 21.0).abs() < 0.5),
+            "{:?}",
+            settled
+        );
+    }
+}
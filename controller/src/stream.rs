@@ -0,0 +1,161 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+use datum::{Datum, Reading};
+use device::protocol;
+use sensor::StreamOptions;
+
+use crate::state::State;
+
+/// Subscribes to the stream of `Reading`s from a `Sensor` over the `connection`, in the specified
+/// protocol `version` and with the specified `options`, calling `on_reading` with each `Reading`
+/// (or error) the `Sensor` sends.
+///
+/// Blocks until the stream drops, returning why. The stream is considered dropped if nothing
+/// arrives before the `connection`'s read timeout.
+pub fn subscribe(
+    mut connection: TcpStream,
+    version: u32,
+    options: StreamOptions,
+    on_reading: impl FnMut(Result<Reading, String>),
+) -> String {
+    let request = format!(
+        "GET /stream?{} HTTP/1.1\r\n{}: {}\r\n\r\n",
+        options,
+        protocol::HEADER,
+        version
+    );
+
+    if let Err(err) = connection.write_all(request.as_bytes()) {
+        return err.to_string();
+    }
+
+    read_events(BufReader::new(connection), version, on_reading)
+}
+
+/// Reads the response to a subscription, calling `on_reading` with each `reading` or `error`
+/// event, until the stream ends. Returns why it ended.
+fn read_events(
+    mut reader: impl BufRead,
+    version: u32,
+    mut on_reading: impl FnMut(Result<Reading, String>),
+) -> String {
+    let mut head = String::new();
+
+    // read the status line and the headers
+    loop {
+        let mut line = String::new();
+
+        match reader.read_line(&mut line) {
+            Ok(0) => return String::from("the sensor closed the stream before it started"),
+            Ok(_) if line == "\r\n" => break,
+            Ok(_) => head.push_str(&line),
+            Err(err) => return err.to_string(),
+        }
+    }
+
+    if let Err(msg) = device::http::body(&head) {
+        return msg;
+    }
+
+    let content_type = device::http::header(&head, "Content-Type").unwrap_or_default();
+
+    if !content_type.starts_with("text/event-stream") {
+        return String::from("the sensor doesn't stream its readings");
+    }
+
+    if let Err(msg) = State::check_version(&head, version) {
+        return msg;
+    }
+
+    let (mut event, mut data) = (String::new(), String::new());
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => return err.to_string(),
+        };
+
+        // a blank line ends each event, and lines starting with a ':' are heartbeats
+        if line.is_empty() {
+            match event.as_str() {
+                // version 1 Sensors only ever report a single Datum
+                "reading" if version == 1 => on_reading(Datum::parse(&data).map(Reading::from)),
+                "reading" => on_reading(Reading::parse(&data)),
+                "error" => on_reading(Err(data.clone())),
+                _ => {}
+            }
+
+            event.clear();
+            data.clear();
+        } else if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data = value.trim().to_string();
+        }
+    }
+
+    String::from("the sensor closed the stream")
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+
+    fn read(response: &str) -> (Vec<Result<Reading, String>>, String) {
+        let mut readings = Vec::new();
+        let reason = read_events(response.as_bytes(), 3, |reading| readings.push(reading));
+        (readings, reason)
+    }
+
+    #[test]
+    fn test_read_events() {
+        let (readings, reason) = read(concat!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nProtocol-Version: 3\r\n\r\n",
+            "event: reading\ndata: temperature=21.5@°C;co2=612@ppm@2024-01-01T00:00:00Z\n\n",
+            ": heartbeat\n\n",
+            "event: error\ndata: cannot reach the environment\n\n",
+            "event: reading\ndata: 22.0@°C@2024-01-01T00:00:05Z\n\n",
+        ));
+
+        assert_eq!(readings.len(), 3);
+        assert_eq!(
+            readings[0].as_ref().unwrap().to_string(),
+            "temperature=21.5@°C;co2=612@ppm@2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            readings[1],
+            Err(String::from("cannot reach the environment"))
+        );
+        assert_eq!(
+            readings[2]
+                .as_ref()
+                .unwrap()
+                .get("")
+                .unwrap()
+                .get_as_float(),
+            Some(22.0)
+        );
+        assert_eq!(reason, "the sensor closed the stream");
+    }
+
+    #[test]
+    fn test_read_events_not_a_stream() {
+        // Sensors which don't stream answer a subscription like any other request
+        let (readings, reason) = read("HTTP/1.1 200 OK\r\n\r\n21.5@°C@2024-01-01T00:00:00Z");
+
+        assert!(readings.is_empty());
+        assert_eq!(reason, "the sensor doesn't stream its readings");
+
+        let (_, reason) = read("HTTP/1.1 400 Bad Request\r\n\r\nunsupported protocol version");
+        assert_eq!(reason, "HTTP/1.1 400 Bad Request");
+
+        let (_, reason) = read(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nProtocol-Version: 2\r\n\r\n",
+        );
+        assert_eq!(
+            reason,
+            "asked for protocol version 3, but the device responded in version 2"
+        );
+    }
+}
@@ -2,6 +2,10 @@ use std::fmt::{Display, Formatter};
 
 use chrono::{DateTime, Utc};
 
+pub use crate::reading::{Channel, Reading};
+
+mod reading;
+
 /// A `Datum` is a singular data point; a single measurement / observation of some `Attribute`.
 ///
 /// It contains a typed `value`, a `unit` associated with that `value`, and a `timestamp`.
@@ -71,13 +75,23 @@ impl From<i32> for DatumValue {
     }
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum DatumValueType {
     Bool,
     Float,
     Int,
 }
 
+impl Display for DatumValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatumValueType::Bool => write!(f, "bool"),
+            DatumValueType::Float => write!(f, "float"),
+            DatumValueType::Int => write!(f, "int"),
+        }
+    }
+}
+
 impl DatumValueType {
     pub fn parse(string: &str) -> Result<DatumValueType, String> {
         if string == "bool" {
@@ -106,7 +120,8 @@ impl Display for DatumValue {
             DatumValue::Float(value) => {
                 let str = value.to_string();
                 // force serialized floats to end with .0 to distinguish them from ints
-                if str.contains('.') {
+                // (NaN and inf can't be mistaken for ints, and don't parse with a .0)
+                if str.contains('.') || !value.is_finite() {
                     str
                 } else {
                     format!("{}.0", str)
@@ -139,6 +154,9 @@ pub enum DatumUnit {
     Unitless,
     PoweredOn,
     DegreesC,
+    RelativeHumidity,
+    Lux,
+    PartsPerMillion,
 }
 
 impl Display for DatumUnit {
@@ -147,6 +165,9 @@ impl Display for DatumUnit {
             DatumUnit::Unitless => "",
             DatumUnit::PoweredOn => "⏼",
             DatumUnit::DegreesC => "°C",
+            DatumUnit::RelativeHumidity => "%RH",
+            DatumUnit::Lux => "lx",
+            DatumUnit::PartsPerMillion => "ppm",
         };
 
         write!(f, "{}", string)
@@ -161,6 +182,12 @@ impl DatumUnit {
             Ok(DatumUnit::PoweredOn)
         } else if string == "°C" {
             Ok(DatumUnit::DegreesC)
+        } else if string == "%RH" {
+            Ok(DatumUnit::RelativeHumidity)
+        } else if string == "lx" {
+            Ok(DatumUnit::Lux)
+        } else if string == "ppm" {
+            Ok(DatumUnit::PartsPerMillion)
         } else {
             Err(format!("cannot parse '{}' as a DatumUnit", string))
         }
@@ -278,4 +305,42 @@ mod datum_tests {
 
         assert_eq!(actual, Ok(expected))
     }
+
+    #[test]
+    fn test_datum_parse_relative_humidity() {
+        let now = Utc::now();
+        let string = format!("55.5@%RH@{}", now.to_rfc3339());
+
+        let expected = Datum::new(55.5, DatumUnit::RelativeHumidity, now);
+        let actual = Datum::parse(string.as_str());
+
+        assert_eq!(actual, Ok(expected))
+    }
+
+    #[test]
+    fn test_datum_parse_lux() {
+        let now = Utc::now();
+        let string = format!("320.0@lx@{}", now.to_rfc3339());
+
+        let expected = Datum::new(320.0, DatumUnit::Lux, now);
+        let actual = Datum::parse(string.as_str());
+
+        assert_eq!(actual, Ok(expected))
+    }
+
+    #[test]
+    fn test_datum_parse_nan() {
+        let datum = Datum::new(f32::NAN, DatumUnit::DegreesC, Utc::now());
+        let parsed = Datum::parse(&datum.to_string()).unwrap();
+
+        assert!(parsed.get_as_float().unwrap().is_nan())
+    }
+
+    #[test]
+    fn test_datum_value_type_serde() {
+        for value_type in ["bool", "float", "int"] {
+            let parsed = DatumValueType::parse(value_type).unwrap();
+            assert_eq!(parsed.to_string(), value_type)
+        }
+    }
 }
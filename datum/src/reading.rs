@@ -0,0 +1,256 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Utc};
+
+use crate::{Datum, DatumUnit, DatumValue};
+
+/// A `Reading` is every measurement a single `Sensor` took at one moment in time.
+///
+/// Many devices (e.g. a combined temperature / humidity / CO₂ probe) measure several quantities at
+/// once. Each quantity is reported on its own named `Channel`, with its own unit, and all of the
+/// channels share the `Reading`'s `timestamp`.
+///
+/// A `Reading` with a single unnamed channel is serialized exactly like a `Datum`, so sensors which
+/// measure only one quantity are unaffected.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Reading {
+    pub channels: Vec<Channel>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A single quantity measured by a `Sensor`, like the `co2` in a `Reading` from an air quality
+/// probe.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Channel {
+    /// The name of the channel, which is empty for sensors which measure only one quantity.
+    pub name: String,
+    pub value: DatumValue,
+    pub unit: DatumUnit,
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{}@{}", self.value, self.unit)
+        } else {
+            write!(f, "{}={}@{}", self.name, self.value, self.unit)
+        }
+    }
+}
+
+impl Channel {
+    /// Parses a channel serialized as `name=value@unit` (or `value@unit`, if it is unnamed).
+    fn parse(string: &str) -> Result<Channel, String> {
+        let (name, datum) = string.split_once('=').unwrap_or(("", string));
+
+        let Some((value, unit)) = datum.split_once('@') else {
+            return Err(format!("unable to parse '{}' as a Channel", string));
+        };
+
+        Ok(Channel {
+            name: Reading::validate(name)?.to_string(),
+            value: DatumValue::parse(value.to_string())?,
+            unit: DatumUnit::parse(unit)?,
+        })
+    }
+}
+
+impl Display for Reading {
+    /// Serializes the `Reading` as `name=value@unit;name=value@unit@timestamp`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let channels: Vec<String> = self.channels.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}@{}", channels.join(";"), self.timestamp.to_rfc3339())
+    }
+}
+
+impl From<Datum> for Reading {
+    /// Creates a `Reading` with a single unnamed channel.
+    fn from(datum: Datum) -> Self {
+        Reading {
+            channels: vec![Channel {
+                name: String::new(),
+                value: datum.value,
+                unit: datum.unit,
+            }],
+            timestamp: datum.timestamp,
+        }
+    }
+}
+
+impl Reading {
+    /// Creates a `Reading` with no channels, taken at `timestamp`.
+    pub fn new(timestamp: DateTime<Utc>) -> Reading {
+        Reading {
+            channels: Vec::new(),
+            timestamp,
+        }
+    }
+
+    /// Adds a channel with the specified `name` to this `Reading`.
+    ///
+    /// Channel names must be unique within a `Reading`, and cannot contain `;`, `=`, or `@`. Only a
+    /// `Reading` with a single channel can leave it unnamed.
+    pub fn with<T: Into<DatumValue>>(
+        mut self,
+        name: &str,
+        value: T,
+        unit: DatumUnit,
+    ) -> Result<Reading, String> {
+        let name = Reading::validate(name)?;
+
+        if self.channels.iter().any(|channel| channel.name == name) {
+            return Err(format!("duplicate channel '{}'", name));
+        }
+
+        let unnamed = name.is_empty() || self.channels.iter().any(|c| c.name.is_empty());
+
+        if unnamed && !self.channels.is_empty() {
+            return Err(String::from(
+                "every channel must be named when a Reading has more than one",
+            ));
+        }
+
+        self.channels.push(Channel {
+            name: String::from(name),
+            value: value.into(),
+            unit,
+        });
+
+        Ok(self)
+    }
+
+    /// Returns the `Datum` measured on the channel with the specified `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Datum> {
+        self.channels
+            .iter()
+            .find(|channel| channel.name == name)
+            .map(|channel| Datum::new(channel.value, channel.unit, self.timestamp))
+    }
+
+    /// Splits this `Reading` into a separate `Datum` for each channel, paired with its name.
+    pub fn data(&self) -> Vec<(&str, Datum)> {
+        self.channels
+            .iter()
+            .map(|c| (c.name.as_str(), Datum::new(c.value, c.unit, self.timestamp)))
+            .collect()
+    }
+
+    pub fn parse(string: &str) -> Result<Reading, String> {
+        let Some((channels, timestamp)) = string.rsplit_once('@') else {
+            return Err(format!("unable to parse '{}' as a Reading", string));
+        };
+
+        let timestamp = timestamp
+            .parse::<DateTime<Utc>>()
+            .map_err(|err| err.to_string())?;
+
+        let channels = channels
+            .split(';')
+            .map(Channel::parse)
+            .collect::<Result<Vec<Channel>, String>>()?;
+
+        let multiple = channels.len() > 1;
+
+        for (index, channel) in channels.iter().enumerate() {
+            if multiple && channel.name.is_empty() {
+                return Err(format!("channels of '{}' must be named", string));
+            }
+
+            if channels[..index].iter().any(|c| c.name == channel.name) {
+                return Err(format!("duplicate channel '{}'", channel.name));
+            }
+        }
+
+        Ok(Reading {
+            channels,
+            timestamp,
+        })
+    }
+
+    /// Returns the `name` if it can be used as the name of a channel.
+    fn validate(name: &str) -> Result<&str, String> {
+        if name.contains([';', '=', '@']) {
+            Err(format!(
+                "channel name '{}' cannot contain ';', '=', or '@'",
+                name
+            ))
+        } else {
+            Ok(name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod reading_tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_channels() {
+        let reading = Reading::new(Utc::now())
+            .with("temperature", 21.5, DatumUnit::DegreesC)
+            .and_then(|r| r.with("humidity", 40.0, DatumUnit::RelativeHumidity))
+            .and_then(|r| r.with("co2", 612, DatumUnit::PartsPerMillion))
+            .unwrap();
+
+        let serialized = reading.to_string();
+        assert!(serialized.starts_with("temperature=21.5@°C;humidity=40.0@%RH;co2=612@ppm@"));
+
+        assert_eq!(Reading::parse(&serialized), Ok(reading));
+    }
+
+    #[test]
+    /// A single-channel `Reading` is serialized exactly like a `Datum`
+    fn test_serde_datum() {
+        let datum = Datum::new_now(42.0, DatumUnit::Lux);
+        let reading = Reading::from(datum.clone());
+
+        assert_eq!(reading.to_string(), datum.to_string());
+        assert_eq!(Reading::parse(&datum.to_string()), Ok(reading.clone()));
+        assert_eq!(reading.get(""), Some(datum));
+    }
+
+    #[test]
+    fn test_data() {
+        let now = Utc::now();
+        let reading = Reading::new(now)
+            .with("open", true, DatumUnit::Unitless)
+            .and_then(|r| r.with("battery", 87, DatumUnit::Unitless))
+            .unwrap();
+
+        let data = reading.data();
+
+        assert_eq!(data.len(), 2);
+        assert_eq!(
+            data[0],
+            ("open", Datum::new(true, DatumUnit::Unitless, now))
+        );
+        assert_eq!(data[1].1.timestamp, now);
+        assert_eq!(reading.get("battery").unwrap().get_as_int(), Some(87));
+        assert_eq!(reading.get("voltage"), None);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let now = Utc::now().to_rfc3339();
+
+        assert!(Reading::parse("21.5@°C").is_err());
+        assert!(Reading::parse(&format!("a=1@°C;1@°C@{}", now)).is_err());
+        assert!(Reading::parse(&format!("a=1@°C;a=2@°C@{}", now)).is_err());
+        assert!(Reading::parse(&format!("a=warm@°C@{}", now)).is_err());
+        assert!(Reading::parse(&format!("a=1@furlongs@{}", now)).is_err());
+    }
+
+    #[test]
+    fn test_with_invalid() {
+        let reading = Reading::new(Utc::now());
+        let named = reading.clone().with("a", 1, DatumUnit::Unitless).unwrap();
+        let unnamed = reading.clone().with("", 1, DatumUnit::Unitless).unwrap();
+
+        assert!(reading.clone().with("a;b", 1, DatumUnit::Unitless).is_err());
+        assert!(reading.clone().with("a=b", 1, DatumUnit::Unitless).is_err());
+        assert!(reading.with("a@b", 1, DatumUnit::Unitless).is_err());
+        assert!(named.clone().with("a", 2, DatumUnit::Unitless).is_err());
+        assert!(named.with("", 2, DatumUnit::Unitless).is_err());
+        assert!(unnamed.with("b", 2, DatumUnit::Unitless).is_err());
+    }
+}
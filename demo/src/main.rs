@@ -1,30 +1,139 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde::Deserialize;
+use tracing::{error, info};
 use uuid::Uuid;
 
 use actuator::Actuator;
 use actuator_temperature::TemperatureActuator;
+use controller::config::{ControllerArgs, ControllerConfig};
 use controller::Controller;
+use device::config::LoggingArgs;
 use device::{Device, Id, Name};
 use sensor::Sensor;
 use sensor_temperature::TemperatureSensor;
 
+/// Runs a sensor-actuator pair and a controller in a single process.
+#[derive(Parser)]
+struct Cli {
+    /// A TOML configuration file; flags and environment variables take precedence over it
+    #[arg(long, env = "DEMO_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    demo: DemoArgs,
+
+    #[command(flatten)]
+    controller: ControllerArgs,
+
+    #[command(flatten)]
+    logging: LoggingArgs,
+}
+
+/// Configures the demo's sensor-actuator pair.
+#[derive(clap::Args, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DemoArgs {
+    /// The Id shared by the sensor and the actuator [default: a random UUID]
+    #[arg(long, env = "DEVICE_ID")]
+    id: Option<String>,
+
+    /// The name shared by the sensor and the actuator
+    #[arg(long, env = "DEVICE_NAME")]
+    name: Option<String>,
+
+    /// The IP address the sensor and actuator listen on [default: this machine's local IP]
+    #[arg(long, env = "BIND_ADDRESS")]
+    address: Option<IpAddr>,
+
+    /// The port the sensor listens on [default: 8787]
+    #[arg(long, env = "SENSOR_PORT")]
+    sensor_port: Option<u16>,
+
+    /// The port the actuator listens on [default: 9898]
+    #[arg(long, env = "ACTUATOR_PORT")]
+    actuator_port: Option<u16>,
+
+    /// The address of the `Environment` which the sensor reads from and the actuator acts on
+    /// [default: 127.0.0.1:8080]
+    #[arg(long, env = "DEVICE_ENVIRONMENT")]
+    environment: Option<String>,
+}
+
+impl DemoArgs {
+    /// Fills in anything which isn't set here from the `file`.
+    fn or(self, file: DemoArgs) -> DemoArgs {
+        DemoArgs {
+            id: self.id.or(file.id),
+            name: self.name.or(file.name),
+            address: self.address.or(file.address),
+            sensor_port: self.sensor_port.or(file.sensor_port),
+            actuator_port: self.actuator_port.or(file.actuator_port),
+            environment: self.environment.or(file.environment),
+        }
+    }
+}
+
+/// The layout of the configuration file.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    demo: DemoArgs,
+    #[serde(default)]
+    controller: ControllerArgs,
+    #[serde(default)]
+    logging: LoggingArgs,
+}
+
 fn main() {
-    // in the local demo, all devices have the same ip (localhost)
-    let ip = local_ip_address::local_ip().unwrap();
+    let cli = Cli::parse();
+
+    let file: File = device::config::read_file(cli.config.as_deref()).unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    });
+
+    if let Err(msg) = cli.logging.or(file.logging).init() {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    }
+
+    let demo = cli.demo.or(file.demo);
+
+    let config = ControllerConfig::from_args(cli.controller.or(file.controller))
+        .unwrap_or_else(|msg| exit(&msg));
+
+    // in the local demo, all devices have the same ip (by default, this machine's local ip)
+    let ip = demo
+        .address
+        .unwrap_or_else(|| local_ip_address::local_ip().unwrap());
+
+    // the sensor and actuator read from and act on an Environment, which must be started separately
+    let environment = demo
+        .environment
+        .unwrap_or_else(|| String::from(device::config::ENVIRONMENT));
 
     // --------------------------------------------------------------------------------
     // spin up a sensor-actuator pair
     // --------------------------------------------------------------------------------
 
-    // id has to be the same for the sensor and its corresponding actuator
-    let id = Id::new(&Uuid::new_v4().to_string());
-    let name = Name::new("user-defined device name, like 'Kitchen Thermostat'");
+    // by default, a sensor is paired with the actuator which has the same id
+    // (sensors and actuators can also be paired explicitly, see controller::pairing::Pairings)
+    let id = Id::new(&demo.id.unwrap_or_else(|| Uuid::new_v4().to_string()));
+    let name =
+        Name::new(&demo.name.unwrap_or_else(|| {
+            String::from("user-defined device name, like 'Kitchen Thermostat'")
+        }));
 
     // ---------- here is the sensor ----------
 
-    let sensor_port = 8787;
+    let sensor_port = demo.sensor_port.unwrap_or(8787);
 
-    let sensor = TemperatureSensor::new(id.clone(), name.clone());
-    let listener = sensor.bind(ip, sensor_port, "_sensor");
+    let sensor = TemperatureSensor::new(id.clone(), name.clone(), &environment);
+    let listener = sensor.bind(ip, sensor_port, &config.sensor_group);
 
     std::thread::spawn(move || {
         sensor.respond(listener);
@@ -32,10 +141,10 @@ fn main() {
 
     // ---------- here is the actuator ----------
 
-    let actuator_port = 9898;
+    let actuator_port = demo.actuator_port.unwrap_or(9898);
 
-    let actuator = TemperatureActuator::new(id, name);
-    let listener = actuator.bind(ip, actuator_port, "_actuator");
+    let actuator = TemperatureActuator::new(id, name, &environment);
+    let listener = actuator.bind(ip, actuator_port, &config.actuator_group);
 
     std::thread::spawn(move || {
         actuator.respond(listener);
@@ -45,5 +154,16 @@ fn main() {
     // spin up the controller
     // --------------------------------------------------------------------------------
 
-    Controller::new().run();
+    // the rules, pairings, schedules, alerts, and store are all optional; see controller::config
+    let mut controller = Controller::with_config(&config).unwrap_or_else(|msg| exit(&msg));
+
+    info!(address = config.api_address, "serving controller API");
+    if let Err(msg) = controller.run() {
+        exit(&msg)
+    }
+}
+
+fn exit(msg: &str) -> ! {
+    error!("{}", msg);
+    std::process::exit(1)
 }
@@ -0,0 +1,509 @@
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Args, Parser};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::logging::{self, LogFormat};
+use crate::{Id, Name};
+
+/// The address of the `Environment`, by default.
+pub const ENVIRONMENT: &str = "127.0.0.1:8080";
+
+/// Reads a TOML configuration file, or returns the default configuration if there is no `path`.
+///
+/// Every binary takes a configuration file, CLI flags, and environment variables. A CLI flag
+/// takes precedence over its environment variable, which takes precedence over the file.
+pub fn read_file<T: DeserializeOwned + Default>(path: Option<&Path>) -> Result<T, String> {
+    let Some(path) = path else {
+        return Ok(T::default());
+    };
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("cannot read config {}: {}", path.display(), err))?;
+
+    toml::from_str(&contents).map_err(|err| format!("invalid config {}:\n{}", path.display(), err))
+}
+
+/// Parses a whole number of milliseconds ("ms"), seconds ("s"), minutes ("m"), hours ("h"), or
+/// days ("d").
+///
+/// This is the only duration format used in configuration files, scenarios, and the HTTP APIs.
+pub fn parse_duration(string: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "cannot parse '{}' as a duration, expected e.g. '500ms', '30s', '10m', '2h', or '1d'",
+            string
+        )
+    };
+
+    let trimmed = string.trim();
+    let (amount, unit) = trimmed.split_at(trimmed.trim_end_matches(char::is_alphabetic).len());
+    let amount = amount.parse::<u64>().map_err(|_| error())?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "s" => Ok(Duration::from_secs(amount)),
+        "m" => Ok(Duration::from_secs(amount * 60)),
+        "h" => Ok(Duration::from_secs(amount * 60 * 60)),
+        "d" => Ok(Duration::from_secs(amount * 60 * 60 * 24)),
+        _ => Err(error()),
+    }
+}
+
+/// Configures logging; see `logging::init`.
+#[derive(Args, Deserialize, PartialEq, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingArgs {
+    /// The log level, e.g. "info", or "warn,controller=debug" for per-crate levels [default: info]
+    #[arg(long = "log-level", env = "LOG_LEVEL")]
+    pub level: Option<String>,
+
+    /// The log format, "human" or "json" [default: human]
+    #[arg(long = "log-format", env = "LOG_FORMAT")]
+    pub format: Option<String>,
+}
+
+impl LoggingArgs {
+    /// Fills in anything which isn't set here from the `file`.
+    pub fn or(self, file: LoggingArgs) -> LoggingArgs {
+        LoggingArgs {
+            level: self.level.or(file.level),
+            format: self.format.or(file.format),
+        }
+    }
+
+    /// Starts logging at the configured level, in the configured format.
+    pub fn init(&self) -> Result<(), String> {
+        let format = match &self.format {
+            Some(format) => LogFormat::parse(format)?,
+            None => LogFormat::default(),
+        };
+
+        logging::init(self.level.as_deref().unwrap_or("info"), format)
+    }
+}
+
+/// Configures a single `Device` on the network.
+#[derive(Args, Deserialize, PartialEq, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceArgs {
+    /// The unique Id of the device
+    #[arg(long, env = "DEVICE_ID")]
+    pub id: Option<String>,
+
+    /// The user-friendly name of the device, like "Kitchen Thermostat"
+    #[arg(long, env = "DEVICE_NAME")]
+    pub name: Option<String>,
+
+    /// The IP address to listen on, and register with mDNS [default: this machine's local IP]
+    #[arg(long, env = "BIND_ADDRESS")]
+    pub address: Option<IpAddr>,
+
+    /// The port to listen on
+    #[arg(long, env = "PORT")]
+    pub port: Option<u16>,
+
+    /// The mDNS group to register in, like "_sensor"
+    #[arg(long, env = "MDNS_GROUP")]
+    pub group: Option<String>,
+
+    /// The address of the `Environment` which the device reads from or acts on [default: 127.0.0.1:8080]
+    #[arg(long, env = "DEVICE_ENVIRONMENT")]
+    pub environment: Option<String>,
+}
+
+impl DeviceArgs {
+    /// Fills in anything which isn't set here from the `file`.
+    pub fn or(self, file: DeviceArgs) -> DeviceArgs {
+        DeviceArgs {
+            id: self.id.or(file.id),
+            name: self.name.or(file.name),
+            address: self.address.or(file.address),
+            port: self.port.or(file.port),
+            group: self.group.or(file.group),
+            environment: self.environment.or(file.environment),
+        }
+    }
+}
+
+/// How much a value must change by before a `Sensor` pushes or streams it, by default.
+pub const DEADBAND: f32 = 0.5;
+
+/// How often a `Sensor` checks whether its data has changed, when pushing or streaming it, by
+/// default.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Configures a `Sensor` to push its data to the `Controller` as it changes, as well as
+/// responding to polls. Sensors only push if `push_to` is set; `Actuator`s never push.
+#[derive(Args, Deserialize, PartialEq, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PushArgs {
+    /// The address of the Controller's API to push data to, like "127.0.0.1:6565" [default: none; data is only polled]
+    #[arg(long, env = "PUSH_TO", help_heading = "Push mode (sensors only)")]
+    pub push_to: Option<String>,
+
+    /// How much a value must change by before it is pushed; booleans are pushed on every change [default: 0.5]
+    #[arg(long, env = "PUSH_DEADBAND", help_heading = "Push mode (sensors only)")]
+    pub deadband: Option<f32>,
+
+    /// How often data is pushed even if it hasn't changed, e.g. "1m" [default: 1m]
+    #[arg(long, env = "PUSH_INTERVAL", help_heading = "Push mode (sensors only)")]
+    pub push_interval: Option<String>,
+
+    /// How often the sensor checks whether its data has changed, e.g. "100ms" [default: 100ms]
+    #[arg(
+        long,
+        env = "PUSH_SAMPLE_INTERVAL",
+        help_heading = "Push mode (sensors only)"
+    )]
+    pub sample_interval: Option<String>,
+}
+
+impl PushArgs {
+    /// Fills in anything which isn't set here from the `file`.
+    pub fn or(self, file: PushArgs) -> PushArgs {
+        PushArgs {
+            push_to: self.push_to.or(file.push_to),
+            deadband: self.deadband.or(file.deadband),
+            push_interval: self.push_interval.or(file.push_interval),
+            sample_interval: self.sample_interval.or(file.sample_interval),
+        }
+    }
+}
+
+/// When a `Sensor` pushes its data to the `Controller`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PushConfig {
+    /// The address of the `Controller`'s API
+    pub to: String,
+    /// How much a value must change by before it is pushed
+    pub deadband: f32,
+    /// How often data is pushed even if it hasn't changed
+    pub interval: Duration,
+    /// How often the `Sensor` checks whether its data has changed
+    pub sample_interval: Duration,
+}
+
+impl PushConfig {
+    /// Validates the `args`, returning `None` if the `Sensor` shouldn't push at all.
+    pub fn from_args(args: PushArgs) -> Result<Option<PushConfig>, String> {
+        let Some(to) = args.push_to.filter(|to| !to.trim().is_empty()) else {
+            return Ok(None);
+        };
+
+        let mut errors = Vec::new();
+
+        let deadband = args.deadband.unwrap_or(DEADBAND);
+
+        if deadband.is_nan() || deadband < 0.0 {
+            errors.push(String::from("deadband: must not be negative"))
+        }
+
+        let mut duration = |name: &str, value: Option<String>, default: Duration| match value
+            .as_deref()
+            .map(parse_duration)
+            .unwrap_or(Ok(default))
+        {
+            Ok(duration) if duration.is_zero() => {
+                errors.push(format!("{}: must be greater than zero", name));
+                default
+            }
+            Ok(duration) => duration,
+            Err(msg) => {
+                errors.push(format!("{}: {}", name, msg));
+                default
+            }
+        };
+
+        let interval = duration("push_interval", args.push_interval, Duration::from_secs(60));
+        let sample_interval = duration("sample_interval", args.sample_interval, SAMPLE_INTERVAL);
+
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+
+        Ok(Some(PushConfig {
+            to,
+            deadband,
+            interval,
+            sample_interval,
+        }))
+    }
+}
+
+/// The settings of a single `Device` on the network.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DeviceConfig {
+    pub id: Id,
+    pub name: Name,
+    pub address: IpAddr,
+    pub port: u16,
+    pub group: String,
+    pub environment: String,
+    /// When a `Sensor` pushes its data to the `Controller`, if it does; see `PushArgs`
+    pub push: Option<PushConfig>,
+}
+
+impl DeviceConfig {
+    /// Validates the `args`, using the specified `port` and `group` if they aren't set.
+    ///
+    /// Every device needs an `id` and a `name`; there are no defaults for those.
+    pub fn from_args(args: DeviceArgs, port: u16, group: &str) -> Result<DeviceConfig, String> {
+        let mut errors = Vec::new();
+
+        let id = match args.id.filter(|id| !id.trim().is_empty()) {
+            Some(id) => match Id::parse(&id) {
+                Ok(id) => Some(id),
+                Err(msg) => {
+                    errors.push(format!("id: {}", msg));
+                    None
+                }
+            },
+            None => {
+                errors.push(String::from("id: must be set"));
+                None
+            }
+        };
+        let name = args.name.filter(|name| !name.trim().is_empty());
+
+        if name.is_none() {
+            errors.push(String::from("name: must be set"))
+        }
+
+        let group = args.group.unwrap_or_else(|| String::from(group));
+
+        if let Err(msg) = validate_group(&group) {
+            errors.push(format!("group: {}", msg))
+        }
+
+        let address = match args.address {
+            Some(address) => Some(address),
+            None => match local_ip_address::local_ip() {
+                Ok(address) => Some(address),
+                Err(err) => {
+                    errors.push(format!("address: cannot find this machine's IP: {}", err));
+                    None
+                }
+            },
+        };
+
+        match (id, name, address) {
+            (Some(id), Some(name), Some(address)) if errors.is_empty() => Ok(DeviceConfig {
+                id,
+                name: Name::new(&name),
+                address,
+                port: args.port.unwrap_or(port),
+                group,
+                environment: args
+                    .environment
+                    .unwrap_or_else(|| String::from(ENVIRONMENT)),
+                push: None,
+            }),
+            _ => Err(errors.join("\n")),
+        }
+    }
+}
+
+/// The command line of a standalone `Sensor` or `Actuator` binary.
+#[derive(Parser)]
+pub struct DeviceCli {
+    /// A TOML configuration file; flags and environment variables take precedence over it
+    #[arg(long, env = "DEVICE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub device: DeviceArgs,
+
+    #[command(flatten)]
+    pub push: PushArgs,
+
+    #[command(flatten)]
+    pub logging: LoggingArgs,
+}
+
+/// The layout of a standalone device's configuration file.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DeviceFile {
+    #[serde(default)]
+    device: DeviceArgs,
+    #[serde(default)]
+    push: PushArgs,
+    #[serde(default)]
+    logging: LoggingArgs,
+}
+
+impl DeviceCli {
+    /// Reads the configuration file named on the command line, starts logging, and returns the
+    /// device's settings, using the specified `port` and `group` if they aren't set.
+    pub fn load(self, port: u16, group: &str) -> Result<DeviceConfig, String> {
+        let file: DeviceFile = read_file(self.config.as_deref())?;
+        self.logging.or(file.logging).init()?;
+
+        let mut config = DeviceConfig::from_args(self.device.or(file.device), port, group)?;
+        config.push = PushConfig::from_args(self.push.or(file.push))?;
+        Ok(config)
+    }
+}
+
+/// Returns an error if `group` can't be used as an mDNS group, like "_sensor".
+pub fn validate_group(group: &str) -> Result<(), String> {
+    let name = group.strip_prefix('_').unwrap_or_default();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!(
+            "'{}' is not a valid mDNS group, expected e.g. '_sensor'",
+            group
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod config_tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser, Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct TestArgs {
+        #[command(flatten)]
+        #[serde(default)]
+        device: DeviceArgs,
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86_400)));
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    /// A CLI flag takes precedence over the configuration file
+    fn test_flags_override_file() {
+        let file: TestArgs = toml::from_str(
+            r#"
+            [device]
+            name = "Kitchen Thermostat"
+            port = 8787
+            "#,
+        )
+        .unwrap();
+
+        let args = TestArgs::try_parse_from(["test", "--port", "9000"]).unwrap();
+        let device = args.device.or(file.device);
+
+        assert_eq!(device.port, Some(9000));
+        assert_eq!(device.name, Some(String::from("Kitchen Thermostat")));
+        assert_eq!(device.id, None);
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let result = toml::from_str::<TestArgs>("[device]\nprot = 8787");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_device_config() {
+        let args = DeviceArgs {
+            id: Some(String::from("k1")),
+            name: Some(String::from("Kitchen Thermostat")),
+            address: Some(IpAddr::from([127, 0, 0, 1])),
+            ..DeviceArgs::default()
+        };
+
+        let config = DeviceConfig::from_args(args, 8787, "_sensor").unwrap();
+
+        assert_eq!(config.id, Id::new("k1"));
+        assert_eq!(config.port, 8787);
+        assert_eq!(config.group, "_sensor");
+        assert_eq!(config.environment, ENVIRONMENT);
+    }
+
+    #[test]
+    fn test_device_config_id() {
+        let args = DeviceArgs {
+            id: Some(String::from("probe:co2")),
+            name: Some(String::from("Probe")),
+            address: Some(IpAddr::from([127, 0, 0, 1])),
+            ..DeviceArgs::default()
+        };
+
+        assert_eq!(
+            DeviceConfig::from_args(args, 8787, "_sensor"),
+            Err(String::from("id: device id 'probe:co2' cannot contain ':'"))
+        );
+    }
+
+    #[test]
+    fn test_device_config_errors() {
+        let args = DeviceArgs {
+            name: Some(String::from(" ")),
+            address: Some(IpAddr::from([127, 0, 0, 1])),
+            group: Some(String::from("sensor")),
+            ..DeviceArgs::default()
+        };
+
+        let errors = DeviceConfig::from_args(args, 8787, "_sensor").unwrap_err();
+        let lines: Vec<&str> = errors.lines().collect();
+
+        assert_eq!(
+            lines,
+            [
+                "id: must be set",
+                "name: must be set",
+                "group: 'sensor' is not a valid mDNS group, expected e.g. '_sensor'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_config() {
+        assert_eq!(PushConfig::from_args(PushArgs::default()), Ok(None));
+
+        let args = PushArgs {
+            push_to: Some(String::from("127.0.0.1:6565")),
+            push_interval: Some(String::from("30s")),
+            ..PushArgs::default()
+        };
+
+        let config = PushConfig::from_args(args).unwrap().unwrap();
+
+        assert_eq!(config.to, "127.0.0.1:6565");
+        assert_eq!(config.deadband, 0.5);
+        assert_eq!(config.interval, Duration::from_secs(30));
+        assert_eq!(config.sample_interval, Duration::from_millis(100));
+
+        let args = PushArgs {
+            push_to: Some(String::from("127.0.0.1:6565")),
+            deadband: Some(-1.0),
+            sample_interval: Some(String::from("0ms")),
+            ..PushArgs::default()
+        };
+
+        assert_eq!(
+            PushConfig::from_args(args),
+            Err(String::from(
+                "deadband: must not be negative\nsample_interval: must be greater than zero"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_group() {
+        assert!(validate_group("_sensor").is_ok());
+        assert!(validate_group("sensor").is_err());
+        assert!(validate_group("_").is_err());
+        assert!(validate_group("_sen.sor").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long to wait for the `Environment` to respond, by default.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest request body the HTTP servers accept; larger requests are rejected with a `413`.
+pub const MAX_BODY_LENGTH: usize = 1024 * 1024;
+
+/// Connects to the `address` (like "127.0.0.1:8080"), sends the `request`, and returns the body of
+/// the response, giving up if any step takes longer than the `timeout`.
+///
+/// Returns an error if the response is not a `2xx`, so the caller only has to handle the body.
+pub fn send(address: &str, request: &str, timeout: Duration) -> Result<String, String> {
+    let error = |err: std::io::Error| format!("cannot reach {}: {}", address, err);
+
+    let socket = address
+        .to_socket_addrs()
+        .map_err(error)?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", address))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket, timeout).map_err(error)?;
+    stream.set_read_timeout(Some(timeout)).map_err(error)?;
+    stream.set_write_timeout(Some(timeout)).map_err(error)?;
+
+    stream.write_all(request.as_bytes()).map_err(error)?;
+    stream.flush().map_err(error)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(error)?;
+
+    body(&response).map(String::from)
+}
+
+/// Returns the body of an HTTP `response`, or an error if its status is not `2xx`.
+pub fn body(response: &str) -> Result<&str, String> {
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((response, ""));
+    let status = head.lines().next().unwrap_or_default();
+
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(body.trim()),
+        Some(_) if body.trim().is_empty() => Err(String::from(status)),
+        Some(_) => Err(format!("{}: {}", status, body.trim())),
+        None => Err(format!("invalid response: '{}'", response)),
+    }
+}
+
+/// Reads an HTTP request from a client: the request line, the headers, a blank line, and a body of
+/// `Content-Length` bytes. Gives up if the client sends nothing for the `timeout`, so a client
+/// which never finishes its request can't tie up the server.
+///
+/// Requests with a body longer than `MAX_BODY_LENGTH` are answered with a `413` straight away,
+/// and `None` is returned.
+pub fn read_request(stream: &mut TcpStream, timeout: Duration) -> std::io::Result<Option<String>> {
+    stream.set_read_timeout(Some(timeout))?;
+
+    let mut reader = BufReader::new(&mut *stream);
+    let mut request = String::new();
+    let mut content_length: usize = 0;
+
+    // read the request line and the headers
+    loop {
+        let mut line = String::new();
+        let len = reader.read_line(&mut line)?;
+        if len == 0 || line == "\r\n" {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+
+        request.push_str(&line);
+    }
+
+    if content_length > MAX_BODY_LENGTH {
+        let response = format!(
+            "HTTP/1.1 413 Payload Too Large\r\n\r\nrequest bodies are limited to {} bytes",
+            MAX_BODY_LENGTH
+        );
+        stream.write_all(response.as_bytes())?;
+        stream.flush()?;
+        return Ok(None);
+    }
+
+    // read the body based on Content-Length
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    request.push_str("\r\n");
+    request.push_str(&String::from_utf8_lossy(&body));
+
+    Ok(Some(request))
+}
+
+/// Decodes `%XX` escapes in a path or query, e.g. "Kitchen%20Thermostat" is "Kitchen Thermostat".
+/// Malformed escapes are left as they are.
+pub fn percent_decode(string: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = string.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Returns the value of the first header with the specified `name` in an HTTP request or
+/// response, if any. Header names are case-insensitive.
+pub fn header<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+#[cfg(test)]
+mod http_tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_body() {
+        assert_eq!(body("HTTP/1.1 200 OK\r\n\r\n21.5@°C\r\n"), Ok("21.5@°C"));
+        assert_eq!(
+            body("HTTP/1.1 404 Not Found\r\n\r\n"),
+            Err(String::from("HTTP/1.1 404 Not Found"))
+        );
+        assert!(body("").is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let request = "GET / HTTP/1.1\r\nDevice-Name: Kitchen\r\nprotocol-version: 2\r\n\r\nX: 1";
+
+        assert_eq!(header(request, "Device-Name"), Some("Kitchen"));
+        assert_eq!(header(request, "Protocol-Version"), Some("2"));
+        assert_eq!(header(request, "X"), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("/get/Kitchen%20Thermostat/float/%C2%B0C"),
+            "/get/Kitchen Thermostat/float/°C"
+        );
+
+        // malformed escapes are left as they are
+        assert_eq!(percent_decode("/100%/%zz"), "/100%/%zz");
+    }
+
+    /// Reads a single request from a client which sends `request` (and then nothing more).
+    fn read(request: &'static str, timeout: Duration) -> (std::io::Result<Option<String>>, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let result = read_request(&mut stream, timeout);
+        drop(stream);
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        (result, response)
+    }
+
+    #[test]
+    fn test_read_request() {
+        let (request, _) = read("PUT /a HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}", TIMEOUT);
+        assert_eq!(
+            request.unwrap(),
+            Some(String::from(
+                "PUT /a HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}"
+            ))
+        );
+
+        let request = "PUT /a HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n{}";
+        let (request, response) = read(request, TIMEOUT);
+        assert_eq!(request.unwrap(), None);
+        assert_eq!(
+            body(&response),
+            Err(String::from(
+                "HTTP/1.1 413 Payload Too Large: request bodies are limited to 1048576 bytes"
+            ))
+        );
+    }
+
+    #[test]
+    /// A client which never finishes its request is given up on
+    fn test_read_request_timeout() {
+        let (request, _) = read("GET / HTTP/1.1\r\n", Duration::from_millis(100));
+        assert!(request.is_err());
+    }
+
+    #[test]
+    fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(&mut stream).read_line(&mut line).unwrap();
+
+            let response = format!("HTTP/1.1 200 OK\r\n\r\n{}", line.trim());
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        let body = send(&address, "GET /echo HTTP/1.1\r\n\r\n", TIMEOUT);
+        assert_eq!(body, Ok(String::from("GET /echo HTTP/1.1")));
+    }
+
+    #[test]
+    fn test_send_unreachable() {
+        // bind and drop a listener to find a port which nothing is listening on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        assert!(send(&address, "GET / HTTP/1.1\r\n\r\n", TIMEOUT).is_err());
+    }
+}
@@ -3,6 +3,15 @@ use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, TcpListener};
 
 use mdns_sd::ServiceInfo;
+use tracing::info;
+
+pub mod config;
+pub mod http;
+pub mod logging;
+pub mod protocol;
+
+/// The version of the firmware running on every `Device`, advertised via mDNS.
+pub const FIRMWARE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// A `Device` exists on the network and is discoverable via mDNS.
 pub trait Device {
@@ -12,17 +21,29 @@ pub trait Device {
     /// Returns the unique ID of this `Device`.
     fn get_id(&self) -> &Id;
 
-    /// Registers this `Device` with mDNS in the specified group.
+    /// Returns the capabilities of this `Device` (e.g. what it measures, or which commands it
+    /// accepts), which are advertised via mDNS as TXT properties.
+    ///
+    /// By default, a `Device` only advertises its `id`, the protocol versions it speaks, and its
+    /// firmware version; see `register`.
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Registers this `Device` with mDNS in the specified group, advertising its capabilities.
     fn register(&self, ip: IpAddr, port: u16, group: &str) {
         let mdns = mdns_sd::ServiceDaemon::new().unwrap();
         let host = ip.clone().to_string();
         let name = self.get_name();
         let domain = format!("{}._tcp.local.", group);
 
-        println!("Registering new device via mDNS at {}.{}", name, domain);
+        info!(id = %self.get_id(), name = %name, domain, "registering device via mDNS");
 
-        let mut properties = HashMap::new();
+        let mut properties = self.get_capabilities();
         properties.insert(String::from("id"), self.get_id().to_string());
+        let versions = protocol::Versions::supported();
+        properties.insert(String::from("protocol"), versions.to_string());
+        properties.insert(String::from("firmware"), String::from(FIRMWARE_VERSION));
 
         let my_service = ServiceInfo::new(
             domain.as_str(),
@@ -43,7 +64,7 @@ pub trait Device {
         let address = format!("{}:{}", host, port);
         let name = &self.get_name();
 
-        println!("Creating new device '{}' at {}", name, address);
+        info!(id = %self.get_id(), name = %name, address, "creating device");
 
         TcpListener::bind(address).unwrap()
     }
@@ -55,7 +76,7 @@ pub trait Device {
     }
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Eq, Hash, Clone)]
 pub struct Name(pub String);
 
 impl Display for Name {
@@ -83,4 +104,24 @@ impl Id {
     pub fn new(id: &str) -> Id {
         Id(String::from(id))
     }
+
+    /// Parses the `Id` of a device, which cannot contain `:`, as that separates the device's `Id`
+    /// from the name of one of its channels (see `Id::channel`).
+    pub fn parse(id: &str) -> Result<Id, String> {
+        if id.contains(':') {
+            Err(format!("device id '{}' cannot contain ':'", id))
+        } else {
+            Ok(Id::new(id))
+        }
+    }
+
+    /// Returns the `Id` of the series of data measured on the named `channel` of this device, e.g.
+    /// `probe:co2`. Unnamed channels are identified by the device's own `Id`.
+    pub fn channel(&self, channel: &str) -> Id {
+        if channel.is_empty() {
+            self.clone()
+        } else {
+            Id(format!("{}:{}", self.0, channel))
+        }
+    }
 }
@@ -0,0 +1,78 @@
+use std::fmt::{Display, Formatter};
+
+use tracing_subscriber::EnvFilter;
+
+/// How log lines are written.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum LogFormat {
+    /// One line of human-readable text per event, with fields as `key=value` pairs
+    #[default]
+    Human,
+    /// One JSON object per event, with fields as keys
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFormat::Human => write!(f, "human"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl LogFormat {
+    pub fn parse(string: &str) -> Result<LogFormat, String> {
+        match string.trim().to_ascii_lowercase().as_str() {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "cannot parse '{}' as LogFormat, expected 'human' or 'json'",
+                string
+            )),
+        }
+    }
+}
+
+/// Parses a log level filter, like "info", or "warn,controller=debug" for per-crate levels.
+fn filter(level: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(level)
+        .map_err(|err| format!("cannot parse '{}' as a log level: {}", level, err))
+}
+
+/// Starts writing the log events of every crate in this process to stdout, at or above `level`.
+///
+/// `level` is a filter like "info", or "warn,controller=debug" for per-crate levels. This can only
+/// be called once per process.
+pub fn init(level: &str, format: LogFormat) -> Result<(), String> {
+    let builder = tracing_subscriber::fmt().with_env_filter(filter(level)?);
+
+    let result = match format {
+        LogFormat::Human => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+
+    result.map_err(|err| format!("cannot start logging: {}", err))
+}
+
+#[cfg(test)]
+mod logging_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(LogFormat::parse("JSON"), Ok(LogFormat::Json));
+        assert_eq!(
+            LogFormat::parse(&LogFormat::Human.to_string()),
+            Ok(LogFormat::Human)
+        );
+        assert!(LogFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert!(filter("warn,controller=debug").is_ok());
+        assert!(filter("controller=loud").is_err());
+        assert!(init("controller=loud", LogFormat::Human).is_err());
+    }
+}
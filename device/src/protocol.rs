@@ -0,0 +1,164 @@
+use std::fmt::{Display, Formatter};
+
+/// The newest version of the protocol `Device`s speak.
+///
+/// - version 1: `Sensor`s respond with a single `Datum`, like `21.5@°C@<timestamp>`
+/// - version 2: `Sensor`s respond with a `Reading`, which may have several named channels, like
+///   `temperature=21.5@°C;co2=612@ppm@<timestamp>`, and `Actuator`s describe their commands with
+///   a `Schema`, at `GET /schema`
+/// - version 3: `Sensor`s also stream their `Reading`s to subscribers as server-sent events, at
+///   `GET /stream` (see `STREAM_VERSION`)
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// The oldest version of the protocol `Device`s still speak.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// The oldest version of the protocol in which `Sensor`s stream their `Reading`s.
+pub const STREAM_VERSION: u32 = 3;
+
+/// The HTTP header which carries the protocol version of a request or response.
+///
+/// Requests without one are assumed to be from peers which predate versioning, which speak
+/// version 1.
+pub const HEADER: &str = "Protocol-Version";
+
+/// A range of protocol versions, from `min` to `max` (inclusive).
+///
+/// A `Versions` is serialized as `min..max`, or as a single version if `min` and `max` are equal.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Versions {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Display for Versions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}..{}", self.min, self.max)
+        }
+    }
+}
+
+impl Versions {
+    pub fn new(min: u32, max: u32) -> Versions {
+        Versions { min, max }
+    }
+
+    /// Returns the versions spoken by this build of the `Device`s and the `Controller`.
+    pub fn supported() -> Versions {
+        Versions::new(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION)
+    }
+
+    pub fn parse(string: &str) -> Result<Versions, String> {
+        let version = |version: &str| {
+            version
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("cannot parse '{}' as a protocol version", string))
+        };
+
+        let versions = match string.split_once("..") {
+            Some((min, max)) => Versions::new(version(min)?, version(max)?),
+            None => Versions::new(version(string)?, version(string)?),
+        };
+
+        if versions.min > versions.max {
+            return Err(format!(
+                "'{}' is not a valid range of protocol versions",
+                string
+            ));
+        }
+
+        Ok(versions)
+    }
+
+    pub fn contains(&self, version: u32) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// Returns the newest version spoken by both ends, or an error if they have none in common.
+    pub fn negotiate(&self, other: &Versions) -> Result<u32, String> {
+        let best = self.max.min(other.max);
+
+        if self.contains(best) && other.contains(best) {
+            Ok(best)
+        } else {
+            Err(format!(
+                "no common protocol version (this end speaks {}, the other speaks {})",
+                self, other
+            ))
+        }
+    }
+}
+
+/// Returns the protocol version of an HTTP request or response, according to its `HEADER`.
+///
+/// Messages without the header are assumed to be version 1. Returns an error if the version can't
+/// be parsed, or if it is not one of the `supported` versions.
+pub fn version(message: &str) -> Result<u32, String> {
+    let Some(value) = crate::http::header(message, HEADER) else {
+        return Ok(MIN_PROTOCOL_VERSION);
+    };
+
+    let supported = Versions::supported();
+
+    match value.parse() {
+        Ok(version) if supported.contains(version) => Ok(version),
+        _ => Err(format!(
+            "unsupported protocol version '{}' (expected {})",
+            value, supported
+        )),
+    }
+}
+
+#[cfg(test)]
+mod protocol_tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_versions() {
+        assert_eq!(Versions::parse("1..2"), Ok(Versions::new(1, 2)));
+        assert_eq!(Versions::parse("2"), Ok(Versions::new(2, 2)));
+        assert_eq!(Versions::new(1, 2).to_string(), "1..2");
+        assert_eq!(Versions::new(3, 3).to_string(), "3");
+
+        assert!(Versions::parse("2..1").is_err());
+        assert!(Versions::parse("v2").is_err());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let ours = Versions::new(1, 2);
+
+        assert_eq!(ours.negotiate(&Versions::new(1, 1)), Ok(1));
+        assert_eq!(ours.negotiate(&Versions::new(1, 5)), Ok(2));
+        assert_eq!(ours.negotiate(&Versions::new(2, 3)), Ok(2));
+        assert_eq!(
+            ours.negotiate(&Versions::new(3, 4)),
+            Err(String::from(
+                "no common protocol version (this end speaks 1..2, the other speaks 3..4)"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(version("GET / HTTP/1.1\r\n\r\n"), Ok(1));
+        assert_eq!(
+            version("GET / HTTP/1.1\r\nProtocol-Version: 2\r\n\r\n"),
+            Ok(2)
+        );
+        assert_eq!(
+            version("HTTP/1.1 200 OK\r\nprotocol-version: 1\r\n\r\n21.5@°C"),
+            Ok(1)
+        );
+        assert_eq!(
+            version("GET / HTTP/1.1\r\nProtocol-Version: 9\r\n\r\n"),
+            Err(String::from(
+                "unsupported protocol version '9' (expected 1..3)"
+            ))
+        );
+    }
+}
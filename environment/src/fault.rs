@@ -0,0 +1,277 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::Duration;
+use rand::{thread_rng, Rng};
+
+use datum::{Datum, DatumValue};
+
+use crate::scenario::parse_duration;
+
+/// A `Fault` simulates misbehaving hardware, so we can test how the `Controller` handles it.
+///
+/// Faults are injected per `Id`, and affect every request for that `Id`'s `Datum`s.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Fault {
+    /// Always report this value, regardless of the real value.
+    StuckAt(DatumValue),
+    /// Respond with an HTTP 503 with this probability.
+    Unavailable(f32),
+    /// Close the connection without responding, with this probability.
+    NoResponse(f32),
+    /// Wait this long before responding.
+    Latency(Duration),
+    /// Add `magnitude` to the real value, with this `probability`.
+    Spike { probability: f32, magnitude: f32 },
+    /// Report a float which is not a number.
+    NaN,
+    /// Scramble the text of the `Datum` so it can't be parsed.
+    Garbled,
+    /// Shift timestamps by this amount, as if the device's clock were wrong.
+    ClockSkew(Duration),
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::StuckAt(value) => write!(f, "StuckAt:{}", value),
+            Fault::Unavailable(probability) => write!(f, "Unavailable:{}", probability),
+            Fault::NoResponse(probability) => write!(f, "NoResponse:{}", probability),
+            Fault::Latency(duration) => write!(f, "Latency:{}ms", duration.num_milliseconds()),
+            Fault::Spike {
+                probability,
+                magnitude,
+            } => write!(f, "Spike:{}:{}", probability, magnitude),
+            Fault::NaN => write!(f, "NaN"),
+            Fault::Garbled => write!(f, "Garbled"),
+            Fault::ClockSkew(duration) => {
+                write!(f, "ClockSkew:{}ms", duration.num_milliseconds())
+            }
+        }
+    }
+}
+
+fn parse_probability(string: &str) -> Result<f32, String> {
+    match string.parse::<f32>() {
+        Ok(probability) if (0.0..=1.0).contains(&probability) => Ok(probability),
+        _ => Err(format!(
+            "cannot parse {} as a probability in [0, 1]",
+            string
+        )),
+    }
+}
+
+impl Fault {
+    pub fn parse(string: &str) -> Result<Fault, String> {
+        let mut pieces = string.trim().split(':');
+
+        match (pieces.next(), pieces.next(), pieces.next(), pieces.next()) {
+            (Some("StuckAt"), Some(value), None, None) => {
+                Ok(Fault::StuckAt(DatumValue::parse(value.to_string())?))
+            }
+            (Some("Unavailable"), Some(probability), None, None) => {
+                Ok(Fault::Unavailable(parse_probability(probability)?))
+            }
+            (Some("NoResponse"), Some(probability), None, None) => {
+                Ok(Fault::NoResponse(parse_probability(probability)?))
+            }
+            (Some("Latency"), Some(duration), None, None) => {
+                let duration = parse_duration(duration)?;
+                if duration < Duration::zero() {
+                    Err(String::from("Latency cannot be negative"))
+                } else {
+                    Ok(Fault::Latency(duration))
+                }
+            }
+            (Some("Spike"), Some(probability), Some(magnitude), None) => match magnitude.parse() {
+                Ok(magnitude) => Ok(Fault::Spike {
+                    probability: parse_probability(probability)?,
+                    magnitude,
+                }),
+                Err(_) => Err(format!("cannot parse {} as f32", magnitude)),
+            },
+            (Some("NaN"), None, None, None) => Ok(Fault::NaN),
+            (Some("Garbled"), None, None, None) => Ok(Fault::Garbled),
+            (Some("ClockSkew"), Some(duration), None, None) => {
+                Ok(Fault::ClockSkew(parse_duration(duration)?))
+            }
+            _ => Err(format!("cannot parse {} as Fault", string)),
+        }
+    }
+}
+
+/// Returns `true` with the specified `probability`.
+fn roll(probability: f32) -> bool {
+    probability >= 1.0 || thread_rng().gen_range(0.0..1.0) < probability
+}
+
+/// Returns `true` if the request should be answered with an HTTP 503.
+pub(crate) fn is_unavailable(faults: &[Fault]) -> bool {
+    faults.iter().any(|fault| match fault {
+        Fault::Unavailable(probability) => roll(*probability),
+        _ => false,
+    })
+}
+
+/// Returns `true` if the request should not be answered at all.
+pub(crate) fn is_unresponsive(faults: &[Fault]) -> bool {
+    faults.iter().any(|fault| match fault {
+        Fault::NoResponse(probability) => roll(*probability),
+        _ => false,
+    })
+}
+
+/// Returns the total extra time to wait before responding.
+pub(crate) fn latency(faults: &[Fault]) -> Duration {
+    faults
+        .iter()
+        .map(|fault| match fault {
+            Fault::Latency(duration) => *duration,
+            _ => Duration::zero(),
+        })
+        .fold(Duration::zero(), |total, duration| total + duration)
+}
+
+/// Returns `true` if the text of the `Datum` should be scrambled.
+pub(crate) fn is_garbled(faults: &[Fault]) -> bool {
+    faults.contains(&Fault::Garbled)
+}
+
+/// Applies all faults which affect the value or timestamp of a `Datum`.
+pub(crate) fn corrupt(mut datum: Datum, faults: &[Fault]) -> Datum {
+    for fault in faults {
+        match fault {
+            Fault::StuckAt(value) => datum.value = *value,
+            Fault::Spike {
+                probability,
+                magnitude,
+            } if roll(*probability) => {
+                datum.value = match datum.value {
+                    DatumValue::Float(value) => DatumValue::Float(value + magnitude),
+                    DatumValue::Int(value) => DatumValue::Int(value + magnitude.round() as i32),
+                    DatumValue::Bool(value) => DatumValue::Bool(!value),
+                }
+            }
+            Fault::NaN => datum.value = DatumValue::Float(f32::NAN),
+            Fault::ClockSkew(duration) => datum.timestamp += *duration,
+            _ => (),
+        }
+    }
+
+    datum
+}
+
+/// Scrambles `text` so that it can no longer be parsed.
+pub(crate) fn garble(text: &str) -> String {
+    text.chars()
+        .rev()
+        .map(|c| if c.is_ascii_digit() { '?' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod fault_tests {
+    use chrono::Utc;
+
+    use datum::DatumUnit;
+
+    use super::*;
+
+    fn serde(fault: &Fault) -> Result<Fault, String> {
+        let serialized = fault.to_string();
+        Fault::parse(serialized.as_str())
+    }
+
+    #[test]
+    fn test_serde() {
+        let faults = vec![
+            Fault::StuckAt(DatumValue::Float(21.5)),
+            Fault::Unavailable(0.5),
+            Fault::NoResponse(1.0),
+            Fault::Latency(Duration::milliseconds(250)),
+            Fault::Spike {
+                probability: 0.1,
+                magnitude: -10.0,
+            },
+            Fault::NaN,
+            Fault::Garbled,
+            Fault::ClockSkew(Duration::minutes(-5)),
+        ];
+
+        for fault in faults {
+            assert_eq!(serde(&fault), Ok(fault))
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Fault::parse("Latency:2s"),
+            Ok(Fault::Latency(Duration::seconds(2)))
+        );
+        assert_eq!(
+            Fault::parse("StuckAt:true"),
+            Ok(Fault::StuckAt(DatumValue::Bool(true)))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Fault::parse("Unavailable:2").is_err());
+        assert!(Fault::parse("Unavailable").is_err());
+        assert!(Fault::parse("Latency:-2s").is_err());
+        assert!(Fault::parse("Spike:0.1").is_err());
+        assert!(Fault::parse("NaN:1").is_err());
+        assert!(Fault::parse("Gremlins").is_err());
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let now = Utc::now();
+        let datum = Datum::new(20.0, DatumUnit::DegreesC, now);
+
+        let spiked = corrupt(
+            datum.clone(),
+            &[Fault::Spike {
+                probability: 1.0,
+                magnitude: 30.0,
+            }],
+        );
+        assert_eq!(spiked.get_as_float(), Some(50.0));
+
+        let stuck = corrupt(datum.clone(), &[Fault::StuckAt(DatumValue::Float(5.0))]);
+        assert_eq!(stuck.get_as_float(), Some(5.0));
+
+        let nan = corrupt(datum.clone(), &[Fault::NaN]);
+        assert!(nan.get_as_float().unwrap().is_nan());
+
+        let skewed = corrupt(datum.clone(), &[Fault::ClockSkew(Duration::hours(1))]);
+        assert_eq!(skewed.timestamp, now + Duration::hours(1));
+    }
+
+    #[test]
+    fn test_probabilities() {
+        assert!(is_unavailable(&[Fault::Unavailable(1.0)]));
+        assert!(!is_unavailable(&[Fault::Unavailable(0.0)]));
+        assert!(!is_unavailable(&[Fault::NoResponse(1.0)]));
+        assert!(is_unresponsive(&[Fault::NoResponse(1.0)]));
+    }
+
+    #[test]
+    fn test_latency() {
+        let faults = [
+            Fault::Latency(Duration::milliseconds(100)),
+            Fault::NaN,
+            Fault::Latency(Duration::milliseconds(50)),
+        ];
+
+        assert_eq!(latency(&faults), Duration::milliseconds(150));
+    }
+
+    #[test]
+    fn test_garble() {
+        let datum = Datum::new(20.0, DatumUnit::DegreesC, Utc::now());
+        let garbled = garble(&datum.to_string());
+
+        assert!(Datum::parse(&garbled).is_err());
+    }
+}
@@ -1,33 +1,86 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use chrono::{DateTime, Utc};
 
 use datum::{Datum, DatumUnit, DatumValue};
 
+type Generator = Box<dyn FnMut(DateTime<Utc>) -> DatumValue + Send>;
+
+/// Generates `Datum`s for a single attribute of the `Environment`.
+///
+/// Cloning a `DatumGenerator` does not create a new generator; all clones share the same state, so
+/// (for example) the outside temperature can be probed by a `Sensor` and also drive a `ThermalModel`.
+#[derive(Clone)]
 pub struct DatumGenerator {
-    generator: Mutex<Box<dyn FnMut(DateTime<Utc>) -> DatumValue>>,
+    generator: Arc<Mutex<Generator>>,
+    offset: Arc<Mutex<f32>>,
+    latest: Arc<Mutex<Option<Datum>>>,
     unit: DatumUnit,
+    kind: &'static str,
 }
 
 impl DatumGenerator {
-    pub(crate) fn new(
-        generator: Box<dyn FnMut(DateTime<Utc>) -> DatumValue>,
-        unit: DatumUnit,
-    ) -> DatumGenerator {
+    pub(crate) fn new(generator: Generator, unit: DatumUnit) -> DatumGenerator {
         DatumGenerator {
-            generator: Mutex::new(generator),
+            generator: Arc::new(Mutex::new(generator)),
+            offset: Arc::new(Mutex::new(0.0)),
+            latest: Arc::new(Mutex::new(None)),
             unit,
+            kind: "custom",
         }
     }
 
+    /// Describes what kind of generator this is, e.g. "thermal" or "f32_linear".
+    pub(crate) fn with_kind(mut self, kind: &'static str) -> DatumGenerator {
+        self.kind = kind;
+        self
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub(crate) fn unit(&self) -> DatumUnit {
+        self.unit
+    }
+
+    /// Returns the most recently generated `Datum`, if any.
+    pub(crate) fn latest(&self) -> Option<Datum> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// Adds `delta` to all numeric values generated from now on (and by all clones of this generator).
+    pub(crate) fn shift(&self, delta: f32) {
+        let mut offset = self.offset.lock().unwrap();
+        *offset += delta;
+    }
+
     pub(crate) fn generate(&self) -> Datum {
-        let now = Utc::now();
+        self.generate_at(Utc::now())
+    }
+
+    /// Generates a `Datum` as of the specified time, rather than as of "now".
+    pub(crate) fn generate_at(&self, now: DateTime<Utc>) -> Datum {
         let mut generator = self.generator.lock().unwrap();
-        let value = (*generator)(now);
-        Datum::new(value, self.unit, now)
+        let offset = *self.offset.lock().unwrap();
+
+        let value = match (*generator)(now) {
+            DatumValue::Float(value) => DatumValue::Float(value + offset),
+            DatumValue::Int(value) => DatumValue::Int(value + offset.round() as i32),
+            value => value,
+        };
+
+        let datum = Datum::new(value, self.unit, now);
+        *self.latest.lock().unwrap() = Some(datum.clone());
+        datum
     }
 }
 
+pub fn constant(value: DatumValue, unit: DatumUnit) -> DatumGenerator {
+    let f = move |_| -> DatumValue { value };
+    DatumGenerator::new(Box::new(f), unit).with_kind("constant")
+}
+
 pub mod time_dependent {
     use chrono::{DateTime, Utc};
     use rand::{thread_rng, Rng};
@@ -38,30 +91,49 @@ pub mod time_dependent {
 
     pub fn f32_linear(slope: f32, noise: f32, unit: DatumUnit) -> DatumGenerator {
         let start = Utc::now().timestamp_millis();
-        let mut rng = thread_rng();
 
         let f = move |now: DateTime<Utc>| -> DatumValue {
+            let mut rng = thread_rng();
             // converting i64 to f32 is safe as long as this demo is running for < 9.4e28 hours
             let delta = (now.timestamp_millis() - start) as f32;
             let noise_factor = rng.gen_range(-1.0..1.0) * noise;
             DatumValue::Float(delta * slope + noise_factor)
         };
 
-        DatumGenerator::new(Box::new(f), unit)
+        DatumGenerator::new(Box::new(f), unit).with_kind("f32_linear")
     }
 
     pub fn i32_linear(slope: i32, noise: i32, unit: DatumUnit) -> DatumGenerator {
         let start = Utc::now().timestamp_millis();
-        let mut rng = thread_rng();
 
         let f = move |now: DateTime<Utc>| -> DatumValue {
+            let mut rng = thread_rng();
             // truncating i64 to i32 is safe as long as this demo is running for < 596.5 hours
             let delta = (now.timestamp_millis() - start) as i32;
             let noise_factor = rng.gen_range(-1..1) * noise;
             DatumValue::Int(delta * slope + noise_factor)
         };
 
-        DatumGenerator::new(Box::new(f), unit)
+        DatumGenerator::new(Box::new(f), unit).with_kind("i32_linear")
+    }
+
+    /// Oscillates around `mean` with the given `amplitude`, completing one cycle every `period`.
+    pub fn f32_sinusoidal(
+        mean: f32,
+        amplitude: f32,
+        period: chrono::Duration,
+        unit: DatumUnit,
+    ) -> DatumGenerator {
+        let start = Utc::now().timestamp_millis();
+        let period = period.num_milliseconds() as f32;
+
+        let f = move |now: DateTime<Utc>| -> DatumValue {
+            let delta = (now.timestamp_millis() - start) as f32;
+            let phase = std::f32::consts::TAU * delta / period;
+            DatumValue::Float(mean + amplitude * phase.sin())
+        };
+
+        DatumGenerator::new(Box::new(f), unit).with_kind("sinusoidal")
     }
 }
 
@@ -73,7 +145,13 @@ pub fn bool_alternating(initial: bool, unit: DatumUnit) -> DatumGenerator {
         DatumValue::Bool(latest_value)
     };
 
-    DatumGenerator::new(Box::new(f), unit)
+    DatumGenerator::new(Box::new(f), unit).with_kind("alternating")
+}
+
+/// Generates `initial` until it is replaced (e.g. a door which stays shut until someone opens it).
+pub fn bool_steady(initial: bool, unit: DatumUnit) -> DatumGenerator {
+    let f = move |_| -> DatumValue { DatumValue::Bool(initial) };
+    DatumGenerator::new(Box::new(f), unit).with_kind("steady")
 }
 
 #[cfg(test)]
@@ -82,7 +160,7 @@ mod generator_tests {
 
     use chrono::Duration;
 
-    use datum::DatumUnit;
+    use datum::{DatumUnit, DatumValue};
 
     use super::*;
 
@@ -146,6 +224,58 @@ mod generator_tests {
         assert!(earlier.get_as_int() > later.get_as_int());
     }
 
+    #[test]
+    /// Tests that a sinusoidal generator stays within `mean ± amplitude`
+    fn test_f32_sinusoidal_bounded() {
+        let generator =
+            time_dependent::f32_sinusoidal(10.0, 5.0, Duration::hours(24), DatumUnit::DegreesC);
+
+        let start = Utc::now();
+
+        for hour in 0..48 {
+            let datum = generator.generate_at(start + Duration::hours(hour));
+            let value = datum.get_as_float().unwrap();
+            assert!((5.0..=15.0).contains(&value), "{} is out of range", value);
+        }
+    }
+
+    #[test]
+    fn test_latest() {
+        let generator = bool_alternating(true, DatumUnit::PoweredOn);
+        assert_eq!(generator.latest(), None);
+
+        let datum = generator.generate();
+        assert_eq!(generator.latest(), Some(datum));
+        assert_eq!(generator.kind(), "alternating");
+    }
+
+    #[test]
+    fn test_constant() {
+        let generator = constant(DatumValue::Int(42), DatumUnit::Unitless);
+
+        assert_eq!(generator.generate().get_as_int(), Some(42));
+        assert_eq!(generator.generate().get_as_int(), Some(42));
+    }
+
+    #[test]
+    /// Shifting a generator shifts the values generated by all of its clones
+    fn test_shift() {
+        let generator = constant(DatumValue::Float(10.0), DatumUnit::DegreesC);
+        let clone = generator.clone();
+
+        clone.shift(-8.0);
+
+        assert_eq!(generator.generate().get_as_float(), Some(2.0));
+        assert_eq!(clone.generate().get_as_float(), Some(2.0));
+    }
+
+    #[test]
+    fn test_shift_int() {
+        let generator = constant(DatumValue::Int(10), DatumUnit::Unitless);
+        generator.shift(2.6);
+        assert_eq!(generator.generate().get_as_int(), Some(13));
+    }
+
     #[test]
     fn test_bool_alternating() {
         let initial = false;
@@ -161,4 +291,16 @@ mod generator_tests {
         assert_eq!(second.get_as_bool(), Some(true));
         assert_eq!(third.get_as_bool(), Some(false));
     }
+
+    #[test]
+    fn test_bool_steady() {
+        let generator = bool_steady(true, DatumUnit::Unitless);
+
+        // a steady generator never changes by itself, no matter how often it is read
+        for _ in 0..3 {
+            assert_eq!(generator.generate().get_as_bool(), Some(true));
+        }
+
+        assert_eq!(generator.kind(), "steady");
+    }
 }
@@ -0,0 +1,210 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+use actuator_humidity::Command;
+use datum::{DatumUnit, DatumValue};
+
+use crate::generator::DatumGenerator;
+
+/// The physical parameters of a single zone (room, greenhouse) in a `HumidityModel`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct HumidityParameters {
+    /// The relative humidity the zone drifts towards when left alone, in %RH.
+    pub ambient: f32,
+    /// How quickly the zone drifts towards the `ambient` humidity, as the fraction of the
+    /// difference which is made up every hour.
+    pub exchange_rate: f32,
+    /// How quickly the humidifier raises the humidity of the zone when it is running, in %RH/h.
+    pub humidifier_rate: f32,
+    /// How quickly the dehumidifier lowers the humidity of the zone when it is running, in %RH/h.
+    pub dehumidifier_rate: f32,
+}
+
+impl Default for HumidityParameters {
+    /// A small room, which exchanges half of its air with a 50%RH outside every hour.
+    fn default() -> Self {
+        Self {
+            ambient: 50.0,
+            exchange_rate: 0.5,
+            humidifier_rate: 40.0,
+            dehumidifier_rate: 30.0,
+        }
+    }
+}
+
+/// A model of the relative humidity in a single zone.
+///
+/// The zone drifts towards the `ambient` humidity, and a humidifier or dehumidifier raises or lowers
+/// the humidity at a fixed rate, depending on the latest `Command` sent by an `Actuator`. The
+/// humidifier runs while the zone is below its `HumidifyTo` target; the dehumidifier runs while
+/// the zone is above its `DehumidifyTo` target.
+///
+/// Like a `ThermalModel`, the model is integrated forward in time whenever it is queried.
+pub struct HumidityModel {
+    humidity: f32,
+    parameters: HumidityParameters,
+    command: Option<Command>,
+    updated: DateTime<Utc>,
+}
+
+impl HumidityModel {
+    /// The longest time step used when integrating the model, to keep the integration stable.
+    const MAX_STEP_SECONDS: i64 = 10;
+
+    pub fn new(
+        humidity: f32,
+        parameters: HumidityParameters,
+        start: DateTime<Utc>,
+    ) -> HumidityModel {
+        HumidityModel {
+            humidity,
+            parameters,
+            command: None,
+            updated: start,
+        }
+    }
+
+    /// Returns the relative humidity of the zone as of the last time the model was advanced.
+    pub fn humidity(&self) -> f32 {
+        self.humidity
+    }
+
+    /// Returns the latest `Command` received by this zone, if any.
+    pub fn command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
+
+    /// Integrates the model forward to `now`, then applies the `command` from that point onward.
+    pub fn apply(&mut self, command: Command, now: DateTime<Utc>) {
+        self.advance(now);
+        self.command = Some(command);
+    }
+
+    /// Integrates the model forward by `duration`, returning the new humidity of the zone.
+    pub fn step(&mut self, duration: Duration) -> f32 {
+        self.advance(self.updated + duration)
+    }
+
+    /// Integrates the model forward to `now`, returning the new humidity of the zone.
+    ///
+    /// If `now` is earlier than the last time the model was advanced, the model is left unchanged.
+    pub fn advance(&mut self, now: DateTime<Utc>) -> f32 {
+        let max_step = Duration::seconds(Self::MAX_STEP_SECONDS);
+
+        while self.updated < now {
+            let step = std::cmp::min(max_step, now - self.updated);
+            let hours = step.num_milliseconds() as f32 / 3_600_000.0;
+
+            let drift = self.parameters.exchange_rate * (self.parameters.ambient - self.humidity);
+            let rate = drift + self.device_rate();
+
+            self.humidity = (self.humidity + rate * hours).clamp(0.0, 100.0);
+            self.updated += step;
+        }
+
+        self.humidity
+    }
+
+    /// Returns how quickly the humidifier (positive) or dehumidifier (negative) is currently
+    /// changing the humidity of the zone, in %RH/h.
+    fn device_rate(&self) -> f32 {
+        match self.command {
+            Some(Command::HumidifyTo(target)) if self.humidity < target => {
+                self.parameters.humidifier_rate
+            }
+            Some(Command::DehumidifyTo(target)) if self.humidity > target => {
+                -self.parameters.dehumidifier_rate
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Creates a `DatumGenerator` which reads the humidity of a zone by integrating its `model`.
+pub fn generator(model: Arc<Mutex<HumidityModel>>) -> DatumGenerator {
+    let f = move |now: DateTime<Utc>| -> DatumValue {
+        let mut model = model.lock().unwrap();
+        DatumValue::Float(model.advance(now))
+    };
+
+    DatumGenerator::new(Box::new(f), DatumUnit::RelativeHumidity).with_kind("humidity")
+}
+
+#[cfg(test)]
+mod humidity_tests {
+    use super::*;
+
+    fn zone(initial: f32) -> HumidityModel {
+        HumidityModel::new(initial, HumidityParameters::default(), Utc::now())
+    }
+
+    #[test]
+    /// With no humidifying or dehumidifying, the zone drifts towards the ambient humidity
+    fn test_zone_drifts_to_ambient() {
+        let mut model = zone(80.0);
+
+        let after_an_hour = model.step(Duration::hours(1));
+        let after_a_day = model.step(Duration::hours(23));
+
+        assert!(after_an_hour < 80.0);
+        assert!(after_a_day < after_an_hour);
+        assert!(
+            (after_a_day - 50.0).abs() < 0.5,
+            "{} is not ~50",
+            after_a_day
+        );
+    }
+
+    #[test]
+    /// The humidifier holds the zone at (about) its target humidity
+    fn test_humidify_to() {
+        let mut model = zone(40.0);
+        let now = model.updated;
+
+        model.apply(Command::HumidifyTo(70.0), now);
+        let humidity = model.step(Duration::hours(12));
+
+        assert!((humidity - 70.0).abs() < 0.5, "{} is not ~70", humidity);
+    }
+
+    #[test]
+    /// The dehumidifier holds the zone at (about) its target humidity
+    fn test_dehumidify_to() {
+        let mut model = zone(60.0);
+        let now = model.updated;
+
+        model.apply(Command::DehumidifyTo(40.0), now);
+        let humidity = model.step(Duration::hours(12));
+
+        assert!((humidity - 40.0).abs() < 0.5, "{} is not ~40", humidity);
+    }
+
+    #[test]
+    /// Turning the humidifier off lets the zone drift back to the ambient humidity
+    fn test_off() {
+        let mut model = zone(50.0);
+        let now = model.updated;
+
+        model.apply(Command::HumidifyTo(80.0), now);
+        let humidified = model.step(Duration::hours(4));
+
+        model.apply(Command::Off, model.updated);
+        let after = model.step(Duration::hours(12));
+
+        assert!(humidified > 70.0);
+        assert!((after - 50.0).abs() < 0.5, "{} is not ~50", after);
+    }
+
+    #[test]
+    fn test_generator() {
+        let start = Utc::now();
+        let model = HumidityModel::new(50.0, HumidityParameters::default(), start);
+        let generator = generator(Arc::new(Mutex::new(model)));
+
+        let datum = generator.generate_at(start + Duration::minutes(5));
+
+        assert_eq!(datum.get_as_float(), Some(50.0));
+        assert_eq!(datum.unit, DatumUnit::RelativeHumidity);
+    }
+}
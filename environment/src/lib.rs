@@ -1,42 +1,543 @@
 use std::collections::HashMap;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::net::{TcpListener, TcpStream};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
+use chrono::{DateTime, Utc};
 use rand::{thread_rng, Rng};
+use tracing::warn;
 
-use datum::{Datum, DatumUnit, DatumValueType};
-use device::Id;
+use actuator::Command as _;
+use actuator_temperature::Command;
+use datum::{Datum, DatumUnit, DatumValue, DatumValueType};
+use device::{protocol, Id, Name};
 
+use crate::fault::Fault;
 use crate::generator::DatumGenerator;
+use crate::humidity::{HumidityModel, HumidityParameters};
+use crate::light::{LightModel, LightParameters};
+use crate::plug::PlugModel;
+use crate::scenario::{Action, Attribute, Event, Generator, Key, Scenario};
+use crate::thermal::{ThermalModel, ZoneParameters};
 
-mod generator;
+pub mod fault;
+pub mod generator;
+pub mod humidity;
+pub mod light;
+pub mod plug;
+pub mod replay;
+pub mod scenario;
+pub mod thermal;
+
+/// The address the `Environment`'s HTTP server listens on by default.
+pub const ADDRESS: &str = device::config::ENVIRONMENT;
+
+/// Links a thermal zone to the contact sensor of one of its windows.
+struct Window {
+    zone: Id,
+    /// The zone's heat loss while the window is closed, in W/°C.
+    closed: f32,
+    /// The zone's heat loss while the window is open, in W/°C.
+    open: f32,
+}
 
 /// A test-only example environment which produces data detected by `Sensor`s.
 ///
 /// The `Environment` can be mutated by `Actuator`s.
 #[derive(Default)] // gives us an "empty" Environment with Environment::default()
-struct Environment {
-    #[allow(dead_code)] // remove this ASAP
+pub struct Environment {
     attributes: Mutex<HashMap<Id, DatumGenerator>>,
+    zones: Mutex<HashMap<Id, Arc<Mutex<ThermalModel>>>>,
+    humidity_zones: Mutex<HashMap<Id, Arc<Mutex<HumidityModel>>>>,
+    light_zones: Mutex<HashMap<Id, Arc<Mutex<LightModel>>>>,
+    plugs: Mutex<HashMap<Id, Arc<Mutex<PlugModel>>>>,
+    /// The thermal zones whose heat loss depends on each window's contact sensor attribute.
+    windows: Mutex<HashMap<Id, Vec<Window>>>,
+    /// Attributes from a `Scenario` which are waiting for a `Sensor` with the specified `Name`.
+    named: Mutex<HashMap<Name, Attribute>>,
+    /// The `Id`s of all `Sensor`s which have told us their `Name`.
+    names: Mutex<HashMap<Name, Id>>,
+    /// `Scenario` events which have not yet occurred, in the order in which they will occur.
+    events: Mutex<Vec<(DateTime<Utc>, Event)>>,
+    faults: Mutex<HashMap<Id, Vec<Fault>>>,
 }
 
 impl Environment {
-    #[allow(dead_code)] // remove this ASAP
-    fn new() -> Environment {
-        Environment {
-            attributes: Mutex::new(HashMap::new()),
-        }
+    pub fn new() -> Environment {
+        Environment::default()
     }
 
-    #[allow(dead_code)] // remove this ASAP
     fn set(&self, id: Id, generator: DatumGenerator) {
         let mut attributes = self.attributes.lock().unwrap();
         attributes.insert(id, generator);
     }
 
-    #[allow(dead_code)] // remove this ASAP
+    /// Models the temperature of the zone with the specified `Id` with a `ThermalModel`.
+    ///
+    /// Temperature `Datum`s for this `Id` are generated by integrating the model, and `Command`s
+    /// sent by `Actuator`s with this `Id` switch the zone's heater and cooler on and off.
+    fn set_zone(&self, id: Id, model: ThermalModel) {
+        let model = Arc::new(Mutex::new(model));
+        self.set(id.clone(), thermal::generator(Arc::clone(&model)));
+
+        let mut zones = self.zones.lock().unwrap();
+        zones.insert(id, model);
+    }
+
+    /// Models the relative humidity of the zone with the specified `Id` with a `HumidityModel`.
+    ///
+    /// Humidity `Datum`s for this `Id` are generated by integrating the model, and
+    /// `actuator_humidity::Command`s sent by `Actuator`s with this `Id` switch the zone's
+    /// humidifier and dehumidifier on and off.
+    fn set_humidity_zone(&self, id: Id, model: HumidityModel) {
+        let model = Arc::new(Mutex::new(model));
+        self.set(id.clone(), humidity::generator(Arc::clone(&model)));
+
+        let mut zones = self.humidity_zones.lock().unwrap();
+        zones.insert(id, model);
+    }
+
+    /// Models the illuminance of the zone with the specified `Id` with a `LightModel`.
+    ///
+    /// Illuminance `Datum`s for this `Id` are read from the model, and `actuator_light::Command`s
+    /// sent by `Actuator`s with this `Id` switch and dim the zone's lamps.
+    fn set_light_zone(&self, id: Id, model: LightModel) {
+        let model = Arc::new(Mutex::new(model));
+        self.set(id.clone(), light::generator(Arc::clone(&model)));
+
+        let mut zones = self.light_zones.lock().unwrap();
+        zones.insert(id, model);
+    }
+
+    /// Models the smart plug with the specified `Id` with a `PlugModel`.
+    ///
+    /// Power state `Datum`s for this `Id` are read from the model, and `actuator_plug::Command`s
+    /// sent by `Actuator`s with this `Id` switch the plug on and off.
+    fn set_plug(&self, id: Id, model: PlugModel) {
+        let model = Arc::new(Mutex::new(model));
+        self.set(id.clone(), plug::generator(Arc::clone(&model)));
+
+        let mut plugs = self.plugs.lock().unwrap();
+        plugs.insert(id, model);
+    }
+
+    /// Forgets any thermal, humidity, light, or plug model with the specified `Id`.
+    fn remove_zone(&self, id: &Id) {
+        self.zones.lock().unwrap().remove(id);
+        self.humidity_zones.lock().unwrap().remove(id);
+        self.light_zones.lock().unwrap().remove(id);
+        self.plugs.lock().unwrap().remove(id);
+    }
+
+    /// Creates a model with typical parameters for the attribute with the specified `Id`, if there
+    /// is no such attribute yet and attributes with this `unit` are modelled physically.
+    fn model(&self, id: &Id, unit: DatumUnit) {
+        if self.attributes.lock().unwrap().contains_key(id) {
+            return;
+        }
+
+        let id = id.clone();
+
+        match unit {
+            DatumUnit::DegreesC => self.set_zone(id, Environment::default_zone()),
+            DatumUnit::RelativeHumidity => self.set_humidity_zone(
+                id,
+                HumidityModel::new(50.0, HumidityParameters::default(), Utc::now()),
+            ),
+            DatumUnit::Lux => self.set_light_zone(id, LightModel::new(LightParameters::default())),
+            DatumUnit::PoweredOn => self.set_plug(id, PlugModel::new(false)),
+            DatumUnit::Unitless | DatumUnit::PartsPerMillion => {}
+        }
+    }
+
+    /// An outside temperature which varies from 5°C to 15°C over the course of a day.
+    fn default_outside() -> DatumGenerator {
+        generator::time_dependent::f32_sinusoidal(
+            10.0,
+            5.0,
+            chrono::Duration::days(1),
+            DatumUnit::DegreesC,
+        )
+    }
+
+    /// Creates a zone with typical parameters, starting at room temperature.
+    fn default_zone() -> ThermalModel {
+        let outside = Environment::default_outside();
+        ThermalModel::new(20.0, ZoneParameters::default(), outside, Utc::now())
+    }
+
+    /// Replaces all attributes with `Id` keys with those defined in the `scenario`, and schedules
+    /// its events relative to now.
+    ///
+    /// Attributes with `Name` keys are created when a `Sensor` with that `Name` first probes the
+    /// `Environment`.
+    pub fn load(&self, scenario: Scenario) {
+        let now = Utc::now();
+
+        // thermal zones are created last, so the outside temperatures they depend on exist
+        let (zones, others): (Vec<_>, Vec<_>) = scenario
+            .attributes
+            .into_iter()
+            .partition(|attribute| matches!(attribute.generator, Generator::Thermal { .. }));
+
+        for attribute in others.into_iter().chain(zones) {
+            match attribute.key.clone() {
+                Key::Id(id) => self.add(id, attribute, now),
+                Key::Name(name) => {
+                    let mut named = self.named.lock().unwrap();
+                    named.insert(name, attribute);
+                }
+            }
+        }
+
+        let mut events = self.events.lock().unwrap();
+        events.extend(
+            scenario
+                .events
+                .into_iter()
+                .map(|event| (now + event.at, event)),
+        );
+        events.sort_by_key(|(at, _)| *at);
+    }
+
+    /// Injects a `Fault` into all future responses to requests for the specified `Id`.
+    pub fn inject(&self, id: Id, fault: Fault) {
+        let mut faults = self.faults.lock().unwrap();
+        faults.entry(id).or_default().push(fault);
+    }
+
+    /// Removes all `Fault`s for the specified `Id`.
+    pub fn clear_faults(&self, id: &Id) {
+        let mut faults = self.faults.lock().unwrap();
+        faults.remove(id);
+    }
+
+    fn faults(&self, id: &Id) -> Vec<Fault> {
+        let faults = self.faults.lock().unwrap();
+        faults.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Creates a generator for the `attribute` described by a `Scenario`, with the specified `Id`.
+    fn add(&self, id: Id, attribute: Attribute, now: DateTime<Utc>) {
+        let unit = attribute.unit;
+
+        for fault in attribute.faults {
+            self.inject(id.clone(), fault);
+        }
+
+        let generator = match attribute.generator {
+            Generator::Constant(value) => generator::constant(value, unit),
+            Generator::F32Linear {
+                initial,
+                slope,
+                noise,
+            } => {
+                let generator = generator::time_dependent::f32_linear(slope, noise, unit);
+                generator.shift(initial);
+                generator
+            }
+            Generator::I32Linear {
+                initial,
+                slope,
+                noise,
+            } => {
+                let generator = generator::time_dependent::i32_linear(slope, noise, unit);
+                generator.shift(initial as f32);
+                generator
+            }
+            Generator::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+            } => generator::time_dependent::f32_sinusoidal(mean, amplitude, period, unit),
+            Generator::Alternating(initial) => generator::bool_alternating(initial, unit),
+            Generator::Steady(initial) => generator::bool_steady(initial, unit),
+            Generator::Replay { recording, options } => replay::generator(recording, options),
+            Generator::Thermal {
+                initial,
+                parameters,
+                outside,
+                window,
+            } => {
+                let attributes = self.attributes.lock().unwrap();
+                let outside = outside
+                    .and_then(|outside| attributes.get(&outside).cloned())
+                    .unwrap_or_else(Environment::default_outside);
+                drop(attributes);
+
+                let model = ThermalModel::new(initial, parameters, outside, now);
+                self.set_zone(id.clone(), model);
+
+                if let Some((window, open)) = window {
+                    let link = Window {
+                        zone: id,
+                        closed: parameters.heat_loss,
+                        open,
+                    };
+                    let mut windows = self.windows.lock().unwrap();
+                    windows.entry(window.clone()).or_default().push(link);
+                    drop(windows);
+
+                    // the window may already be open when the zone is created
+                    let attributes = self.attributes.lock().unwrap();
+                    let state = attributes.get(&window).map(|g| g.generate().value);
+                    drop(attributes);
+
+                    if let Some(DatumValue::Bool(open)) = state {
+                        self.open_window(&window, open, now);
+                    }
+                }
+
+                return;
+            }
+            Generator::Humidity {
+                initial,
+                parameters,
+            } => {
+                let model = HumidityModel::new(initial, parameters, now);
+                return self.set_humidity_zone(id, model);
+            }
+            Generator::Light(parameters) => {
+                return self.set_light_zone(id, LightModel::new(parameters));
+            }
+            Generator::Plug(on) => return self.set_plug(id, PlugModel::new(on)),
+        };
+
+        self.set(id, generator)
+    }
+
+    /// Replaces the generator for the specified `Id` with one described by `attribute`, along with
+    /// any faults injected for it.
+    pub fn replace(&self, id: Id, attribute: Attribute) {
+        self.remove_zone(&id);
+        self.clear_faults(&id);
+        self.add(id, attribute, Utc::now())
+    }
+
+    /// Replaces the generator for the specified `Id` with a constant `value`, keeping its unit.
+    ///
+    /// Opening or closing a window (pinning its contact sensor to `true` or `false`) changes the
+    /// heat loss of the thermal zones linked to it.
+    pub fn pin(&self, id: &Id, value: DatumValue) {
+        let existing = self.attributes.lock().unwrap().get(id).cloned();
+        let unit = existing.as_ref().map(|g| g.unit()).unwrap_or_default();
+        self.remove_zone(id);
+
+        let generator = match (existing.map(|g| g.kind()), value) {
+            (Some("steady"), DatumValue::Bool(value)) => generator::bool_steady(value, unit),
+            _ => generator::constant(value, unit),
+        };
+        self.set(id.clone(), generator);
+
+        if let DatumValue::Bool(open) = value {
+            self.open_window(id, open, Utc::now());
+        }
+    }
+
+    /// Sets the heat loss of all thermal zones linked to the window with the specified `Id`.
+    fn open_window(&self, window: &Id, open: bool, now: DateTime<Utc>) {
+        let windows = self.windows.lock().unwrap();
+        let zones = self.zones.lock().unwrap();
+
+        for link in windows.get(window).into_iter().flatten() {
+            if let Some(model) = zones.get(&link.zone) {
+                let heat_loss = if open { link.open } else { link.closed };
+                model.lock().unwrap().set_heat_loss(heat_loss, now)
+            }
+        }
+    }
+
+    /// Forgets the attribute with the specified `Id`, and any faults injected for it, returning
+    /// `true` if there was one.
+    ///
+    /// The next time a `Sensor` with this `Id` probes the `Environment`, a new generator is created.
+    pub fn remove(&self, id: &Id) -> bool {
+        self.remove_zone(id);
+        self.clear_faults(id);
+        self.attributes.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Describes all known attributes as JSON, sorted by `Id`.
+    fn describe(&self) -> serde_json::Value {
+        let attributes = self.attributes.lock().unwrap();
+        let names = self.names.lock().unwrap();
+
+        let mut ids: Vec<&Id> = attributes.keys().collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let described = ids.into_iter().map(|id| {
+            let generator = attributes.get(id).unwrap();
+
+            let name = names
+                .iter()
+                .find(|(_, named)| *named == id)
+                .map(|(name, _)| name.to_string());
+
+            serde_json::json!({
+                "id": id.to_string(),
+                "name": name,
+                "generator": generator.kind(),
+                "unit": generator.unit().to_string(),
+                "latest": generator.latest().map(|datum| datum.to_string()),
+            })
+        });
+
+        serde_json::Value::Array(described.collect())
+    }
+
+    /// Finds the `Id` of an attribute given either its `Id` or the `Name` of its `Sensor`.
+    fn resolve(&self, key: &str) -> Id {
+        let names = self.names.lock().unwrap();
+
+        match names.get(&Name::new(key)) {
+            Some(id) => id.clone(),
+            None => Id::new(key),
+        }
+    }
+
+    /// Handles requests to the admin API, which inspects and overrides attributes at runtime.
+    ///
+    /// - `GET /attributes` lists all attributes
+    /// - `GET /attributes/<id>` describes a single attribute
+    /// - `PUT /attributes/<id>` replaces an attribute's generator (the body is a JSON `Scenario` attribute)
+    /// - `PUT /attributes/<id>/value` pins an attribute to a constant value (the body is the value)
+    /// - `DELETE /attributes/<id>` forgets an attribute
+    ///
+    /// Attributes can be identified by their `Id` or by the `Name` of their `Sensor`.
+    fn handle_admin_request(&self, request: &str) -> String {
+        let method = request.split_whitespace().next().unwrap_or_default();
+        let path = Environment::parse_path(request);
+        let key = path
+            .trim_start_matches("/attributes")
+            .trim_start_matches('/');
+
+        match (method, key.strip_suffix("/value")) {
+            ("GET", _) if key.is_empty() => {
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+                    self.describe()
+                )
+            }
+            ("GET", _) => {
+                let id = self.resolve(key);
+                let all = self.describe();
+                let found = all
+                    .as_array()
+                    .and_then(|all| all.iter().find(|attribute| attribute["id"] == id.0));
+
+                match found {
+                    Some(attribute) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+                        attribute
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+                }
+            }
+            ("PUT", Some(key)) => {
+                let id = self.resolve(key);
+
+                match DatumValue::parse(Environment::parse_body(request).to_string()) {
+                    Ok(value) => {
+                        self.pin(&id, value);
+                        "HTTP/1.1 200 OK\r\n\r\n".to_string()
+                    }
+                    Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+                }
+            }
+            ("PUT", None) if !key.is_empty() => {
+                let id = self.resolve(key);
+
+                match Attribute::parse_json(&id, Environment::parse_body(request)) {
+                    Ok(attribute) => {
+                        self.replace(id, attribute);
+                        "HTTP/1.1 200 OK\r\n\r\n".to_string()
+                    }
+                    Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+                }
+            }
+            ("DELETE", None) if !key.is_empty() => {
+                if self.remove(&self.resolve(key)) {
+                    "HTTP/1.1 200 OK\r\n\r\n".to_string()
+                } else {
+                    "HTTP/1.1 404 Not Found\r\n\r\n".to_string()
+                }
+            }
+            _ => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+        }
+    }
+
+    /// Associates a `Sensor`'s `Name` with its `Id`, creating the attribute described by the
+    /// `Scenario` for that `Name`, if there is one.
+    fn bind(&self, id: &Id, name: Name) {
+        let mut names = self.names.lock().unwrap();
+
+        if names.contains_key(&name) {
+            return;
+        }
+
+        names.insert(name.clone(), id.clone());
+        drop(names);
+
+        let attribute = self.named.lock().unwrap().remove(&name);
+
+        if let Some(attribute) = attribute {
+            self.add(id.clone(), attribute, Utc::now());
+        }
+    }
+
+    /// Applies all `Scenario` events which should have occurred by `now`.
+    fn apply_events(&self, now: DateTime<Utc>) {
+        let mut events = self.events.lock().unwrap();
+        let mut pending = Vec::new();
+
+        for (at, event) in events.drain(..) {
+            if at > now {
+                pending.push((at, event));
+                continue;
+            }
+
+            let id = match &event.key {
+                Key::Id(id) => Some(id.clone()),
+                Key::Name(name) => self.names.lock().unwrap().get(name).cloned(),
+            };
+
+            match id {
+                Some(id) => self.apply(&id, &event.action, now),
+                // no Sensor with this Name has probed the Environment yet, so try again later
+                None => pending.push((at, event)),
+            }
+        }
+
+        *events = pending;
+    }
+
+    fn apply(&self, id: &Id, action: &Action, now: DateTime<Utc>) {
+        match action {
+            Action::Offset(delta) => {
+                if let Some(generator) = self.attributes.lock().unwrap().get(id) {
+                    generator.shift(*delta)
+                }
+            }
+            Action::Value(value) => self.pin(id, *value),
+            Action::HeatLoss(heat_loss) => {
+                if let Some(model) = self.zones.lock().unwrap().get(id) {
+                    model.lock().unwrap().set_heat_loss(*heat_loss, now)
+                }
+            }
+        }
+    }
+
     fn get(&mut self, id: &Id, kind: DatumValueType, unit: DatumUnit) -> Datum {
+        // temperatures, humidities, light levels, and plugs are modelled physically, so they
+        // respond to commands sent by Actuators
+        match (&kind, unit) {
+            (DatumValueType::Float, DatumUnit::DegreesC)
+            | (DatumValueType::Float, DatumUnit::RelativeHumidity)
+            | (DatumValueType::Float, DatumUnit::Lux)
+            | (DatumValueType::Bool, DatumUnit::PoweredOn) => self.model(id, unit),
+            _ => {}
+        }
+
         let mut attributes = self.attributes.lock().unwrap();
         match attributes.get_mut(id) {
             Some(generator) => generator.generate(),
@@ -45,8 +546,10 @@ impl Environment {
                 let mut rng = thread_rng();
                 let generator = match kind {
                     DatumValueType::Bool => {
-                        let initial = false; // first value returned
-                        generator::bool_alternating(initial, unit)
+                        // doors and windows stay shut (and rooms stay empty) until a scenario
+                        // event or the admin API says otherwise
+                        let initial = false;
+                        generator::bool_steady(initial, unit)
                     }
                     DatumValueType::Int => {
                         let slope = rng.gen_range(-10..10); // arbitrarily selected range of slopes
@@ -69,18 +572,31 @@ impl Environment {
         }
     }
 
-    #[allow(dead_code)] // remove this ASAP
     pub fn handle_request(&mut self, request: &str) -> String {
+        self.apply_events(Utc::now());
+
+        // devices say which version of the protocol they speak; those we don't speak are refused
+        if let Err(msg) = protocol::version(request) {
+            return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg);
+        }
+
         if request.starts_with("POST /set/") {
             // if the Environment gets a command from an actuator with a Device::Id that it is not
-            // yet aware of, it should ignore it
+            // yet aware of, it should ignore it, unless the actuator says which unit it acts on
+
+            let (id, unit, command) = match self.extract_command(request) {
+                Ok(parsed) => parsed,
+                Err(msg) => return format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+            };
+
+            if let Some(unit) = unit {
+                self.model(&id, unit);
+            }
 
-            // Not complete, but this is the general idea
-            // Extract ID and command
-            let (id, command) = self.extract_command(request);
             match self.execute_command(&id, &command) {
-                Some(datum) => format!("HTTP/1.1 200 OK\r\n\r\n{:?}", datum),
-                None => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+                Ok(Some(datum)) => format!("HTTP/1.1 200 OK\r\n\r\n{}", datum),
+                Ok(None) => "HTTP/1.1 404 Not Found\r\n\r\n".to_string(),
+                Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
             }
         } else if request.starts_with("GET /get/") {
             // if the Environment gets a request from a sensor with a Device::Id that it is not
@@ -89,66 +605,227 @@ impl Environment {
             let parsed = Environment::parse_get_request(request);
 
             if let Ok((id, value_type, unit)) = parsed {
-                let datum = self.get(&id, value_type, unit);
-                format!("HTTP/1.1 200 OK\r\n\r\n{:?}", datum)
+                // Sensors may tell us their Name, so we can match them to attributes in a Scenario
+                if let Some(name) = Environment::parse_header(request, "Device-Name") {
+                    self.bind(&id, Name::new(name));
+                }
+
+                // any Latency is added by handle_client, so only this Sensor's response is delayed
+                let faults = self.faults(&id);
+
+                if fault::is_unresponsive(&faults) {
+                    return String::new();
+                } else if fault::is_unavailable(&faults) {
+                    return "HTTP/1.1 503 Service Unavailable\r\n\r\n".to_string();
+                }
+
+                let datum = fault::corrupt(self.get(&id, value_type, unit), &faults);
+                let body = datum.to_string();
+
+                if fault::is_garbled(&faults) {
+                    format!("HTTP/1.1 200 OK\r\n\r\n{}", fault::garble(&body))
+                } else {
+                    format!("HTTP/1.1 200 OK\r\n\r\n{}", body)
+                }
             } else {
                 let msg = parsed.unwrap_err();
                 format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg)
             }
+        } else if Environment::parse_path(request).starts_with("/attributes") {
+            self.handle_admin_request(request)
+        } else if request.starts_with("POST /faults/") {
+            let id = Id::new(Environment::parse_path(request).trim_start_matches("/faults/"));
+
+            match Fault::parse(Environment::parse_body(request)) {
+                Ok(fault) => {
+                    self.inject(id, fault);
+                    "HTTP/1.1 200 OK\r\n\r\n".to_string()
+                }
+                Err(msg) => format!("HTTP/1.1 400 Bad Request\r\n\r\n{}", msg),
+            }
+        } else if request.starts_with("GET /faults/") {
+            let id = Id::new(Environment::parse_path(request).trim_start_matches("/faults/"));
+
+            let faults: Vec<String> = self.faults(&id).iter().map(Fault::to_string).collect();
+            format!("HTTP/1.1 200 OK\r\n\r\n{}", faults.join("\n"))
+        } else if request.starts_with("DELETE /faults/") {
+            let id = Id::new(Environment::parse_path(request).trim_start_matches("/faults/"));
+
+            self.clear_faults(&id);
+            "HTTP/1.1 200 OK\r\n\r\n".to_string()
         } else {
             "HTTP/1.1 404 Not Found\r\n\r\n".to_string()
         }
     }
 
-    #[allow(dead_code)] // remove this ASAP
-    fn start_server(&mut self) -> std::io::Result<()> {
-        let listener = TcpListener::bind("127.0.0.1:8080")?;
+    pub fn start_server(&mut self, address: &str) -> std::io::Result<()> {
+        let listener = TcpListener::bind(address)?;
 
         for stream in listener.incoming() {
             match stream {
                 Ok(stream) => {
                     self.handle_client(stream)?;
                 }
-                Err(e) => eprintln!("Failed to handle client: {}", e),
+                Err(e) => warn!(error = %e, "failed to handle client"),
             }
         }
         Ok(())
     }
 
-    #[allow(dead_code)] // remove this ASAP
+    /// Reads a request from a client and responds to it. Clients are served one at a time, so a
+    /// client which stops sending its request is given up on after the `device::http::TIMEOUT`.
     fn handle_client(&mut self, mut stream: TcpStream) -> std::io::Result<()> {
-        let mut request = Vec::new();
-        stream.read_to_end(&mut request).unwrap();
+        let Some(request) = device::http::read_request(&mut stream, device::http::TIMEOUT)? else {
+            return Ok(());
+        };
 
-        let request = std::str::from_utf8(&request)
-            .map(|s| s.trim())
-            .unwrap_or("Failed to read response");
+        let latency = self.latency(&request);
+        let response = self.handle_request(request.trim());
 
-        let response = self.handle_request(request);
+        // an empty response means we should hang up without responding
+        if response.is_empty() {
+            return Ok(());
+        }
+
+        if latency.is_zero() {
+            return Self::respond(stream, &response);
+        }
+
+        // delay only this client's response, so other devices are still answered promptly
+        std::thread::spawn(move || {
+            std::thread::sleep(latency);
+
+            if let Err(e) = Self::respond(stream, &response) {
+                warn!(error = %e, "failed to respond to client")
+            }
+        });
 
-        stream.write_all(response.as_bytes())?;
-        stream.flush()?;
         Ok(())
     }
 
-    #[allow(dead_code)] // remove this ASAP
-    fn extract_command(&self, _request: &str) -> (Id, String) {
-        todo!()
+    fn respond(mut stream: TcpStream, response: &str) -> std::io::Result<()> {
+        stream.write_all(response.as_bytes())?;
+        stream.flush()
+    }
+
+    /// Returns how long the response to a request from a `Sensor` should be delayed by, according
+    /// to any `Latency` faults injected for it.
+    fn latency(&self, request: &str) -> std::time::Duration {
+        if !request.starts_with("GET /get/") {
+            return std::time::Duration::ZERO;
+        }
+
+        match Environment::parse_get_request(request) {
+            Ok((id, _, _)) => fault::latency(&self.faults(&id))
+                .to_std()
+                .unwrap_or_default(),
+            Err(_) => std::time::Duration::ZERO,
+        }
+    }
+
+    /// Returns the (percent-decoded) path from the first line of an HTTP request, e.g.
+    /// "/get/test_id/float/°C".
+    fn parse_path(request: &str) -> String {
+        let path = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or_default();
+
+        device::http::percent_decode(path)
     }
 
-    #[allow(dead_code)] // remove this ASAP
-    fn execute_command(&self, _id: &Id, _command: &str) -> Option<Datum> {
-        // TODO: Implement actual command execution logic
-        // Maybe the command should be an struct or enum with a type and a value?
-        todo!()
+    /// Returns the value of the first header with the specified name in an HTTP request, if any.
+    fn parse_header<'a>(request: &'a str, header: &str) -> Option<&'a str> {
+        request
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(header))
+            .map(|(_, value)| value.trim())
+    }
+
+    /// Returns the body of an HTTP request, or an empty string if there is no body.
+    fn parse_body(request: &str) -> &str {
+        match request.split_once("\r\n\r\n") {
+            Some((_, body)) => body.trim(),
+            None => "",
+        }
+    }
+
+    fn extract_command(&self, request: &str) -> Result<(Id, Option<DatumUnit>, String), String> {
+        // example request: "POST /set/test_id HTTP/1.1\r\nContent-Length: 10\r\n\r\nHeatTo:21"
+        // actuators may also name the unit they act on, e.g. "POST /set/test_id/°C HTTP/1.1"
+        let body = Environment::parse_body(request);
+
+        let path = Environment::parse_path(request);
+        let path = path.trim_start_matches("/set/");
+
+        let (id, unit) = match path.split_once('/') {
+            Some((id, unit)) => (id, Some(DatumUnit::parse(unit)?)),
+            None => (path, None),
+        };
+
+        Ok((Id::new(id), unit, body.trim().to_string()))
+    }
+
+    /// Applies the `command` to the zone with the specified `Id`, returning its latest `Datum`.
+    ///
+    /// Thermal zones take `actuator_temperature::Command`s, humidity zones take
+    /// `actuator_humidity::Command`s, light zones take `actuator_light::Command`s, and plugs take
+    /// `actuator_plug::Command`s. Returns `Ok(None)` if there is no zone with the specified `Id`.
+    fn execute_command(&self, id: &Id, command: &str) -> Result<Option<Datum>, String> {
+        let now = Utc::now();
+
+        if let Some(model) = self.zones.lock().unwrap().get(id) {
+            let command = Command::parse(command)?;
+            let mut model = model.lock().unwrap();
+            model.apply(command, now);
+            return Ok(Some(Datum::new(
+                model.temperature(),
+                DatumUnit::DegreesC,
+                now,
+            )));
+        }
+
+        if let Some(model) = self.humidity_zones.lock().unwrap().get(id) {
+            let command = actuator_humidity::Command::parse(command)?;
+            let mut model = model.lock().unwrap();
+            model.apply(command, now);
+            return Ok(Some(Datum::new(
+                model.humidity(),
+                DatumUnit::RelativeHumidity,
+                now,
+            )));
+        }
+
+        if let Some(model) = self.light_zones.lock().unwrap().get(id) {
+            let command = actuator_light::Command::parse(command)?;
+            let mut model = model.lock().unwrap();
+            model.apply(command);
+            return Ok(Some(Datum::new(
+                model.illuminance(now),
+                DatumUnit::Lux,
+                now,
+            )));
+        }
+
+        if let Some(model) = self.plugs.lock().unwrap().get(id) {
+            let command = actuator_plug::Command::parse(command)?;
+            let on = model.lock().unwrap().apply(command);
+            return Ok(Some(Datum::new(on, DatumUnit::PoweredOn, now)));
+        }
+
+        Ok(None)
     }
 
-    #[allow(dead_code)] // remove this ASAP
     fn parse_get_request(request: &str) -> Result<(Id, DatumValueType, DatumUnit), String> {
-        // example request: "GET /get/test_id/float/°C"
-        let mut parts = request.split('/');
+        // example request: "GET /get/test_id/float/°C HTTP/1.1"
+        let path = Environment::parse_path(request);
+        let mut parts = path.split('/');
 
-        parts.next(); // throw out "GET"
+        parts.next(); // throw out ""
         parts.next(); // throw out "get"
 
         match (parts.next(), parts.next(), parts.next()) {
@@ -201,9 +878,9 @@ mod env_tests {
         let mut environment = Environment::new();
 
         fn contains_datum(response: String) -> bool {
-            let datum_regex =
-                Regex::new(r"Datum \{ value: .*, unit: .*, timestamp: .* \}").unwrap();
-            datum_regex.is_match(&response)
+            let datum_regex = Regex::new(r"\r\n\r\n.*@.*@.*").unwrap();
+            let body = response.split_once("\r\n\r\n").unwrap().1;
+            datum_regex.is_match(&response) && Datum::parse(body).is_ok()
         }
 
         let get_request = "GET /get/test_id/float/°C";
@@ -214,6 +891,19 @@ mod env_tests {
         assert!(contains_datum(get_response));
     }
 
+    #[test]
+    fn test_handle_request_unsupported_protocol() {
+        let mut environment = Environment::new();
+
+        let request = "GET /get/test_id/float/°C HTTP/1.1\r\nProtocol-Version: 9\r\n\r\n";
+        let response = environment.handle_request(request);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+
+        let request = "GET /get/test_id/float/°C HTTP/1.1\r\nProtocol-Version: 2\r\n\r\n";
+        let response = environment.handle_request(request);
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+
     #[test]
     fn test_handle_get_request_undefined() {
         let mut environment = Environment::new();
@@ -222,6 +912,577 @@ mod env_tests {
         assert_eq!(undefined_response, "HTTP/1.1 404 Not Found\r\n\r\n");
     }
 
+    #[test]
+    fn test_handle_set_request() {
+        let mut environment = Environment::new();
+
+        // the Environment learns about the zone the first time it is probed by a Sensor
+        environment.handle_request("GET /get/test_id/float/°C");
+
+        let set_request = "POST /set/test_id HTTP/1.1\r\nContent-Length: 12\r\n\r\nHeatTo:21.5";
+        let set_response = environment.handle_request(set_request);
+
+        assert!(set_response.starts_with("HTTP/1.1 200 OK"));
+
+        let zones = environment.zones.lock().unwrap();
+        let model = zones.get(&Id::new("test_id")).unwrap().lock().unwrap();
+        assert_eq!(model.command(), Some(&Command::HeatTo(21.5)));
+    }
+
+    #[test]
+    fn test_handle_humidity_set_request() {
+        let mut environment = Environment::new();
+
+        // the Environment learns about the zone the first time it is probed by a Sensor
+        let response = environment.handle_request("GET /get/greenhouse/float/%RH");
+        assert!(response.contains("@%RH@"), "{}", response);
+
+        let set_request = "POST /set/greenhouse\r\n\r\nDehumidifyTo:40";
+        let set_response = environment.handle_request(set_request);
+        assert!(set_response.starts_with("HTTP/1.1 200 OK"));
+
+        // humidity zones don't take temperature commands
+        let set_request = "POST /set/greenhouse\r\n\r\nHeatTo:21.5";
+        let set_response = environment.handle_request(set_request);
+        assert!(set_response.starts_with("HTTP/1.1 400 Bad Request"));
+
+        let zones = environment.humidity_zones.lock().unwrap();
+        let model = zones.get(&Id::new("greenhouse")).unwrap().lock().unwrap();
+        assert_eq!(
+            model.command(),
+            Some(&actuator_humidity::Command::DehumidifyTo(40.0))
+        );
+    }
+
+    #[test]
+    fn test_handle_light_set_request() {
+        let mut environment = Environment::new();
+        environment.handle_request("GET /get/office/float/lx");
+
+        let set_response = environment.handle_request("POST /set/office\r\n\r\nDimTo:50");
+        assert!(set_response.starts_with("HTTP/1.1 200 OK"));
+
+        let zones = environment.light_zones.lock().unwrap();
+        let model = zones.get(&Id::new("office")).unwrap().lock().unwrap();
+        assert_eq!(model.command(), Some(&actuator_light::Command::DimTo(50.0)));
+    }
+
+    #[test]
+    fn test_handle_plug_set_request() {
+        let mut environment = Environment::new();
+
+        // a plug has no Sensor, so its Actuator tells the Environment which unit it acts on
+        let response = environment.handle_request("POST /set/kettle/%E2%8F%BC\r\n\r\nToggle");
+        assert!(response.contains("\r\n\r\ntrue@⏼@"), "{}", response);
+
+        let response = environment.handle_request("POST /set/kettle\r\n\r\nToggle");
+        assert!(response.contains("\r\n\r\nfalse@⏼@"), "{}", response);
+
+        let response = environment.handle_request("GET /get/kettle/bool/⏼");
+        assert!(response.contains("\r\n\r\nfalse@⏼@"), "{}", response);
+
+        let response = environment.handle_request("POST /set/kettle/furlongs\r\n\r\nOn");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_handle_set_request_unknown_id() {
+        let mut environment = Environment::new();
+        let set_request = "POST /set/unknown\r\n\r\nHeatTo:21.5";
+        let set_response = environment.handle_request(set_request);
+        assert_eq!(set_response, "HTTP/1.1 404 Not Found\r\n\r\n");
+    }
+
+    #[test]
+    fn test_handle_set_request_invalid_command() {
+        let mut environment = Environment::new();
+        environment.handle_request("GET /get/test_id/float/°C");
+
+        let set_request = "POST /set/test_id\r\n\r\nWarmTo:21.5";
+        let set_response = environment.handle_request(set_request);
+
+        assert!(set_response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_load_scenario() {
+        let mut environment = Environment::new();
+
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "outside"
+            generator = "constant"
+            unit = "°C"
+            value = -5.0
+
+            [[attributes]]
+            id = "kitchen"
+            generator = "thermal"
+            initial = 21.0
+            outside = "outside"
+            "#,
+        )
+        .unwrap();
+
+        environment.load(scenario);
+
+        let outside = environment.get(
+            &Id::new("outside"),
+            DatumValueType::Float,
+            DatumUnit::DegreesC,
+        );
+        assert_eq!(outside.get_as_float(), Some(-5.0));
+
+        // the kitchen is a thermal zone, so it responds to commands
+        assert!(environment
+            .zones
+            .lock()
+            .unwrap()
+            .contains_key(&Id::new("kitchen")));
+        assert!(!environment
+            .zones
+            .lock()
+            .unwrap()
+            .contains_key(&Id::new("outside")));
+    }
+
+    #[test]
+    /// Attributes keyed by Name are created when a Sensor with that Name first probes the Environment
+    fn test_load_scenario_by_name() {
+        let mut environment = Environment::new();
+
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            name = "Kitchen Thermostat"
+            generator = "constant"
+            value = 42
+            "#,
+        )
+        .unwrap();
+
+        environment.load(scenario);
+
+        let request = "GET /get/some_uuid/int/ HTTP/1.1\r\nDevice-Name: Kitchen Thermostat\r\n\r\n";
+        let response = environment.handle_request(request);
+
+        assert!(response.contains("\r\n\r\n42@"), "{}", response);
+    }
+
+    #[test]
+    fn test_apply_events() {
+        let environment = Environment::new();
+
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "outside"
+            generator = "constant"
+            unit = "°C"
+            value = 10.0
+
+            [[events]]
+            at = "10m"
+            id = "outside"
+            offset = -8.0
+
+            [[events]]
+            at = "20m"
+            id = "outside"
+            value = 30.0
+            "#,
+        )
+        .unwrap();
+
+        let start = Utc::now();
+        environment.load(scenario);
+
+        let outside = || {
+            let attributes = environment.attributes.lock().unwrap();
+            let generator = attributes.get(&Id::new("outside")).unwrap();
+            generator.generate().get_as_float().unwrap()
+        };
+
+        environment.apply_events(start + chrono::Duration::minutes(5));
+        assert_eq!(outside(), 10.0);
+
+        environment.apply_events(start + chrono::Duration::minutes(15));
+        assert_eq!(outside(), 2.0);
+
+        environment.apply_events(start + chrono::Duration::minutes(25));
+        assert_eq!(outside(), 30.0);
+        assert!(environment.events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    /// Opening and closing a window changes how quickly its zone loses heat
+    fn test_window_heat_loss() {
+        let mut environment = Environment::new();
+
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "window"
+            generator = "steady"
+
+            [[attributes]]
+            id = "kitchen"
+            generator = "thermal"
+            initial = 20.0
+            heat_loss = 100.0
+            window = "window"
+            open_heat_loss = 400.0
+
+            [[events]]
+            at = "10m"
+            id = "window"
+            value = true
+            "#,
+        )
+        .unwrap();
+
+        let start = Utc::now();
+        environment.load(scenario);
+
+        let heat_loss = |environment: &Environment| {
+            let zones = environment.zones.lock().unwrap();
+            let heat_loss = zones
+                .get(&Id::new("kitchen"))
+                .unwrap()
+                .lock()
+                .unwrap()
+                .heat_loss();
+            heat_loss
+        };
+
+        environment.apply_events(start + chrono::Duration::minutes(5));
+        assert_eq!(heat_loss(&environment), 100.0);
+
+        environment.apply_events(start + chrono::Duration::minutes(15));
+        assert_eq!(heat_loss(&environment), 400.0);
+
+        let window = environment.get(
+            &Id::new("window"),
+            DatumValueType::Bool,
+            DatumUnit::Unitless,
+        );
+        assert_eq!(window.get_as_bool(), Some(true));
+
+        // closing the window through the admin API restores the zone's usual heat loss
+        let request = "PUT /attributes/window/value HTTP/1.1\r\nContent-Length: 5\r\n\r\nfalse";
+        let response = environment.handle_request(request);
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(heat_loss(&environment), 100.0);
+    }
+
+    #[test]
+    fn test_handle_fault_requests() {
+        let mut environment = Environment::new();
+
+        let response = environment.handle_request("POST /faults/test_id\r\n\r\nStuckAt:99.5");
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let response = environment.handle_request("POST /faults/test_id\r\n\r\nClockSkew:1h");
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let response = environment.handle_request("GET /faults/test_id");
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 OK\r\n\r\nStuckAt:99.5\nClockSkew:3600000ms"
+        );
+
+        let response = environment.handle_request("GET /get/test_id/float/°C");
+        assert!(response.contains("\r\n\r\n99.5@"), "{}", response);
+
+        let response = environment.handle_request("DELETE /faults/test_id");
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let response = environment.handle_request("GET /get/test_id/float/°C");
+        assert!(!response.contains("\r\n\r\n99.5@"), "{}", response);
+    }
+
+    #[test]
+    /// A Latency fault only delays the responses to the Sensor it was injected for
+    fn test_latency_is_per_device() {
+        use std::net::TcpListener;
+        use std::time::{Duration, Instant};
+
+        // find a free port for the Environment to listen on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let mut environment = Environment::new();
+        environment.inject(
+            Id::new("slow"),
+            Fault::Latency(chrono::Duration::seconds(2)),
+        );
+
+        let server = address.clone();
+        std::thread::spawn(move || environment.start_server(&server));
+
+        fn get(address: &str, id: &str) -> (Result<String, String>, Duration) {
+            let request = format!("GET /get/{}/float/°C HTTP/1.1\r\n\r\n", id);
+            let started = Instant::now();
+
+            // retry until the server has started
+            loop {
+                match device::http::send(address, &request, Duration::from_secs(5)) {
+                    Err(msg) if msg.contains("refused") && started.elapsed().as_secs() < 1 => {
+                        std::thread::sleep(Duration::from_millis(10))
+                    }
+                    result => return (result, started.elapsed()),
+                }
+            }
+        }
+
+        let slow = std::thread::spawn({
+            let address = address.clone();
+            move || get(&address, "slow")
+        });
+        std::thread::sleep(Duration::from_millis(200));
+
+        // the fast Sensor is answered while the slow one is still waiting for its response
+        let (fast, elapsed) = get(&address, "fast");
+        assert!(fast.is_ok(), "{:?}", fast);
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+
+        let (slow, elapsed) = slow.join().unwrap();
+        assert!(slow.is_ok(), "{:?}", slow);
+        assert!(elapsed >= Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn test_payload_too_large() {
+        use std::time::Duration;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            Environment::new().handle_client(stream).unwrap();
+        });
+
+        let request = "PUT /attributes/test_id HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n{}";
+        let response = device::http::send(&address, request, Duration::from_secs(1));
+
+        assert_eq!(
+            response,
+            Err(String::from(
+                "HTTP/1.1 413 Payload Too Large: request bodies are limited to 1048576 bytes"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_handle_invalid_fault_request() {
+        let mut environment = Environment::new();
+        let response = environment.handle_request("POST /faults/test_id\r\n\r\nGremlins");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_dropouts() {
+        let mut environment = Environment::new();
+        let id = Id::new("test_id");
+
+        environment.inject(id.clone(), Fault::Unavailable(1.0));
+        let response = environment.handle_request("GET /get/test_id/float/°C");
+        assert_eq!(response, "HTTP/1.1 503 Service Unavailable\r\n\r\n");
+
+        environment.inject(id, Fault::NoResponse(1.0));
+        let response = environment.handle_request("GET /get/test_id/float/°C");
+        assert_eq!(response, "");
+    }
+
+    #[test]
+    fn test_faults_from_scenario() {
+        let mut environment = Environment::new();
+
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "thermal"
+            initial = 21.0
+            faults = ["NaN"]
+            "#,
+        )
+        .unwrap();
+
+        environment.load(scenario);
+
+        let response = environment.handle_request("GET /get/kitchen/float/°C");
+        assert!(response.contains("\r\n\r\nNaN@"), "{}", response);
+    }
+
+    #[test]
+    fn test_replace_and_remove_clear_faults() {
+        let environment = Environment::new();
+        let id = Id::new("kitchen");
+
+        let attribute = || {
+            let scenario = Scenario::parse_toml(
+                r#"
+                [[attributes]]
+                id = "kitchen"
+                generator = "thermal"
+                initial = 21.0
+                faults = ["NaN"]
+                "#,
+            );
+            scenario.unwrap().attributes.remove(0)
+        };
+
+        environment.replace(id.clone(), attribute());
+        environment.replace(id.clone(), attribute());
+        assert_eq!(environment.faults(&id).len(), 1);
+
+        assert!(environment.remove(&id));
+        assert!(environment.faults(&id).is_empty());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let request = "GET /get/Kitchen%20Thermostat/float/%C2%B0C HTTP/1.1\r\n\r\n";
+        assert_eq!(
+            Environment::parse_path(request),
+            "/get/Kitchen Thermostat/float/°C"
+        );
+
+        // malformed escapes are left as they are
+        assert_eq!(Environment::parse_path("GET /100%/%zz"), "/100%/%zz");
+    }
+
+    #[test]
+    fn test_admin_list_attributes() {
+        let mut environment = Environment::new();
+
+        let response = environment.handle_request("GET /attributes HTTP/1.1");
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n[]"
+        );
+
+        environment.handle_request("GET /get/b/bool/ HTTP/1.1\r\nDevice-Name: Back Door");
+        environment.handle_request("GET /get/a/float/°C HTTP/1.1");
+
+        let response = environment.handle_request("GET /attributes HTTP/1.1");
+        let json = response.split("\r\n\r\n").nth(1).unwrap();
+        let json: serde_json::Value = serde_json::from_str(json).unwrap();
+
+        assert_eq!(json[0]["id"], "a");
+        assert_eq!(json[0]["name"], serde_json::Value::Null);
+        assert_eq!(json[0]["generator"], "thermal");
+        assert_eq!(json[0]["unit"], "°C");
+        assert!(json[0]["latest"].as_str().unwrap().contains("@°C@"));
+
+        assert_eq!(json[1]["id"], "b");
+        assert_eq!(json[1]["name"], "Back Door");
+        assert_eq!(json[1]["generator"], "steady");
+        assert!(json[1]["latest"].as_str().unwrap().starts_with("false@"));
+    }
+
+    #[test]
+    fn test_admin_get_attribute() {
+        let mut environment = Environment::new();
+
+        let response = environment.handle_request("GET /attributes/kitchen HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+
+        environment.handle_request("GET /get/kitchen/int/ HTTP/1.1");
+        let response = environment.handle_request("GET /attributes/kitchen HTTP/1.1");
+        assert!(
+            response.contains(r#""generator":"i32_linear""#),
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    /// "make the kitchen 35 °C now"
+    fn test_admin_pin_value_by_name() {
+        let mut environment = Environment::new();
+
+        environment.handle_request("GET /get/uuid/float/°C HTTP/1.1\r\nDevice-Name: Kitchen");
+
+        let request = "PUT /attributes/Kitchen/value HTTP/1.1\r\nContent-Length: 4\r\n\r\n35.0";
+        let response = environment.handle_request(request);
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let datum = environment.get(&Id::new("uuid"), DatumValueType::Float, DatumUnit::DegreesC);
+        assert_eq!(datum.get_as_float(), Some(35.0));
+        assert_eq!(datum.unit, DatumUnit::DegreesC);
+
+        // once pinned, the kitchen is no longer a thermal zone, so it ignores commands
+        assert!(!environment
+            .zones
+            .lock()
+            .unwrap()
+            .contains_key(&Id::new("uuid")));
+    }
+
+    #[test]
+    fn test_admin_replace_generator() {
+        let mut environment = Environment::new();
+
+        let request =
+            "PUT /attributes/kitchen HTTP/1.1\r\n\r\n{\"generator\": \"thermal\", \"initial\": 18}";
+        let response = environment.handle_request(request);
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+        assert!(environment
+            .zones
+            .lock()
+            .unwrap()
+            .contains_key(&Id::new("kitchen")));
+
+        let request =
+            "PUT /attributes/kitchen HTTP/1.1\r\n\r\n{\"generator\": \"constant\", \"value\": 3}";
+        let response = environment.handle_request(request);
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+        assert!(!environment
+            .zones
+            .lock()
+            .unwrap()
+            .contains_key(&Id::new("kitchen")));
+
+        let datum = environment.get(
+            &Id::new("kitchen"),
+            DatumValueType::Int,
+            DatumUnit::Unitless,
+        );
+        assert_eq!(datum.get_as_int(), Some(3));
+    }
+
+    #[test]
+    fn test_admin_replace_generator_invalid() {
+        let mut environment = Environment::new();
+
+        let request = "PUT /attributes/kitchen HTTP/1.1\r\n\r\n{\"generator\": \"constant\"}";
+        let response = environment.handle_request(request);
+
+        assert_eq!(
+            response,
+            "HTTP/1.1 400 Bad Request\r\n\r\n'value' is required by generator 'constant'"
+        );
+    }
+
+    #[test]
+    fn test_admin_delete_attribute() {
+        let mut environment = Environment::new();
+        environment.handle_request("GET /get/kitchen/float/°C HTTP/1.1");
+
+        let response = environment.handle_request("DELETE /attributes/kitchen HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 200 OK\r\n\r\n");
+
+        let response = environment.handle_request("DELETE /attributes/kitchen HTTP/1.1");
+        assert_eq!(response, "HTTP/1.1 404 Not Found\r\n\r\n");
+    }
+
     #[test]
     fn test_get_with_existing_generator() {
         let mut env = Environment::default();
@@ -252,6 +1513,15 @@ mod env_tests {
             _ => panic!("Expected Bool, found {:?}", datum.value),
         }
         assert_eq!(datum.unit, unit);
+
+        // contact and motion sensors read the same value until something changes it
+        let again = env.get(&id, DatumValueType::Bool, unit);
+        assert_eq!(again.value, datum.value);
+
+        env.pin(&id, DatumValue::Bool(true));
+        let datum = env.get(&id, DatumValueType::Bool, unit);
+        assert_eq!(datum.get_as_bool(), Some(true));
+        assert_eq!(env.describe()[0]["generator"], "steady");
     }
 
     #[test]
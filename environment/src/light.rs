@@ -0,0 +1,142 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Timelike, Utc};
+
+use actuator_light::Command;
+use datum::{DatumUnit, DatumValue};
+
+use crate::generator::DatumGenerator;
+
+/// The physical parameters of a single zone (room, office) in a `LightModel`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct LightParameters {
+    /// How much daylight reaches the zone at noon, in lux.
+    pub daylight: f32,
+    /// How much light the zone's lamps add at full brightness, in lux.
+    pub lamp: f32,
+}
+
+impl Default for LightParameters {
+    /// An office with a window, and lamps which light it to a typical 500 lux.
+    fn default() -> Self {
+        Self {
+            daylight: 300.0,
+            lamp: 500.0,
+        }
+    }
+}
+
+/// A model of the illuminance in a single zone.
+///
+/// Daylight follows a daily cycle: there is none between sunset (18:00 UTC) and sunrise (06:00
+/// UTC), and it peaks at noon. Artificial light is added in proportion to the brightness of the
+/// zone's lamps, which is set by the latest `Command` sent by an `Actuator`.
+///
+/// Unlike a `ThermalModel`, the illuminance changes instantly, so nothing is integrated over time.
+pub struct LightModel {
+    parameters: LightParameters,
+    command: Option<Command>,
+}
+
+impl LightModel {
+    const SUNRISE_HOUR: f32 = 6.0;
+    const SUNSET_HOUR: f32 = 18.0;
+
+    pub fn new(parameters: LightParameters) -> LightModel {
+        LightModel {
+            parameters,
+            command: None,
+        }
+    }
+
+    /// Returns the latest `Command` received by this zone, if any.
+    pub fn command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
+
+    /// Applies the `command` from now on.
+    pub fn apply(&mut self, command: Command) {
+        self.command = Some(command);
+    }
+
+    /// Returns how much daylight reaches the zone at `now`, in lux.
+    pub fn daylight(&self, now: DateTime<Utc>) -> f32 {
+        let hour = now.num_seconds_from_midnight() as f32 / 3600.0;
+        let day = (hour - Self::SUNRISE_HOUR) / (Self::SUNSET_HOUR - Self::SUNRISE_HOUR);
+
+        if (0.0..=1.0).contains(&day) {
+            self.parameters.daylight * (std::f32::consts::PI * day).sin()
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the illuminance of the zone at `now`, in lux.
+    pub fn illuminance(&self, now: DateTime<Utc>) -> f32 {
+        let brightness = self.command.as_ref().map_or(0.0, Command::brightness);
+        self.daylight(now) + self.parameters.lamp * brightness / 100.0
+    }
+}
+
+/// Creates a `DatumGenerator` which reads the illuminance of a zone from its `model`.
+pub fn generator(model: Arc<Mutex<LightModel>>) -> DatumGenerator {
+    let f = move |now: DateTime<Utc>| -> DatumValue {
+        let model = model.lock().unwrap();
+        DatumValue::Float(model.illuminance(now))
+    };
+
+    DatumGenerator::new(Box::new(f), DatumUnit::Lux).with_kind("light")
+}
+
+#[cfg(test)]
+mod light_tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    /// Daylight peaks at noon, and there is none at night
+    fn test_daylight_cycle() {
+        let model = LightModel::new(LightParameters::default());
+
+        assert_eq!(model.daylight(at(0)), 0.0);
+        assert_eq!(model.daylight(at(20)), 0.0);
+        assert!((model.daylight(at(12)) - 300.0).abs() < 0.01);
+        assert!(model.daylight(at(9)) < model.daylight(at(12)));
+        assert!((model.daylight(at(9)) - model.daylight(at(15))).abs() < 0.01);
+    }
+
+    #[test]
+    /// The lamps add light in proportion to their brightness
+    fn test_lamps() {
+        let mut model = LightModel::new(LightParameters::default());
+        let midnight = at(0);
+
+        assert_eq!(model.illuminance(midnight), 0.0);
+
+        model.apply(Command::On);
+        assert_eq!(model.illuminance(midnight), 500.0);
+
+        model.apply(Command::DimTo(40.0));
+        assert_eq!(model.illuminance(midnight), 200.0);
+
+        model.apply(Command::Off);
+        assert_eq!(model.illuminance(midnight), 0.0);
+    }
+
+    #[test]
+    fn test_generator() {
+        let mut model = LightModel::new(LightParameters::default());
+        model.apply(Command::On);
+
+        let generator = generator(Arc::new(Mutex::new(model)));
+        let datum = generator.generate_at(at(12));
+
+        assert!((datum.get_as_float().unwrap() - 800.0).abs() < 0.01);
+        assert_eq!(datum.unit, DatumUnit::Lux);
+    }
+}
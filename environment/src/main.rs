@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use device::config::LoggingArgs;
+use environment::scenario::Scenario;
+use environment::Environment;
+
+/// Simulates the physical environment which sensors read from and actuators act on.
+#[derive(Parser)]
+struct Cli {
+    /// A TOML configuration file; flags and environment variables take precedence over it
+    #[arg(long, env = "ENVIRONMENT_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    args: EnvironmentArgs,
+
+    #[command(flatten)]
+    logging: LoggingArgs,
+}
+
+#[derive(clap::Args, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentArgs {
+    /// The address the HTTP server listens on [default: 127.0.0.1:8080]
+    #[arg(long, env = "ENVIRONMENT_ADDRESS")]
+    address: Option<String>,
+
+    /// A `Scenario` file to play
+    #[arg(long, env = "ENVIRONMENT_SCENARIO")]
+    scenario: Option<PathBuf>,
+}
+
+/// The layout of the configuration file.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    environment: EnvironmentArgs,
+    #[serde(default)]
+    logging: LoggingArgs,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let file: File = device::config::read_file(cli.config.as_deref()).unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    });
+
+    if let Err(msg) = cli.logging.or(file.logging).init() {
+        eprintln!("{}", msg);
+        std::process::exit(1)
+    }
+
+    let address = cli.args.address.or(file.environment.address);
+    let address = address.as_deref().unwrap_or(environment::ADDRESS);
+
+    let mut environment = Environment::new();
+
+    if let Some(path) = cli.args.scenario.or(file.environment.scenario) {
+        match Scenario::from_file(&path) {
+            Ok(scenario) => {
+                info!(path = %path.display(), "loaded scenario");
+                environment.load(scenario)
+            }
+            Err(msg) => {
+                error!("{}", msg);
+                std::process::exit(1)
+            }
+        }
+    }
+
+    info!(address, "serving environment");
+
+    if let Err(err) = environment.start_server(address) {
+        error!(address, error = %err, "cannot serve environment");
+        std::process::exit(1)
+    }
+}
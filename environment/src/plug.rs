@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+
+use actuator_plug::Command;
+use datum::{DatumUnit, DatumValue};
+
+use crate::generator::DatumGenerator;
+
+/// A model of a smart plug, which is either powered on or off.
+///
+/// The plug is switched by the latest `Command` sent by an `Actuator`, and its power state is
+/// reported as a `bool` with the `DatumUnit::PoweredOn` unit.
+pub struct PlugModel {
+    on: bool,
+}
+
+impl PlugModel {
+    pub fn new(on: bool) -> PlugModel {
+        PlugModel { on }
+    }
+
+    /// Returns `true` if the plug is powered on.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Switches the plug as the `command` specifies, returning its new power state.
+    pub fn apply(&mut self, command: Command) -> bool {
+        self.on = match command {
+            Command::On => true,
+            Command::Off => false,
+            Command::Toggle => !self.on,
+        };
+
+        self.on
+    }
+}
+
+/// Creates a `DatumGenerator` which reads the power state of a plug from its `model`.
+pub fn generator(model: Arc<Mutex<PlugModel>>) -> DatumGenerator {
+    let f = move |_: DateTime<Utc>| -> DatumValue {
+        let model = model.lock().unwrap();
+        DatumValue::Bool(model.is_on())
+    };
+
+    DatumGenerator::new(Box::new(f), DatumUnit::PoweredOn).with_kind("plug")
+}
+
+#[cfg(test)]
+mod plug_tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut model = PlugModel::new(false);
+
+        assert!(model.apply(Command::On));
+        assert!(model.apply(Command::On));
+        assert!(!model.apply(Command::Toggle));
+        assert!(model.apply(Command::Toggle));
+        assert!(!model.apply(Command::Off));
+    }
+
+    #[test]
+    fn test_generator() {
+        let model = Arc::new(Mutex::new(PlugModel::new(true)));
+        let generator = generator(Arc::clone(&model));
+
+        assert_eq!(generator.generate().get_as_bool(), Some(true));
+
+        model.lock().unwrap().apply(Command::Toggle);
+        let datum = generator.generate();
+
+        assert_eq!(datum.get_as_bool(), Some(false));
+        assert_eq!(datum.unit, DatumUnit::PoweredOn);
+    }
+}
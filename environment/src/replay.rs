@@ -0,0 +1,350 @@
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+
+use datum::{DatumUnit, DatumValue};
+
+use crate::generator::DatumGenerator;
+
+/// A `Recording` is a series of real `Sensor` readings, in chronological order.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Recording {
+    samples: Vec<(DateTime<Utc>, DatumValue)>,
+    unit: DatumUnit,
+}
+
+/// Controls how a `Recording` is replayed.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ReplayOptions {
+    /// Start over from the beginning once the end of the `Recording` is reached, rather than
+    /// repeating the last value forever.
+    pub looping: bool,
+    /// Start replaying this far into the `Recording` (or, if negative, hold the first value for
+    /// this long before starting).
+    pub shift: Duration,
+    /// Linearly interpolate numeric values between samples, rather than holding each value until
+    /// the next sample.
+    pub interpolate: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            looping: false,
+            shift: Duration::zero(),
+            interpolate: false,
+        }
+    }
+}
+
+impl Recording {
+    pub fn unit(&self) -> DatumUnit {
+        self.unit
+    }
+
+    /// Reads a `Recording` from a CSV file; see `Recording::parse_csv`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Recording, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read recording {}: {}", path.display(), err))?;
+
+        Recording::parse_csv(&contents)
+            .map_err(|msg| format!("invalid recording {}: {}", path.display(), msg))
+    }
+
+    /// Parses a `Recording` from CSV rows of `timestamp,value,unit`, for example
+    ///
+    /// ```csv
+    /// timestamp,value,unit
+    /// 2023-11-20T10:00:00Z,21.5,°C
+    /// 2023-11-20T10:05:00Z,21.25,°C
+    /// ```
+    ///
+    /// The header row is optional. Timestamps are RFC 3339, and must be in chronological order.
+    /// Every row must have the same unit and the same type of value.
+    pub fn parse_csv(string: &str) -> Result<Recording, String> {
+        let mut samples: Vec<(DateTime<Utc>, DatumValue)> = Vec::new();
+        let mut unit = None;
+
+        for (index, line) in string.lines().enumerate() {
+            let line = line.trim();
+            let number = index + 1;
+
+            if line.is_empty() || (index == 0 && line.starts_with("timestamp")) {
+                continue;
+            }
+
+            let mut pieces = line.split(',').map(|piece| piece.trim());
+
+            let (timestamp, value, this_unit) =
+                match (pieces.next(), pieces.next(), pieces.next(), pieces.next()) {
+                    (Some(timestamp), Some(value), Some(unit), None) => (timestamp, value, unit),
+                    (Some(timestamp), Some(value), None, None) => (timestamp, value, ""),
+                    _ => {
+                        return Err(format!(
+                            "line {}: expected 'timestamp,value,unit' but found '{}'",
+                            number, line
+                        ))
+                    }
+                };
+
+            let timestamp = timestamp
+                .parse::<DateTime<Utc>>()
+                .map_err(|err| format!("line {}: {} ('{}')", number, err, timestamp))?;
+
+            let value = DatumValue::parse(value.to_string())
+                .map_err(|msg| format!("line {}: {}", number, msg))?;
+
+            let this_unit =
+                DatumUnit::parse(this_unit).map_err(|msg| format!("line {}: {}", number, msg))?;
+
+            match unit {
+                None => unit = Some(this_unit),
+                Some(unit) if unit != this_unit => {
+                    return Err(format!(
+                        "line {}: unit '{}' differs from earlier unit '{}'",
+                        number, this_unit, unit
+                    ))
+                }
+                Some(_) => (),
+            }
+
+            if let Some((previous, earlier)) = samples.last() {
+                if timestamp < *previous {
+                    return Err(format!(
+                        "line {}: timestamp is earlier than the line before",
+                        number
+                    ));
+                }
+
+                if std::mem::discriminant(earlier) != std::mem::discriminant(&value) {
+                    return Err(format!(
+                        "line {}: value '{}' is a different type than earlier value '{}'",
+                        number, value, earlier
+                    ));
+                }
+            }
+
+            samples.push((timestamp, value));
+        }
+
+        match unit {
+            Some(unit) => Ok(Recording { samples, unit }),
+            None => Err(String::from("recording contains no samples")),
+        }
+    }
+
+    fn start(&self) -> DateTime<Utc> {
+        self.samples.first().unwrap().0
+    }
+
+    fn length(&self) -> Duration {
+        self.samples.last().unwrap().0 - self.start()
+    }
+
+    /// Returns the value at `elapsed` time since the start of the `Recording`.
+    fn value_at(&self, elapsed: Duration, options: &ReplayOptions) -> DatumValue {
+        let length = self.length();
+
+        // before the start of the recording, we hold the first value, even when looping
+        let elapsed = if options.looping && length > Duration::zero() && elapsed > length {
+            let millis = elapsed
+                .num_milliseconds()
+                .rem_euclid(length.num_milliseconds());
+            Duration::milliseconds(millis)
+        } else {
+            elapsed
+        };
+
+        let position = self.start() + elapsed;
+
+        // index of the first sample after `position`
+        let next = self.samples.partition_point(|(time, _)| *time <= position);
+
+        if next == 0 {
+            return self.samples[0].1;
+        } else if next == self.samples.len() {
+            return self.samples[next - 1].1;
+        }
+
+        let (before_time, before) = self.samples[next - 1];
+        let (after_time, after) = self.samples[next];
+
+        if !options.interpolate {
+            return before;
+        }
+
+        // converting i64 to f32 is safe as long as samples are < 9.4e28 hours apart
+        let fraction = (position - before_time).num_milliseconds() as f32
+            / (after_time - before_time).num_milliseconds() as f32;
+
+        match (before, after) {
+            (DatumValue::Float(before), DatumValue::Float(after)) => {
+                DatumValue::Float(before + (after - before) * fraction)
+            }
+            (DatumValue::Int(before), DatumValue::Int(after)) => {
+                let value = before as f32 + (after - before) as f32 * fraction;
+                DatumValue::Int(value.round() as i32)
+            }
+            // bools can't be interpolated
+            (before, _) => before,
+        }
+    }
+}
+
+/// Replays the `recording` in real time, starting now.
+pub fn generator(recording: Recording, options: ReplayOptions) -> DatumGenerator {
+    let start = Utc::now();
+    let unit = recording.unit;
+
+    let f = move |now: DateTime<Utc>| -> DatumValue {
+        let elapsed = now - start + options.shift;
+        recording.value_at(elapsed, &options)
+    };
+
+    DatumGenerator::new(Box::new(f), unit).with_kind("replay")
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+
+    const CSV: &str = "timestamp,value,unit
+        2023-11-20T10:00:00Z,20.0,°C
+        2023-11-20T10:10:00Z,22.0,°C
+        2023-11-20T10:20:00Z,21.0,°C";
+
+    fn at(recording: &Recording, minutes: i64, options: ReplayOptions) -> DatumValue {
+        recording.value_at(Duration::minutes(minutes), &options)
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+
+        assert_eq!(recording.unit(), DatumUnit::DegreesC);
+        assert_eq!(recording.samples.len(), 3);
+        assert_eq!(recording.length(), Duration::minutes(20));
+    }
+
+    #[test]
+    fn test_parse_csv_without_header_or_unit() {
+        let csv = "2023-11-20T10:00:00Z,1\n2023-11-20T10:00:01Z,2\n";
+        let recording = Recording::parse_csv(csv).unwrap();
+
+        assert_eq!(recording.unit(), DatumUnit::Unitless);
+        assert_eq!(recording.samples[1].1, DatumValue::Int(2));
+    }
+
+    #[test]
+    fn test_parse_csv_errors() {
+        let errors = vec![
+            ("", "recording contains no samples"),
+            (
+                "2023-11-20T10:00:00Z",
+                "line 1: expected 'timestamp,value,unit' but found '2023-11-20T10:00:00Z'",
+            ),
+            (
+                "yesterday,1,°C",
+                "line 1: input contains invalid characters ('yesterday')",
+            ),
+            (
+                "2023-11-20T10:00:00Z,warm,°C",
+                "line 1: cannot parse 'warm' as a DatumValue",
+            ),
+            (
+                "2023-11-20T10:00:00Z,1.0,°C\n2023-11-20T10:00:01Z,2.0,",
+                "line 2: unit '' differs from earlier unit '°C'",
+            ),
+            (
+                "2023-11-20T10:00:01Z,1.0,°C\n2023-11-20T10:00:00Z,2.0,°C",
+                "line 2: timestamp is earlier than the line before",
+            ),
+            (
+                "2023-11-20T10:00:00Z,1.0,°C\n2023-11-20T10:00:01Z,2,°C",
+                "line 2: value '2' is a different type than earlier value '1.0'",
+            ),
+        ];
+
+        for (csv, msg) in errors {
+            assert_eq!(Recording::parse_csv(csv), Err(String::from(msg)));
+        }
+    }
+
+    #[test]
+    fn test_replay_holds_values_between_samples() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+        let options = ReplayOptions::default();
+
+        assert_eq!(at(&recording, 0, options), DatumValue::Float(20.0));
+        assert_eq!(at(&recording, 5, options), DatumValue::Float(20.0));
+        assert_eq!(at(&recording, 10, options), DatumValue::Float(22.0));
+        assert_eq!(at(&recording, 15, options), DatumValue::Float(22.0));
+    }
+
+    #[test]
+    fn test_replay_interpolates() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+        let options = ReplayOptions {
+            interpolate: true,
+            ..ReplayOptions::default()
+        };
+
+        assert_eq!(at(&recording, 5, options), DatumValue::Float(21.0));
+        assert_eq!(at(&recording, 15, options), DatumValue::Float(21.5));
+    }
+
+    #[test]
+    fn test_replay_holds_last_value_without_looping() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+        let options = ReplayOptions::default();
+
+        assert_eq!(at(&recording, 25, options), DatumValue::Float(21.0));
+        assert_eq!(at(&recording, 1000, options), DatumValue::Float(21.0));
+    }
+
+    #[test]
+    fn test_replay_loops() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+        let options = ReplayOptions {
+            looping: true,
+            ..ReplayOptions::default()
+        };
+
+        assert_eq!(at(&recording, 25, options), DatumValue::Float(20.0));
+        assert_eq!(at(&recording, 35, options), DatumValue::Float(22.0));
+        assert_eq!(at(&recording, -5, options), DatumValue::Float(20.0));
+    }
+
+    #[test]
+    fn test_replay_shifted() {
+        let recording = Recording::parse_csv(CSV).unwrap();
+
+        let options = ReplayOptions {
+            shift: Duration::minutes(10),
+            ..ReplayOptions::default()
+        };
+
+        let generator = generator(recording, options);
+        let datum = generator.generate();
+
+        assert_eq!(datum.get_as_float(), Some(22.0));
+        assert_eq!(datum.unit, DatumUnit::DegreesC);
+    }
+
+    #[test]
+    fn test_replay_interpolates_ints_but_not_bools() {
+        let ints = Recording::parse_csv("2023-11-20T10:00:00Z,0\n2023-11-20T10:10:00Z,10").unwrap();
+        let bools =
+            Recording::parse_csv("2023-11-20T10:00:00Z,false\n2023-11-20T10:10:00Z,true").unwrap();
+
+        let options = ReplayOptions {
+            interpolate: true,
+            ..ReplayOptions::default()
+        };
+
+        assert_eq!(at(&ints, 3, options), DatumValue::Int(3));
+        assert_eq!(at(&bools, 9, options), DatumValue::Bool(false));
+    }
+}
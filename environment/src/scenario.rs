@@ -0,0 +1,1111 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use chrono::Duration;
+use serde::Deserialize;
+
+use datum::{DatumUnit, DatumValue};
+use device::{Id, Name};
+
+use crate::fault::Fault;
+use crate::humidity::HumidityParameters;
+use crate::light::LightParameters;
+use crate::replay::{Recording, ReplayOptions};
+use crate::thermal::ZoneParameters;
+
+/// A `Scenario` declaratively describes the attributes of an `Environment`, and how they change
+/// over time.
+///
+/// Scenarios are written in TOML, YAML, or JSON, for example
+///
+/// ```toml
+/// [[attributes]]
+/// id = "outside"
+/// generator = "sinusoidal"
+/// unit = "°C"
+/// mean = 10.0
+/// amplitude = 5.0
+/// period = "1d"
+///
+/// [[attributes]]
+/// id = "kitchen_window"
+/// generator = "steady"
+/// initial = false
+///
+/// # the kitchen loses heat more quickly while its window is open
+/// [[attributes]]
+/// name = "Kitchen Thermostat"
+/// generator = "thermal"
+/// initial = 20.0
+/// outside = "outside"
+/// window = "kitchen_window"
+/// open_heat_loss = 400.0
+///
+/// # the kitchen window opens after 10 minutes, and it gets colder outside
+/// [[events]]
+/// at = "10m"
+/// id = "kitchen_window"
+/// value = true
+///
+/// [[events]]
+/// at = "10m"
+/// id = "outside"
+/// offset = -8.0
+/// ```
+///
+/// Each attribute is keyed by either the `Id` or the `Name` of the `Sensor` which probes it.
+#[derive(PartialEq, Debug, Default)]
+pub struct Scenario {
+    pub attributes: Vec<Attribute>,
+    pub events: Vec<Event>,
+}
+
+/// Identifies the `Sensor` (and `Actuator`) associated with an attribute.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Key {
+    Id(Id),
+    Name(Name),
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Id(id) => write!(f, "id '{}'", id),
+            Key::Name(name) => write!(f, "name '{}'", name),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Attribute {
+    pub key: Key,
+    pub unit: DatumUnit,
+    pub generator: Generator,
+    /// `Fault`s to inject into every response for this attribute.
+    pub faults: Vec<Fault>,
+}
+
+/// Describes how to build a `DatumGenerator`; see the functions in the `generator` module.
+#[derive(PartialEq, Debug)]
+pub enum Generator {
+    Constant(DatumValue),
+    F32Linear {
+        initial: f32,
+        slope: f32,
+        noise: f32,
+    },
+    I32Linear {
+        initial: i32,
+        slope: i32,
+        noise: i32,
+    },
+    Sinusoidal {
+        mean: f32,
+        amplitude: f32,
+        period: Duration,
+    },
+    Alternating(bool),
+    Steady(bool),
+    Thermal {
+        initial: f32,
+        parameters: ZoneParameters,
+        /// The `Id` of the attribute which holds the outside temperature, if any.
+        outside: Option<Id>,
+        /// The `Id` of a window's contact sensor attribute, and the zone's heat loss while that
+        /// window is open, if any.
+        window: Option<(Id, f32)>,
+    },
+    Humidity {
+        initial: f32,
+        parameters: HumidityParameters,
+    },
+    Light(LightParameters),
+    Plug(bool),
+    Replay {
+        recording: Recording,
+        options: ReplayOptions,
+    },
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Event {
+    /// How long after the `Scenario` is loaded this event occurs.
+    pub at: Duration,
+    pub key: Key,
+    pub action: Action,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Action {
+    /// Adds a constant to all values generated from now on.
+    Offset(f32),
+    /// Replaces the attribute's generator with a constant value.
+    Value(DatumValue),
+    /// Changes how quickly a thermal zone loses heat to the outside.
+    HeatLoss(f32),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RawScenario {
+    #[serde(default)]
+    attributes: Vec<RawAttribute>,
+    #[serde(default)]
+    events: Vec<RawEvent>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAttribute {
+    id: Option<String>,
+    name: Option<String>,
+    generator: String,
+    unit: Option<String>,
+    initial: Option<RawValue>,
+    value: Option<RawValue>,
+    slope: Option<f32>,
+    noise: Option<f32>,
+    mean: Option<f32>,
+    amplitude: Option<f32>,
+    period: Option<String>,
+    outside: Option<String>,
+    window: Option<String>,
+    open_heat_loss: Option<f32>,
+    thermal_mass: Option<f32>,
+    heat_loss: Option<f32>,
+    heater_power: Option<f32>,
+    cooler_power: Option<f32>,
+    ambient: Option<f32>,
+    exchange_rate: Option<f32>,
+    humidifier_rate: Option<f32>,
+    dehumidifier_rate: Option<f32>,
+    daylight: Option<f32>,
+    lamp: Option<f32>,
+    file: Option<String>,
+    #[serde(rename = "loop")]
+    looping: Option<bool>,
+    shift: Option<String>,
+    interpolate: Option<bool>,
+    #[serde(default)]
+    faults: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEvent {
+    at: String,
+    id: Option<String>,
+    name: Option<String>,
+    offset: Option<f32>,
+    value: Option<RawValue>,
+    heat_loss: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum RawValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+}
+
+impl From<RawValue> for DatumValue {
+    fn from(value: RawValue) -> Self {
+        match value {
+            RawValue::Bool(value) => DatumValue::Bool(value),
+            RawValue::Int(value) => DatumValue::Int(value),
+            RawValue::Float(value) => DatumValue::Float(value),
+        }
+    }
+}
+
+impl RawValue {
+    fn as_f32(&self) -> Result<f32, String> {
+        match self {
+            RawValue::Int(value) => Ok(*value as f32),
+            RawValue::Float(value) => Ok(*value),
+            RawValue::Bool(value) => Err(format!("expected a number, found {}", value)),
+        }
+    }
+}
+
+/// Parses durations like "90s", "10m", "2h", or "1d" (see `device::config::parse_duration`),
+/// which may be negative, like "-30m".
+pub(crate) fn parse_duration(string: &str) -> Result<Duration, String> {
+    let string = string.trim();
+
+    let (sign, magnitude) = match string.strip_prefix('-') {
+        Some(magnitude) => (-1, magnitude),
+        None => (1, string),
+    };
+
+    let duration = device::config::parse_duration(magnitude)?;
+    let duration = Duration::from_std(duration).map_err(|err| err.to_string())?;
+
+    Ok(duration * sign)
+}
+
+fn parse_key(id: &Option<String>, name: &Option<String>) -> Result<Key, String> {
+    match (id, name) {
+        (Some(id), None) => Ok(Key::Id(Id::new(id))),
+        (None, Some(name)) => Ok(Key::Name(Name::new(name))),
+        (Some(_), Some(_)) => Err(String::from("specify either 'id' or 'name', not both")),
+        (None, None) => Err(String::from("one of 'id' or 'name' is required")),
+    }
+}
+
+fn required<T: Copy>(field: Option<T>, name: &str, generator: &str) -> Result<T, String> {
+    field.ok_or_else(|| format!("'{}' is required by generator '{}'", name, generator))
+}
+
+impl RawAttribute {
+    /// Validates this attribute; relative paths to recordings are relative to `dir`.
+    fn validate(&self, dir: &Path) -> Result<Attribute, String> {
+        let key = parse_key(&self.id, &self.name)?;
+
+        let generator = self.generator.as_str();
+
+        let recording = match (generator, &self.file) {
+            ("replay", Some(file)) => Some(Recording::from_file(dir.join(file))?),
+            ("replay", None) => {
+                return Err(String::from("'file' is required by generator 'replay'"))
+            }
+            _ => None,
+        };
+
+        let unit = match (&self.unit, generator, &recording) {
+            (Some(unit), _, _) => DatumUnit::parse(unit)?,
+            (None, "thermal", _) => DatumUnit::DegreesC,
+            (None, "humidity", _) => DatumUnit::RelativeHumidity,
+            (None, "light", _) => DatumUnit::Lux,
+            (None, "plug", _) => DatumUnit::PoweredOn,
+            (None, _, Some(recording)) => recording.unit(),
+            (None, _, None) => DatumUnit::Unitless,
+        };
+
+        let generator = match generator {
+            "constant" => Generator::Constant(required(self.value, "value", generator)?.into()),
+            "f32_linear" => Generator::F32Linear {
+                initial: self.initial.map(|v| v.as_f32()).transpose()?.unwrap_or(0.0),
+                slope: required(self.slope, "slope", generator)?,
+                noise: self.noise.unwrap_or(0.0),
+            },
+            "i32_linear" => Generator::I32Linear {
+                initial: match self.initial {
+                    None => 0,
+                    Some(RawValue::Int(value)) => value,
+                    Some(_) => return Err(String::from("'initial' must be an integer")),
+                },
+                slope: required(self.slope, "slope", generator)?.round() as i32,
+                noise: self.noise.unwrap_or(0.0).round() as i32,
+            },
+            "sinusoidal" => {
+                let period = parse_duration(required(self.period.as_ref(), "period", generator)?)?;
+
+                if period <= Duration::zero() {
+                    return Err(String::from("'period' must be positive"));
+                }
+
+                Generator::Sinusoidal {
+                    mean: required(self.mean, "mean", generator)?,
+                    amplitude: required(self.amplitude, "amplitude", generator)?,
+                    period,
+                }
+            }
+            "alternating" => match self.initial {
+                None => Generator::Alternating(false),
+                Some(RawValue::Bool(value)) => Generator::Alternating(value),
+                Some(_) => return Err(String::from("'initial' must be a bool")),
+            },
+            "steady" => match self.initial {
+                None => Generator::Steady(false),
+                Some(RawValue::Bool(value)) => Generator::Steady(value),
+                Some(_) => return Err(String::from("'initial' must be a bool")),
+            },
+            "thermal" => {
+                if unit != DatumUnit::DegreesC {
+                    return Err(format!("thermal zones must have unit '°C', not '{}'", unit));
+                }
+
+                let default = ZoneParameters::default();
+
+                let parameters = ZoneParameters {
+                    thermal_mass: self.thermal_mass.unwrap_or(default.thermal_mass),
+                    heat_loss: self.heat_loss.unwrap_or(default.heat_loss),
+                    heater_power: self.heater_power.unwrap_or(default.heater_power),
+                    cooler_power: self.cooler_power.unwrap_or(default.cooler_power),
+                };
+
+                if parameters.thermal_mass <= 0.0 {
+                    return Err(String::from("'thermal_mass' must be positive"));
+                }
+
+                if parameters.heat_loss < 0.0
+                    || parameters.heater_power < 0.0
+                    || parameters.cooler_power < 0.0
+                {
+                    return Err(String::from(
+                        "'heat_loss', 'heater_power', and 'cooler_power' cannot be negative",
+                    ));
+                }
+
+                let window = match (&self.window, self.open_heat_loss) {
+                    (None, None) => None,
+                    (Some(window), Some(open)) if open >= 0.0 => Some((Id::new(window), open)),
+                    (Some(_), Some(_)) => {
+                        return Err(String::from("'open_heat_loss' cannot be negative"))
+                    }
+                    _ => {
+                        return Err(String::from(
+                            "'window' and 'open_heat_loss' must be specified together",
+                        ))
+                    }
+                };
+
+                Generator::Thermal {
+                    initial: required(self.initial, "initial", generator)?.as_f32()?,
+                    parameters,
+                    outside: self.outside.as_ref().map(|id| Id::new(id)),
+                    window,
+                }
+            }
+            "humidity" => {
+                if unit != DatumUnit::RelativeHumidity {
+                    return Err(format!(
+                        "humidity zones must have unit '%RH', not '{}'",
+                        unit
+                    ));
+                }
+
+                let default = HumidityParameters::default();
+
+                let parameters = HumidityParameters {
+                    ambient: self.ambient.unwrap_or(default.ambient),
+                    exchange_rate: self.exchange_rate.unwrap_or(default.exchange_rate),
+                    humidifier_rate: self.humidifier_rate.unwrap_or(default.humidifier_rate),
+                    dehumidifier_rate: self.dehumidifier_rate.unwrap_or(default.dehumidifier_rate),
+                };
+
+                let initial = required(self.initial, "initial", generator)?.as_f32()?;
+
+                if !(0.0..=100.0).contains(&initial) || !(0.0..=100.0).contains(&parameters.ambient)
+                {
+                    return Err(String::from(
+                        "'initial' and 'ambient' must be between 0 and 100",
+                    ));
+                }
+
+                if parameters.exchange_rate < 0.0
+                    || parameters.humidifier_rate < 0.0
+                    || parameters.dehumidifier_rate < 0.0
+                {
+                    return Err(String::from(
+                        "'exchange_rate', 'humidifier_rate', and 'dehumidifier_rate' cannot be negative",
+                    ));
+                }
+
+                Generator::Humidity {
+                    initial,
+                    parameters,
+                }
+            }
+            "light" => {
+                if unit != DatumUnit::Lux {
+                    return Err(format!("light zones must have unit 'lx', not '{}'", unit));
+                }
+
+                let default = LightParameters::default();
+
+                let parameters = LightParameters {
+                    daylight: self.daylight.unwrap_or(default.daylight),
+                    lamp: self.lamp.unwrap_or(default.lamp),
+                };
+
+                if parameters.daylight < 0.0 || parameters.lamp < 0.0 {
+                    return Err(String::from("'daylight' and 'lamp' cannot be negative"));
+                }
+
+                Generator::Light(parameters)
+            }
+            "plug" => {
+                if unit != DatumUnit::PoweredOn {
+                    return Err(format!("plugs must have unit '⏼', not '{}'", unit));
+                }
+
+                match self.initial {
+                    None => Generator::Plug(false),
+                    Some(RawValue::Bool(value)) => Generator::Plug(value),
+                    Some(_) => return Err(String::from("'initial' must be a bool")),
+                }
+            }
+            "replay" => {
+                let recording = recording.unwrap();
+
+                if recording.unit() != unit {
+                    return Err(format!(
+                        "unit '{}' differs from the recording's unit '{}'",
+                        unit,
+                        recording.unit()
+                    ));
+                }
+
+                let options = ReplayOptions {
+                    looping: self.looping.unwrap_or(false),
+                    shift: match &self.shift {
+                        Some(shift) => parse_duration(shift)?,
+                        None => Duration::zero(),
+                    },
+                    interpolate: self.interpolate.unwrap_or(false),
+                };
+
+                Generator::Replay { recording, options }
+            }
+            other => {
+                return Err(format!(
+                    "unknown generator '{}' (expected one of constant, f32_linear, i32_linear, sinusoidal, alternating, steady, thermal, humidity, light, plug, replay)",
+                    other
+                ))
+            }
+        };
+
+        let faults = self
+            .faults
+            .iter()
+            .map(|fault| Fault::parse(fault))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Attribute {
+            key,
+            unit,
+            generator,
+            faults,
+        })
+    }
+}
+
+impl Attribute {
+    /// Parses a single attribute from JSON, in the same format as in a `Scenario`, but without an
+    /// `id` or `name` (since the attribute is assigned to the specified `Id`).
+    pub fn parse_json(id: &Id, string: &str) -> Result<Attribute, String> {
+        let mut raw: RawAttribute = serde_json::from_str(string).map_err(|err| err.to_string())?;
+
+        if raw.id.is_some() || raw.name.is_some() {
+            return Err(String::from("'id' and 'name' cannot be specified here"));
+        }
+
+        raw.id = Some(id.to_string());
+        raw.validate(Path::new(""))
+    }
+}
+
+impl RawEvent {
+    fn validate(&self) -> Result<Event, String> {
+        let key = parse_key(&self.id, &self.name)?;
+        let at = parse_duration(&self.at)?;
+
+        if at < Duration::zero() {
+            return Err(String::from("'at' cannot be negative"));
+        }
+
+        let action = match (self.offset, self.value, self.heat_loss) {
+            (Some(offset), None, None) => Action::Offset(offset),
+            (None, Some(value), None) => Action::Value(value.into()),
+            (None, None, Some(heat_loss)) => Action::HeatLoss(heat_loss),
+            _ => {
+                return Err(String::from(
+                    "exactly one of 'offset', 'value', or 'heat_loss' is required",
+                ))
+            }
+        };
+
+        Ok(Event { at, key, action })
+    }
+}
+
+impl Scenario {
+    /// Reads a `Scenario` from a `.toml`, `.yaml` (or `.yml`), or `.json` file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Scenario, String> {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read scenario {}: {}", path.display(), err))?;
+
+        // recordings referenced by the scenario are found relative to the scenario file
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let result = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Scenario::parse_toml_in(&contents, dir),
+            Some("yaml" | "yml") => Scenario::parse_yaml_in(&contents, dir),
+            Some("json") => Scenario::parse_json_in(&contents, dir),
+            _ => Err(String::from(
+                "scenario files must end in .toml, .yaml, .yml, or .json",
+            )),
+        };
+
+        result.map_err(|msg| format!("invalid scenario {}:\n{}", path.display(), msg))
+    }
+
+    /// Parses a TOML `Scenario`; relative paths to recordings are relative to the working directory.
+    pub fn parse_toml(string: &str) -> Result<Scenario, String> {
+        Scenario::parse_toml_in(string, PathBuf::new())
+    }
+
+    /// Parses a YAML `Scenario`; relative paths to recordings are relative to the working directory.
+    pub fn parse_yaml(string: &str) -> Result<Scenario, String> {
+        Scenario::parse_yaml_in(string, PathBuf::new())
+    }
+
+    /// Parses a JSON `Scenario`; relative paths to recordings are relative to the working directory.
+    pub fn parse_json(string: &str) -> Result<Scenario, String> {
+        Scenario::parse_json_in(string, PathBuf::new())
+    }
+
+    fn parse_toml_in(string: &str, dir: PathBuf) -> Result<Scenario, String> {
+        let raw: RawScenario = toml::from_str(string).map_err(|err| err.to_string())?;
+        Scenario::validate(raw, dir)
+    }
+
+    fn parse_yaml_in(string: &str, dir: PathBuf) -> Result<Scenario, String> {
+        let raw: RawScenario = serde_yaml::from_str(string).map_err(|err| err.to_string())?;
+        Scenario::validate(raw, dir)
+    }
+
+    fn parse_json_in(string: &str, dir: PathBuf) -> Result<Scenario, String> {
+        let raw: RawScenario = serde_json::from_str(string).map_err(|err| err.to_string())?;
+        Scenario::validate(raw, dir)
+    }
+
+    /// Validates every attribute and event, collecting all errors (one per line) rather than
+    /// stopping at the first one.
+    fn validate(raw: RawScenario, dir: PathBuf) -> Result<Scenario, String> {
+        let mut errors = Vec::new();
+        let mut scenario = Scenario::default();
+
+        for (index, attribute) in raw.attributes.iter().enumerate() {
+            match attribute.validate(&dir) {
+                Ok(attribute) => scenario.attributes.push(attribute),
+                Err(msg) => errors.push(format!("attributes[{}]: {}", index, msg)),
+            }
+        }
+
+        for (index, event) in raw.events.iter().enumerate() {
+            match event.validate() {
+                Ok(event) => scenario.events.push(event),
+                Err(msg) => errors.push(format!("events[{}]: {}", index, msg)),
+            }
+        }
+
+        // cross-references can only be checked once all attributes are valid
+        if errors.is_empty() {
+            errors.extend(scenario.cross_check());
+        }
+
+        if errors.is_empty() {
+            Ok(scenario)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    fn find(&self, key: &Key) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| &attribute.key == key)
+    }
+
+    fn cross_check(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+
+        for attribute in self.attributes.iter() {
+            if !seen.insert(attribute.key.to_string()) {
+                errors.push(format!("{} is defined more than once", attribute.key));
+            }
+
+            if let Generator::Thermal {
+                outside: Some(outside),
+                ..
+            } = &attribute.generator
+            {
+                match self.find(&Key::Id(outside.clone())) {
+                    None => errors.push(format!(
+                        "{}: outside temperature '{}' is not defined",
+                        attribute.key, outside
+                    )),
+                    Some(other) if other.unit != DatumUnit::DegreesC => errors.push(format!(
+                        "{}: outside temperature '{}' must have unit '°C'",
+                        attribute.key, outside
+                    )),
+                    Some(other) if matches!(other.generator, Generator::Thermal { .. }) => errors
+                        .push(format!(
+                            "{}: outside temperature '{}' cannot itself be a thermal zone",
+                            attribute.key, outside
+                        )),
+                    Some(_) => (),
+                }
+            }
+
+            if let Generator::Thermal {
+                window: Some((window, _)),
+                ..
+            } = &attribute.generator
+            {
+                match self
+                    .find(&Key::Id(window.clone()))
+                    .map(|other| &other.generator)
+                {
+                    None => errors.push(format!(
+                        "{}: window '{}' is not defined",
+                        attribute.key, window
+                    )),
+                    Some(Generator::Steady(_)) => (),
+                    Some(_) => errors.push(format!(
+                        "{}: window '{}' must use the 'steady' generator",
+                        attribute.key, window
+                    )),
+                }
+            }
+        }
+
+        for event in self.events.iter() {
+            match self.find(&event.key) {
+                None => errors.push(format!(
+                    "event at {}s: {} is not defined",
+                    event.at.num_seconds(),
+                    event.key
+                )),
+                Some(attribute) => {
+                    let is_thermal = matches!(attribute.generator, Generator::Thermal { .. });
+                    if matches!(event.action, Action::HeatLoss(_)) && !is_thermal {
+                        errors.push(format!(
+                            "event at {}s: 'heat_loss' can only be changed for thermal zones, but {} is not one",
+                            event.at.num_seconds(),
+                            event.key
+                        ))
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod scenario_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms"), Ok(Duration::milliseconds(250)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::minutes(10)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+        assert_eq!(parse_duration("-30m"), Ok(Duration::minutes(-30)));
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10 minutes").is_err());
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let scenario = Scenario::parse_toml(include_str!("../scenarios/window.toml")).unwrap();
+
+        assert_eq!(scenario.attributes.len(), 3);
+        assert_eq!(scenario.events.len(), 2);
+
+        assert_eq!(scenario.attributes[1].generator, Generator::Steady(false));
+        assert!(matches!(
+            &scenario.attributes[2].generator,
+            Generator::Thermal { window: Some((window, open)), .. }
+                if window == &Id::new("kitchen_window") && *open == 400.0
+        ));
+
+        assert_eq!(
+            scenario.events[0],
+            Event {
+                at: Duration::minutes(10),
+                key: Key::Id(Id::new("kitchen_window")),
+                action: Action::Value(DatumValue::Bool(true)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_humidity() {
+        let scenario = Scenario::parse_toml(include_str!("../scenarios/greenhouse.toml")).unwrap();
+
+        assert_eq!(scenario.attributes[0].unit, DatumUnit::RelativeHumidity);
+        assert_eq!(
+            scenario.attributes[0].generator,
+            Generator::Humidity {
+                initial: 75.0,
+                parameters: HumidityParameters {
+                    ambient: 85.0,
+                    exchange_rate: 0.2,
+                    dehumidifier_rate: 25.0,
+                    ..HumidityParameters::default()
+                },
+            }
+        );
+
+        let errors = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "greenhouse"
+            generator = "humidity"
+            initial = 120.0
+            "#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors,
+            "attributes[0]: 'initial' and 'ambient' must be between 0 and 100"
+        );
+    }
+
+    #[test]
+    fn test_parse_light() {
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            name = "Office Light Meter"
+            generator = "light"
+            daylight = 1000.0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.attributes[0].unit, DatumUnit::Lux);
+        assert_eq!(
+            scenario.attributes[0].generator,
+            Generator::Light(LightParameters {
+                daylight: 1000.0,
+                lamp: 500.0,
+            })
+        );
+
+        let errors = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "office"
+            generator = "light"
+            unit = "°C"
+            "#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors,
+            "attributes[0]: light zones must have unit 'lx', not '°C'"
+        );
+    }
+
+    #[test]
+    fn test_parse_plug() {
+        let scenario = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "kettle"
+            generator = "plug"
+            initial = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.attributes[0].unit, DatumUnit::PoweredOn);
+        assert_eq!(scenario.attributes[0].generator, Generator::Plug(true));
+
+        let errors = Scenario::parse_toml(
+            r#"
+            [[attributes]]
+            id = "kettle"
+            generator = "plug"
+            initial = 1.5
+            "#,
+        )
+        .unwrap_err();
+
+        assert_eq!(errors, "attributes[0]: 'initial' must be a bool");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{
+            "attributes": [
+                { "id": "door", "generator": "alternating", "unit": "⏼", "initial": true },
+                { "id": "count", "generator": "i32_linear", "slope": 1, "initial": 10 }
+            ]
+        }"#;
+
+        let scenario = Scenario::parse_json(json).unwrap();
+
+        assert_eq!(
+            scenario.attributes,
+            vec![
+                Attribute {
+                    key: Key::Id(Id::new("door")),
+                    unit: DatumUnit::PoweredOn,
+                    generator: Generator::Alternating(true),
+                    faults: vec![],
+                },
+                Attribute {
+                    key: Key::Id(Id::new("count")),
+                    unit: DatumUnit::Unitless,
+                    generator: Generator::I32Linear {
+                        initial: 10,
+                        slope: 1,
+                        noise: 0,
+                    },
+                    faults: vec![],
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = r#"
+            attributes:
+              - id: outside
+                generator: sinusoidal
+                unit: °C
+                mean: 10.0
+                amplitude: 5.0
+                period: 1d
+              - id: kitchen_window
+                generator: steady
+                initial: false
+              - name: Kitchen Thermostat
+                generator: thermal
+                initial: 20.0
+                outside: outside
+                heat_loss: 100.0
+                heater_power: 2000.0
+                window: kitchen_window
+                open_heat_loss: 400.0
+            events:
+              - at: 10m
+                id: kitchen_window
+                value: true
+              - at: 10m
+                id: outside
+                offset: -8.0
+        "#;
+
+        let toml = include_str!("../scenarios/window.toml");
+
+        assert_eq!(Scenario::parse_yaml(yaml), Scenario::parse_toml(toml));
+        assert!(Scenario::parse_yaml("attributes:\n  - id: door\n    generatr: steady").is_err());
+    }
+
+    #[test]
+    fn test_parse_replay() {
+        let scenario = Scenario::from_file("scenarios/incident.toml").unwrap();
+
+        match &scenario.attributes[0].generator {
+            Generator::Replay { recording, options } => {
+                assert_eq!(recording.unit(), DatumUnit::DegreesC);
+                assert_eq!(
+                    options,
+                    &ReplayOptions {
+                        looping: true,
+                        shift: Duration::minutes(-5),
+                        interpolate: true,
+                    }
+                );
+            }
+            other => panic!("Expected Replay, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_replay_errors() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "replay"
+
+            [[attributes]]
+            id = "hall"
+            generator = "replay"
+            file = "does/not/exist.csv"
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+        let lines: Vec<&str> = msg.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "attributes[0]: 'file' is required by generator 'replay'"
+        );
+        assert!(lines[1].starts_with("attributes[1]: cannot read recording does/not/exist.csv"));
+    }
+
+    #[test]
+    fn test_parse_faults() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "constant"
+            value = 1.0
+            faults = ["Latency:2s", "Spike:0.1:10"]
+        "#;
+
+        let scenario = Scenario::parse_toml(toml).unwrap();
+
+        assert_eq!(
+            scenario.attributes[0].faults,
+            vec![
+                Fault::Latency(Duration::seconds(2)),
+                Fault::Spike {
+                    probability: 0.1,
+                    magnitude: 10.0
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_faults_error() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "constant"
+            value = 1.0
+            faults = ["Gremlins"]
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+        assert_eq!(msg, "attributes[0]: cannot parse Gremlins as Fault");
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "constant"
+            valu = 3
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+        assert!(msg.contains("unknown field `valu`"), "{}", msg);
+    }
+
+    #[test]
+    /// All errors are reported at once, and each one says where it is
+    fn test_validation_errors() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "f32_linear"
+
+            [[attributes]]
+            id = "hall"
+            name = "Hall"
+            generator = "constant"
+            value = 1
+
+            [[attributes]]
+            id = "bedroom"
+            generator = "heat_pump"
+
+            [[attributes]]
+            id = "study"
+            generator = "thermal"
+            initial = 20.0
+            window = "study_window"
+
+            [[events]]
+            at = "10 minutes"
+            id = "kitchen"
+            offset = 1.0
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+        let lines: Vec<&str> = msg.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "attributes[0]: 'slope' is required by generator 'f32_linear'",
+                "attributes[1]: specify either 'id' or 'name', not both",
+                "attributes[2]: unknown generator 'heat_pump' (expected one of constant, f32_linear, i32_linear, sinusoidal, alternating, steady, thermal, humidity, light, plug, replay)",
+                "attributes[3]: 'window' and 'open_heat_loss' must be specified together",
+                "events[0]: cannot parse '10 minutes' as a duration, expected e.g. '500ms', '30s', '10m', '2h', or '1d'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cross_check_errors() {
+        let toml = r#"
+            [[attributes]]
+            id = "kitchen"
+            generator = "thermal"
+            initial = 20
+            outside = "outside"
+            window = "back_door"
+            open_heat_loss = 400.0
+
+            [[attributes]]
+            id = "kitchen"
+            generator = "constant"
+            value = 1.0
+
+            [[attributes]]
+            id = "back_door"
+            generator = "alternating"
+
+            [[events]]
+            at = "1h"
+            id = "kitchen"
+            heat_loss = 300.0
+
+            [[events]]
+            at = "1h"
+            name = "Bedroom"
+            offset = 1.0
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+        let lines: Vec<&str> = msg.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "id 'kitchen': outside temperature 'outside' is not defined",
+                "id 'kitchen': window 'back_door' must use the 'steady' generator",
+                "id 'kitchen' is defined more than once",
+                "event at 3600s: name 'Bedroom' is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heat_loss_requires_thermal_zone() {
+        let toml = r#"
+            [[attributes]]
+            id = "outside"
+            generator = "constant"
+            unit = "°C"
+            value = 1.0
+
+            [[events]]
+            at = "1h"
+            id = "outside"
+            heat_loss = 300.0
+        "#;
+
+        let msg = Scenario::parse_toml(toml).unwrap_err();
+
+        assert_eq!(
+            msg,
+            "event at 3600s: 'heat_loss' can only be changed for thermal zones, but id 'outside' is not one"
+        );
+    }
+}
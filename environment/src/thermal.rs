@@ -0,0 +1,270 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+use actuator_temperature::Command;
+use datum::{DatumUnit, DatumValue};
+
+use crate::generator::DatumGenerator;
+
+/// The physical parameters of a single zone (room) in a `ThermalModel`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ZoneParameters {
+    /// How much energy it takes to raise the temperature of the zone by 1°C, in J/°C.
+    pub thermal_mass: f32,
+    /// How much power leaks out of the zone per degree of difference from the outside, in W/°C.
+    pub heat_loss: f32,
+    /// How much power the heater adds to the zone when it is running, in W.
+    pub heater_power: f32,
+    /// How much power the cooler removes from the zone when it is running, in W.
+    pub cooler_power: f32,
+}
+
+impl Default for ZoneParameters {
+    /// A small, moderately-insulated room with a 2kW heater and a 1.5kW air conditioner.
+    fn default() -> Self {
+        Self {
+            thermal_mass: 2_000_000.0,
+            heat_loss: 100.0,
+            heater_power: 2_000.0,
+            cooler_power: 1_500.0,
+        }
+    }
+}
+
+/// A lumped-capacitance model of the temperature in a single zone.
+///
+/// The zone loses (or gains) heat to the `outside` in proportion to the difference between the two
+/// temperatures, and a heater or cooler adds or removes a fixed amount of power, depending on the
+/// latest `Command` sent by an `Actuator`. The heater runs while the zone is below its `HeatTo`
+/// target; the cooler runs while the zone is above its `CoolTo` target.
+///
+/// The model is integrated forward in time whenever it is queried, so it can be driven by the wall
+/// clock (when probed by `Sensor`s) or by a synthetic clock (when tuning control strategies offline).
+pub struct ThermalModel {
+    temperature: f32,
+    parameters: ZoneParameters,
+    outside: DatumGenerator,
+    command: Option<Command>,
+    updated: DateTime<Utc>,
+}
+
+impl ThermalModel {
+    /// The longest time step used when integrating the model, to keep the integration stable.
+    const MAX_STEP_SECONDS: i64 = 10;
+
+    pub fn new(
+        temperature: f32,
+        parameters: ZoneParameters,
+        outside: DatumGenerator,
+        start: DateTime<Utc>,
+    ) -> ThermalModel {
+        ThermalModel {
+            temperature,
+            parameters,
+            outside,
+            command: None,
+            updated: start,
+        }
+    }
+
+    /// Returns the temperature of the zone as of the last time the model was advanced.
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    /// Returns how quickly the zone currently loses heat to the outside, in W/°C.
+    pub fn heat_loss(&self) -> f32 {
+        self.parameters.heat_loss
+    }
+
+    /// Returns the latest `Command` received by this zone, if any.
+    pub fn command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
+
+    /// Integrates the model forward to `now`, then applies the `command` from that point onward.
+    pub fn apply(&mut self, command: Command, now: DateTime<Utc>) {
+        self.advance(now);
+        self.command = Some(command);
+    }
+
+    /// Integrates the model forward to `now`, then changes how quickly the zone loses heat to the
+    /// outside from that point onward (e.g. when a window is opened or closed).
+    pub fn set_heat_loss(&mut self, heat_loss: f32, now: DateTime<Utc>) {
+        self.advance(now);
+        self.parameters.heat_loss = heat_loss;
+    }
+
+    /// Integrates the model forward by `duration`, returning the new temperature of the zone.
+    pub fn step(&mut self, duration: Duration) -> f32 {
+        self.advance(self.updated + duration)
+    }
+
+    /// Integrates the model forward to `now`, returning the new temperature of the zone.
+    ///
+    /// If `now` is earlier than the last time the model was advanced, the model is left unchanged.
+    pub fn advance(&mut self, now: DateTime<Utc>) -> f32 {
+        // the outside temperature changes slowly, so we only sample it once per call
+        let outside = self
+            .outside
+            .generate_at(now)
+            .get_as_float()
+            .unwrap_or_default();
+
+        let max_step = Duration::seconds(Self::MAX_STEP_SECONDS);
+
+        while self.updated < now {
+            let step = std::cmp::min(max_step, now - self.updated);
+            // converting i64 to f32 is safe as long as steps are < 9.4e28 hours
+            let seconds = step.num_milliseconds() as f32 / 1000.0;
+
+            let loss = self.parameters.heat_loss * (self.temperature - outside);
+            let power = self.hvac_power() - loss;
+
+            self.temperature += power * seconds / self.parameters.thermal_mass;
+            self.updated += step;
+        }
+
+        self.temperature
+    }
+
+    /// Returns the power currently added to (positive) or removed from (negative) the zone.
+    fn hvac_power(&self) -> f32 {
+        match self.command {
+            Some(Command::HeatTo(target)) if self.temperature < target => {
+                self.parameters.heater_power
+            }
+            Some(Command::CoolTo(target)) if self.temperature > target => {
+                -self.parameters.cooler_power
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Creates a `DatumGenerator` which reads the temperature of a zone by integrating its `model`.
+pub fn generator(model: Arc<Mutex<ThermalModel>>) -> DatumGenerator {
+    let f = move |now: DateTime<Utc>| -> DatumValue {
+        let mut model = model.lock().unwrap();
+        DatumValue::Float(model.advance(now))
+    };
+
+    DatumGenerator::new(Box::new(f), DatumUnit::DegreesC).with_kind("thermal")
+}
+
+#[cfg(test)]
+mod thermal_tests {
+    use super::*;
+
+    fn constant(temperature: f32) -> DatumGenerator {
+        let f = move |_| -> DatumValue { DatumValue::Float(temperature) };
+        DatumGenerator::new(Box::new(f), DatumUnit::DegreesC)
+    }
+
+    fn zone(initial: f32, outside: f32) -> ThermalModel {
+        ThermalModel::new(
+            initial,
+            ZoneParameters::default(),
+            constant(outside),
+            Utc::now(),
+        )
+    }
+
+    #[test]
+    /// With no heating or cooling, the zone drifts towards the outside temperature
+    fn test_zone_loses_heat_to_outside() {
+        let mut model = zone(20.0, 0.0);
+
+        let after_an_hour = model.step(Duration::hours(1));
+        let after_a_day = model.step(Duration::hours(23));
+
+        assert!(after_an_hour < 20.0);
+        assert!(after_a_day < after_an_hour);
+        assert!(after_a_day > 0.0);
+    }
+
+    #[test]
+    /// The heater holds the zone at (about) its target temperature
+    fn test_heat_to() {
+        let mut model = zone(15.0, 10.0);
+        let now = model.updated;
+
+        model.apply(Command::HeatTo(21.0), now);
+        let temperature = model.step(Duration::hours(12));
+
+        assert!(
+            (temperature - 21.0).abs() < 0.5,
+            "{} is not ~21",
+            temperature
+        );
+    }
+
+    #[test]
+    /// The cooler holds the zone at (about) its target temperature
+    fn test_cool_to() {
+        let mut model = zone(30.0, 35.0);
+        let now = model.updated;
+
+        model.apply(Command::CoolTo(24.0), now);
+        let temperature = model.step(Duration::hours(12));
+
+        assert!(
+            (temperature - 24.0).abs() < 0.5,
+            "{} is not ~24",
+            temperature
+        );
+    }
+
+    #[test]
+    /// A heater that is too weak for the weather cannot reach its target
+    fn test_heater_limited_by_power() {
+        let mut model = zone(20.0, -30.0);
+        let now = model.updated;
+
+        model.apply(Command::HeatTo(21.0), now);
+        let temperature = model.step(Duration::hours(48));
+
+        // steady state is outside + heater_power / heat_loss = -30 + 2000 / 100 = -10
+        assert!(
+            (temperature + 10.0).abs() < 0.5,
+            "{} is not ~-10",
+            temperature
+        );
+    }
+
+    #[test]
+    /// Opening a window makes the zone cool down more quickly
+    fn test_set_heat_loss() {
+        let mut closed = zone(20.0, 0.0);
+        let mut open = zone(20.0, 0.0);
+        let now = open.updated;
+
+        open.set_heat_loss(1_000.0, now);
+
+        assert!(open.step(Duration::hours(1)) < closed.step(Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_advance_into_the_past_is_ignored() {
+        let mut model = zone(20.0, 0.0);
+        let start = model.updated;
+
+        model.step(Duration::hours(1));
+        let temperature = model.temperature();
+
+        assert_eq!(model.advance(start), temperature);
+    }
+
+    #[test]
+    fn test_generator() {
+        let start = Utc::now();
+        let model = ThermalModel::new(18.0, ZoneParameters::default(), constant(18.0), start);
+        let generator = generator(Arc::new(Mutex::new(model)));
+
+        let datum = generator.generate_at(start + Duration::minutes(5));
+
+        assert_eq!(datum.get_as_float(), Some(18.0));
+        assert_eq!(datum.unit, DatumUnit::DegreesC);
+    }
+}
@@ -1,8 +1,23 @@
-use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
 
-use datum::Datum;
-use device::Device;
+use datum::{Datum, DatumUnit, DatumValueType, Reading};
+use device::config::{PushConfig, SAMPLE_INTERVAL};
+use device::{protocol, Device, Id};
+use tracing::{debug, warn};
+
+pub use crate::measurement::Measurement;
+pub use crate::stream::{Sampler, StreamOptions};
+
+mod measurement;
+mod push;
+mod stream;
+
+/// The longest a `Sensor` streaming its `Reading`s (see `Sensor::stream`) goes without sending
+/// one, even if nothing has changed.
+pub const HEARTBEAT: Duration = Duration::from_secs(5);
 
 /// A Sensor collects data from the Environment.
 pub trait Sensor: Device {
@@ -10,31 +25,307 @@ pub trait Sensor: Device {
     ///
     /// In the "real world", this would poll some actual physical sensor for a data point.
     ///
-    /// In our example MVP, this queries the `Environment` for data.
-    fn get_datum(&self) -> Datum;
-
-    /// Responds to all incoming requests with the latest `Datum`.
-    fn respond(&self, listener: TcpListener) {
-        for stream in listener.incoming() {
-            let mut stream = stream.unwrap();
-            let mut request = String::new();
-
-            BufReader::new(&mut stream).read_line(&mut request).unwrap();
-            println!("{} received request: {}", self.get_name(), request.trim());
-
-            let contents = self.get_datum().to_string();
-            let ack = format!(
-                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
-                contents.len(),
-                contents
-            );
-            stream.write_all(ack.as_bytes()).unwrap();
+    /// In our example MVP, this queries the `Environment` for data. Returns an error if no data
+    /// could be read.
+    fn get_datum(&self) -> Result<Datum, String>;
+
+    /// Gets every quantity this sensor measures, each on its own named channel.
+    ///
+    /// Most sensors measure a single quantity, so by default this is a `Reading` with a single
+    /// unnamed channel, holding the `Datum` from `get_datum`. Sensors which measure several
+    /// quantities at once (like a combined temperature / humidity / CO₂ probe) override this.
+    fn get_reading(&self) -> Result<Reading, String> {
+        self.get_datum().map(Reading::from)
+    }
+
+    /// Returns the kind of this sensor, e.g. `temperature` or `climate`.
+    fn get_kind(&self) -> &str;
+
+    /// Describes every quantity this sensor measures, on the same channels as `get_reading`.
+    fn get_measurements(&self) -> Vec<Measurement>;
+
+    /// Describes the capabilities of this sensor as mDNS TXT properties: its `kind`, and the
+    /// `Measurement`s it `measures` (see `Device::get_capabilities`).
+    fn describe(&self) -> HashMap<String, String> {
+        let mut properties = HashMap::new();
+        properties.insert(String::from("kind"), self.get_kind().to_string());
+        properties.insert(
+            String::from("measures"),
+            Measurement::join(&self.get_measurements()),
+        );
+        properties
+    }
+
+    /// Responds to all incoming requests with the latest `Reading`, or with a `503` if no data
+    /// could be read.
+    ///
+    /// Requests for protocol version 1 (see `device::protocol`) are answered with the `Datum` from
+    /// `get_datum` instead, and requests for versions this sensor doesn't speak with a `400`.
+    /// `GET /stream` requests (in versions which support it) subscribe to a stream of `Reading`s,
+    /// each served on its own thread, and all sharing the same samples; see `stream`.
+    fn respond(&self, listener: TcpListener)
+    where
+        Self: Sync,
+    {
+        let sampler = Sampler::default();
+
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+
+                let request = match device::http::read_request(&mut stream, device::http::TIMEOUT) {
+                    Ok(Some(request)) => request,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(id = %self.get_id(), name = %self.get_name(), error = %err, "cannot read request");
+                        continue;
+                    }
+                };
+
+                debug!(id = %self.get_id(), name = %self.get_name(), request = request.trim(), "received request");
+
+                let (status, contents, version) = match protocol::version(&request) {
+                    Err(msg) => {
+                        warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "invalid request");
+                        ("400 Bad Request", msg, protocol::PROTOCOL_VERSION)
+                    }
+                    Ok(version)
+                        if version >= protocol::STREAM_VERSION
+                            && request.starts_with("GET /stream") =>
+                    {
+                        let path = request.split_whitespace().nth(1).unwrap_or_default();
+                        let query = path.split_once('?').map(|(_, query)| query);
+
+                        match StreamOptions::parse(query.unwrap_or_default()) {
+                            Ok(options) => {
+                                let sampler = &sampler;
+                                scope.spawn(move || self.stream(stream, version, options, sampler));
+                                continue;
+                            }
+                            Err(msg) => ("400 Bad Request", msg, version),
+                        }
+                    }
+                    Ok(version) => {
+                        let result = match version {
+                            1 => self.get_datum().map(|datum| datum.to_string()),
+                            _ => self.get_reading().map(|reading| reading.to_string()),
+                        };
+
+                        match result {
+                            Ok(contents) => ("200 OK", contents, version),
+                            Err(msg) => {
+                                warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "cannot read datum");
+                                ("503 Service Unavailable", msg, version)
+                            }
+                        }
+                    }
+                };
+
+                let ack = format!(
+                    "HTTP/1.1 {}\r\n{}: {}\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
+                    status,
+                    protocol::HEADER,
+                    version,
+                    contents.len(),
+                    contents
+                );
+                stream.write_all(ack.as_bytes()).unwrap();
+            }
+        })
+    }
+
+    /// Streams this sensor's `Reading`s to a subscriber as server-sent events, until it
+    /// disconnects.
+    ///
+    /// A `reading` event is sent whenever a value moves by more than the subscriber's `deadband`,
+    /// and an `error` event whenever the sensor can no longer be read. If nothing has been sent for
+    /// the subscriber's `interval` (or a `HEARTBEAT`, if that is sooner), the latest event is sent
+    /// again, so the subscriber has fresh data even when nothing changes, and knows the stream is
+    /// still alive. The sensor is read through the `sampler`, which all subscribers share.
+    fn stream(
+        &self,
+        mut stream: TcpStream,
+        version: u32,
+        options: StreamOptions,
+        sampler: &Sampler,
+    ) {
+        let headers = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n{}: {}\r\n\r\n",
+            protocol::HEADER,
+            version
+        );
+
+        if stream.write_all(headers.as_bytes()).is_err() {
+            return;
+        }
+
+        debug!(id = %self.get_id(), name = %self.get_name(), "subscriber connected");
+
+        let mut sent: Option<Result<Reading, String>> = None;
+        let mut sent_at = Instant::now();
+        let interval = options.interval.min(HEARTBEAT);
+
+        loop {
+            let latest = sampler.sample(|| self.get_reading());
+            let due = sent_at.elapsed() >= interval;
+
+            let event = match (&sent, &latest) {
+                (Some(Ok(sent)), Ok(reading))
+                    if !due && !push::changed(sent, reading, options.deadband) =>
+                {
+                    None
+                }
+                (Some(Err(sent)), Err(msg)) if !due && sent == msg => None,
+                (_, Ok(reading)) => Some(format!("event: reading\ndata: {}\n\n", reading)),
+                (_, Err(msg)) => Some(format!(
+                    "event: error\ndata: {}\n\n",
+                    msg.replace('\n', " ")
+                )),
+            };
+
+            let Some(event) = event else {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                continue;
+            };
+
+            sent = Some(latest);
+
+            if stream.write_all(event.as_bytes()).is_err() || stream.flush().is_err() {
+                debug!(id = %self.get_id(), name = %self.get_name(), "subscriber disconnected");
+                return;
+            }
+
+            sent_at = Instant::now();
+            std::thread::sleep(SAMPLE_INTERVAL)
+        }
+    }
+
+    /// Pushes this sensor's `Reading`s to the `Controller`, forever, as they change.
+    ///
+    /// Every `sample_interval`, the latest `Reading` is pushed if any of its values has moved by
+    /// more than the `deadband` since the last push, or if nothing has been pushed for `interval`.
+    /// Failed pushes (e.g. because the `Controller` is rate-limiting this sensor) are retried
+    /// after a backoff, which doubles after each failure, up to the `interval`.
+    fn push(&self, config: &PushConfig) {
+        let mut pushed: Option<(Reading, Instant)> = None;
+        let mut backoff = config.sample_interval;
+
+        loop {
+            let reading = match self.get_reading() {
+                Ok(reading) => reading,
+                Err(msg) => {
+                    debug!(id = %self.get_id(), name = %self.get_name(), error = msg, "cannot read datum");
+                    std::thread::sleep(config.sample_interval);
+                    continue;
+                }
+            };
+
+            let due = match &pushed {
+                Some((last, at)) => {
+                    at.elapsed() >= config.interval
+                        || push::changed(last, &reading, config.deadband)
+                }
+                None => true,
+            };
+
+            if due {
+                match push::push_reading(&config.to, self.get_id(), &reading) {
+                    Ok(()) => {
+                        debug!(id = %self.get_id(), name = %self.get_name(), %reading, "pushed reading");
+                        pushed = Some((reading, Instant::now()));
+                        backoff = config.sample_interval;
+                    }
+                    Err(msg) => {
+                        warn!(id = %self.get_id(), name = %self.get_name(), error = msg, "cannot push reading");
+                        std::thread::sleep(backoff);
+                        backoff = (backoff * 2).min(config.interval);
+                        continue;
+                    }
+                }
+            }
+
+            std::thread::sleep(config.sample_interval)
         }
     }
+
+    /// Responds to polls from the `Controller` on the `listener` (see `respond`) and, if `push` is
+    /// set, also pushes `Reading`s to it as they change (see `push`).
+    fn serve(&self, listener: TcpListener, push: Option<&PushConfig>)
+    where
+        Self: Sync,
+    {
+        std::thread::scope(|scope| {
+            if let Some(config) = push {
+                scope.spawn(|| self.push(config));
+            }
+
+            self.respond(listener)
+        })
+    }
+}
+
+/// Queries the `Environment` at `address` for the latest `Datum` of the specified type and unit
+/// observed by the `sensor`.
+///
+/// The `Environment` identifies attributes by the `sensor`'s `Id`, and may also match it to an
+/// attribute in a `Scenario` by its `Name`.
+pub fn query_environment<S: Sensor + ?Sized>(
+    sensor: &S,
+    address: &str,
+    value_type: DatumValueType,
+    unit: DatumUnit,
+) -> Result<Datum, String> {
+    get(
+        address,
+        sensor.get_id(),
+        &sensor.get_name().0,
+        value_type,
+        unit,
+    )
+}
+
+/// Queries the `Environment` at `address` for the latest `Datum` measured on the named `channel`
+/// of the `sensor`.
+///
+/// The `Environment` identifies each channel by its own `Id` (see `Id::channel`), and its `Name`
+/// by the `sensor`'s `Name` followed by the channel, e.g. `Greenhouse Probe:co2`.
+pub fn query_channel<S: Sensor + ?Sized>(
+    sensor: &S,
+    address: &str,
+    channel: &str,
+    value_type: DatumValueType,
+    unit: DatumUnit,
+) -> Result<Datum, String> {
+    let id = sensor.get_id().channel(channel);
+    let name = format!("{}:{}", sensor.get_name(), channel);
+    get(address, &id, &name, value_type, unit)
+}
+
+fn get(
+    address: &str,
+    id: &Id,
+    name: &str,
+    value_type: DatumValueType,
+    unit: DatumUnit,
+) -> Result<Datum, String> {
+    let request = format!(
+        "GET /get/{}/{}/{} HTTP/1.1\r\nDevice-Name: {}\r\n{}: {}\r\n\r\n",
+        id,
+        value_type,
+        unit,
+        name,
+        protocol::HEADER,
+        protocol::PROTOCOL_VERSION
+    );
+
+    let body = device::http::send(address, &request, device::http::TIMEOUT)?;
+    Datum::parse(&body)
 }
 
 #[cfg(test)]
 mod sensor_tests {
+    use std::io::{BufRead, BufReader, Read};
+
     use datum::{DatumUnit, DatumValue};
     use device::{Id, Name};
 
@@ -65,19 +356,149 @@ mod sensor_tests {
     }
 
     impl Sensor for Thermometer {
-        fn get_datum(&self) -> Datum {
+        fn get_datum(&self) -> Result<Datum, String> {
             // in our example, this should query the Environment
             // in this test, we just return a constant value
-            Datum::new_now(DatumValue::Float(42.0), DatumUnit::DegreesC)
+            Ok(Datum::new_now(DatumValue::Float(42.0), DatumUnit::DegreesC))
+        }
+
+        fn get_kind(&self) -> &str {
+            "temperature"
+        }
+
+        fn get_measurements(&self) -> Vec<Measurement> {
+            vec![Measurement::new(
+                "",
+                DatumValueType::Float,
+                DatumUnit::DegreesC,
+            )]
         }
     }
 
     #[test]
     fn test_get_datum() {
         let thermometer = Thermometer::new();
-        let datum = thermometer.get_datum();
+        let datum = thermometer.get_datum().unwrap();
 
         assert_eq!(datum.value, DatumValue::Float(42.0));
         assert_eq!(datum.unit, DatumUnit::DegreesC)
     }
+
+    #[test]
+    fn test_get_reading() {
+        let thermometer = Thermometer::new();
+        let reading = thermometer.get_reading().unwrap();
+
+        assert_eq!(reading.channels.len(), 1);
+        assert_eq!(reading.get("").unwrap().value, DatumValue::Float(42.0));
+    }
+
+    #[test]
+    fn test_respond() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || Thermometer::new().respond(listener));
+
+        let request = |version: &str| {
+            let request = format!("GET / HTTP/1.1\r\nProtocol-Version: {}\r\n\r\n", version);
+            device::http::send(&address, &request, device::http::TIMEOUT)
+        };
+
+        assert!(request("1").unwrap().starts_with("42.0@°C@"));
+        assert!(request("2").unwrap().starts_with("42.0@°C@"));
+        assert_eq!(
+            request("9"),
+            Err(String::from(
+                "HTTP/1.1 400 Bad Request: unsupported protocol version '9' (expected 1..3)"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || Thermometer::new().respond(listener));
+
+        // versions which predate streaming just get the latest Reading
+        let request = "GET /stream HTTP/1.1\r\nProtocol-Version: 2\r\n\r\n";
+        let response = device::http::send(&address, request, device::http::TIMEOUT);
+        assert!(response.unwrap().starts_with("42.0@°C@"));
+
+        let request = "GET /stream?interval=never HTTP/1.1\r\nProtocol-Version: 3\r\n\r\n";
+        let response = device::http::send(&address, request, device::http::TIMEOUT);
+        assert!(response
+            .unwrap_err()
+            .starts_with("HTTP/1.1 400 Bad Request"));
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let request = "GET /stream?interval=200ms HTTP/1.1\r\nProtocol-Version: 3\r\n\r\n";
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut lines = BufReader::new(stream).lines().map(|line| line.unwrap());
+        let head: Vec<String> = lines.by_ref().take_while(|line| !line.is_empty()).collect();
+
+        assert_eq!(head[0], "HTTP/1.1 200 OK");
+        assert!(head.contains(&String::from("Content-Type: text/event-stream")));
+        assert!(head.contains(&String::from("Protocol-Version: 3")));
+
+        // the temperature never changes, but it is sent again every interval
+        for _ in 0..2 {
+            assert_eq!(lines.next().unwrap(), "event: reading");
+            assert!(lines.next().unwrap().starts_with("data: 42.0@°C@"));
+            assert_eq!(lines.next().unwrap(), "");
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let config = PushConfig {
+            to: listener.local_addr().unwrap().to_string(),
+            deadband: 0.5,
+            interval: Duration::from_secs(60),
+            sample_interval: Duration::from_millis(10),
+        };
+
+        std::thread::spawn(move || Thermometer::new().push(&config));
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(&mut stream);
+        let mut request = String::new();
+
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+
+        let length = device::http::header(&request, "Content-Length").unwrap();
+        let mut body = vec![0; length.parse().unwrap()];
+        reader.read_exact(&mut body).unwrap();
+        let body = String::from_utf8(body).unwrap();
+
+        assert!(request.starts_with("POST /sensors/should be random/readings HTTP/1.1\r\n"));
+        let version = protocol::PROTOCOL_VERSION.to_string();
+        assert_eq!(
+            device::http::header(&request, protocol::HEADER),
+            Some(version.as_str())
+        );
+        assert!(body.starts_with("42.0@°C@"));
+
+        stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+    }
+
+    #[test]
+    fn test_describe() {
+        let properties = Thermometer::new().describe();
+
+        assert_eq!(properties["kind"], "temperature");
+        assert_eq!(properties["measures"], "float@°C");
+    }
 }
@@ -0,0 +1,124 @@
+use std::fmt::{Display, Formatter};
+
+use datum::{Datum, DatumUnit, DatumValue, DatumValueType};
+
+/// Describes a quantity measured by a `Sensor`: the channel it is reported on, and the type and
+/// unit of its values.
+///
+/// A `Measurement` is serialized as `type@unit` if its channel is unnamed (as for most sensors),
+/// or as `channel:type@unit`, e.g. `co2:float@ppm`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Measurement {
+    pub channel: String,
+    pub value_type: DatumValueType,
+    pub unit: DatumUnit,
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.channel.is_empty() {
+            write!(f, "{}:", self.channel)?;
+        }
+
+        write!(f, "{}@{}", self.value_type, self.unit)
+    }
+}
+
+impl Measurement {
+    pub fn new(channel: &str, value_type: DatumValueType, unit: DatumUnit) -> Measurement {
+        Measurement {
+            channel: String::from(channel),
+            value_type,
+            unit,
+        }
+    }
+
+    pub fn parse(string: &str) -> Result<Measurement, String> {
+        // units and types never contain a ':', but channel names might
+        let (channel, rest) = string.rsplit_once(':').unwrap_or(("", string));
+
+        let Some((value_type, unit)) = rest.split_once('@') else {
+            return Err(format!("cannot parse '{}' as a Measurement", string));
+        };
+
+        Ok(Measurement::new(
+            channel,
+            DatumValueType::parse(value_type)?,
+            DatumUnit::parse(unit)?,
+        ))
+    }
+
+    /// Parses a list of `Measurement`s, separated by semicolons.
+    pub fn parse_all(string: &str) -> Result<Vec<Measurement>, String> {
+        string
+            .split(';')
+            .filter(|measurement| !measurement.is_empty())
+            .map(Measurement::parse)
+            .collect()
+    }
+
+    /// Serializes a list of `Measurement`s, separated by semicolons.
+    pub fn join(measurements: &[Measurement]) -> String {
+        let strings: Vec<String> = measurements.iter().map(|m| m.to_string()).collect();
+        strings.join(";")
+    }
+
+    /// Returns an error if the `datum` is not of this measurement's type and unit.
+    pub fn check(&self, datum: &Datum) -> Result<(), String> {
+        let valid = matches!(
+            (self.value_type, &datum.value),
+            (DatumValueType::Bool, DatumValue::Bool(_))
+                | (DatumValueType::Int, DatumValue::Int(_))
+                | (
+                    DatumValueType::Float,
+                    DatumValue::Float(_) | DatumValue::Int(_)
+                )
+        );
+
+        if valid && datum.unit == self.unit {
+            Ok(())
+        } else {
+            Err(format!("expected {}, but got {}", self, datum))
+        }
+    }
+}
+
+#[cfg(test)]
+mod measurement_tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let measurements = vec![
+            Measurement::new("", DatumValueType::Float, DatumUnit::DegreesC),
+            Measurement::new("co2", DatumValueType::Float, DatumUnit::PartsPerMillion),
+            Measurement::new("open", DatumValueType::Bool, DatumUnit::Unitless),
+        ];
+
+        let serialized = Measurement::join(&measurements);
+
+        assert_eq!(serialized, "float@°C;co2:float@ppm;open:bool@");
+        assert_eq!(Measurement::parse_all(&serialized), Ok(measurements));
+
+        assert!(Measurement::parse("float").is_err());
+        assert!(Measurement::parse("co2:gas@ppm").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let measurement = Measurement::new("", DatumValueType::Float, DatumUnit::DegreesC);
+
+        assert!(measurement
+            .check(&Datum::new_now(21.5, DatumUnit::DegreesC))
+            .is_ok());
+        assert!(measurement
+            .check(&Datum::new_now(21, DatumUnit::DegreesC))
+            .is_ok());
+        assert!(measurement
+            .check(&Datum::new_now(true, DatumUnit::DegreesC))
+            .is_err());
+        assert!(measurement
+            .check(&Datum::new_now(50.0, DatumUnit::RelativeHumidity))
+            .is_err());
+    }
+}
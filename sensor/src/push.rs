@@ -0,0 +1,75 @@
+use datum::{DatumValue, Reading};
+use device::{protocol, Id};
+
+/// Returns `true` if the `latest` `Reading` differs enough from the one last `pushed` to be worth
+/// pushing to the `Controller`.
+///
+/// A numeric channel has changed if its value moved by more than the `deadband`. Any other change
+/// (a boolean flipping, a unit changing, or a channel appearing or disappearing) always counts.
+pub(crate) fn changed(pushed: &Reading, latest: &Reading, deadband: f32) -> bool {
+    if pushed.channels.len() != latest.channels.len() {
+        return true;
+    }
+
+    let number = |value: &DatumValue| match *value {
+        DatumValue::Float(value) => Some(value),
+        DatumValue::Int(value) => Some(value as f32),
+        DatumValue::Bool(_) => None,
+    };
+
+    latest.channels.iter().any(|channel| {
+        let Some(previous) = pushed.channels.iter().find(|c| c.name == channel.name) else {
+            return true;
+        };
+
+        if previous.unit != channel.unit {
+            return true;
+        }
+
+        match (number(&previous.value), number(&channel.value)) {
+            (Some(previous), Some(latest)) => (latest - previous).abs() > deadband,
+            _ => previous.value != channel.value,
+        }
+    })
+}
+
+/// Pushes a `Reading` from the `Sensor` with the specified `Id` to the `Controller` at `address`.
+///
+/// Returns an error if the `Controller` can't be reached, or rejects the `Reading` (e.g. with a
+/// `429` because this sensor is pushing too often).
+pub(crate) fn push_reading(address: &str, id: &Id, reading: &Reading) -> Result<(), String> {
+    let body = reading.to_string();
+
+    let request = format!(
+        "POST /sensors/{}/readings HTTP/1.1\r\n{}: {}\r\nContent-Length: {}\r\n\r\n{}",
+        id,
+        protocol::HEADER,
+        protocol::PROTOCOL_VERSION,
+        body.len(),
+        body
+    );
+
+    device::http::send(address, &request, device::http::TIMEOUT).map(|_| ())
+}
+
+#[cfg(test)]
+mod push_tests {
+    use super::*;
+
+    #[test]
+    fn test_changed() {
+        let reading =
+            |channels: &str| Reading::parse(&format!("{}@2024-01-01T00:00:00Z", channels));
+
+        let pushed = reading("temperature=21.0@°C;open=false@").unwrap();
+
+        let changed = |channels: &str| changed(&pushed, &reading(channels).unwrap(), 0.5);
+
+        assert!(!changed("temperature=21.4@°C;open=false@"));
+        assert!(!changed("temperature=20.6@°C;open=false@"));
+        assert!(changed("temperature=21.6@°C;open=false@"));
+        assert!(changed("temperature=21.0@°C;open=true@"));
+        assert!(changed("temperature=21.0@°C"));
+        assert!(changed("temperature=21.0@%RH;open=false@"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use datum::Reading;
+use device::config::{parse_duration, DEADBAND, SAMPLE_INTERVAL};
+
+use crate::HEARTBEAT;
+
+/// What a subscriber to a `Sensor`'s stream (see `Sensor::stream`) asked for, in the query of its
+/// `GET /stream` request, e.g. `deadband=0.1&interval=1s`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct StreamOptions {
+    /// How much a value must change by before it is sent; booleans are sent on every change
+    pub deadband: f32,
+    /// How often the latest `Reading` is sent even if it hasn't changed (at most every `HEARTBEAT`)
+    pub interval: Duration,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            deadband: DEADBAND,
+            interval: HEARTBEAT,
+        }
+    }
+}
+
+impl Display for StreamOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "deadband={}&interval={}ms",
+            self.deadband,
+            self.interval.as_millis()
+        )
+    }
+}
+
+impl StreamOptions {
+    /// Parses the query of a `GET /stream` request; anything which isn't set keeps its default.
+    pub fn parse(query: &str) -> Result<StreamOptions, String> {
+        let mut options = StreamOptions::default();
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            match key {
+                "deadband" => {
+                    options.deadband = match value.parse::<f32>() {
+                        Ok(deadband) if deadband >= 0.0 => deadband,
+                        _ => return Err(format!("deadband must not be negative, not '{}'", value)),
+                    }
+                }
+                "interval" => {
+                    options.interval = match parse_duration(value) {
+                        Ok(interval) if interval.is_zero() => {
+                            return Err(String::from("interval must be greater than zero"))
+                        }
+                        Ok(interval) => interval,
+                        Err(msg) => return Err(msg),
+                    }
+                }
+                other => return Err(format!("unknown stream option '{}'", other)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Shares the latest `Reading` between all of a `Sensor`'s subscribers, so the sensor is read at
+/// most once every `SAMPLE_INTERVAL`, however many subscribers there are.
+#[derive(Default)]
+pub struct Sampler {
+    latest: Mutex<Option<(Instant, Result<Reading, String>)>>,
+}
+
+impl Sampler {
+    /// Returns the latest `Reading`, calling `read` for a new one if it is older than the
+    /// `SAMPLE_INTERVAL`.
+    pub fn sample(
+        &self,
+        read: impl FnOnce() -> Result<Reading, String>,
+    ) -> Result<Reading, String> {
+        let mut latest = self.latest.lock().unwrap();
+
+        match latest.as_ref() {
+            Some((at, reading)) if at.elapsed() < SAMPLE_INTERVAL => reading.clone(),
+            _ => {
+                let reading = read();
+                *latest = Some((Instant::now(), reading.clone()));
+                reading
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(StreamOptions::parse(""), Ok(StreamOptions::default()));
+
+        let options = StreamOptions {
+            deadband: 0.1,
+            interval: Duration::from_millis(1500),
+        };
+        assert_eq!(StreamOptions::parse(&options.to_string()), Ok(options));
+        assert_eq!(
+            StreamOptions::parse("interval=2s").map(|options| options.interval),
+            Ok(Duration::from_secs(2))
+        );
+
+        assert!(StreamOptions::parse("deadband=-1").is_err());
+        assert!(StreamOptions::parse("interval=0s").is_err());
+        assert!(StreamOptions::parse("interval=soon").is_err());
+        assert!(StreamOptions::parse("rate=1").is_err());
+    }
+
+    #[test]
+    fn test_sampler() {
+        let sampler = Sampler::default();
+        let reads = Cell::new(0);
+
+        let read = || {
+            reads.set(reads.get() + 1);
+            Reading::parse("21.5@°C@2024-01-01T00:00:00Z")
+        };
+
+        // subscribers reading at the same time share a single sample
+        assert!(sampler.sample(read).is_ok());
+        assert!(sampler.sample(read).is_ok());
+        assert_eq!(reads.get(), 1);
+
+        std::thread::sleep(SAMPLE_INTERVAL);
+        assert!(sampler.sample(read).is_ok());
+        assert_eq!(reads.get(), 2);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType, Reading};
+use device::{Device, Id, Name};
+use sensor::{Measurement, Sensor};
+
+/// A combined climate probe, which measures the temperature, relative humidity, and CO₂
+/// concentration of the air at the same time.
+///
+/// Each quantity is reported on its own channel (`temperature`, `humidity`, and `co2`).
+pub struct ClimateSensor {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` which this sensor measures the air of
+    environment: String,
+}
+
+impl Device for ClimateSensor {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Sensor for ClimateSensor {
+    /// Returns the temperature, for consumers which can only handle a single `Datum`.
+    fn get_datum(&self) -> Result<Datum, String> {
+        self.query("temperature", DatumUnit::DegreesC)
+    }
+
+    fn get_reading(&self) -> Result<Reading, String> {
+        let temperature = self.query("temperature", DatumUnit::DegreesC)?;
+        let humidity = self.query("humidity", DatumUnit::RelativeHumidity)?;
+        let co2 = self.query("co2", DatumUnit::PartsPerMillion)?;
+
+        // all channels are reported as if they were measured when the temperature was
+        Reading::new(temperature.timestamp)
+            .with("temperature", temperature.value, temperature.unit)?
+            .with("humidity", humidity.value, humidity.unit)?
+            .with("co2", co2.value, co2.unit)
+    }
+
+    fn get_kind(&self) -> &str {
+        "climate"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![
+            Measurement::new("temperature", DatumValueType::Float, DatumUnit::DegreesC),
+            Measurement::new(
+                "humidity",
+                DatumValueType::Float,
+                DatumUnit::RelativeHumidity,
+            ),
+            Measurement::new("co2", DatumValueType::Float, DatumUnit::PartsPerMillion),
+        ]
+    }
+}
+
+impl ClimateSensor {
+    pub fn new(id: Id, name: Name, environment: &str) -> ClimateSensor {
+        ClimateSensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+
+    fn query(&self, channel: &str, unit: DatumUnit) -> Result<Datum, String> {
+        sensor::query_channel(
+            self,
+            &self.environment,
+            channel,
+            DatumValueType::Float,
+            unit,
+        )
+    }
+}
@@ -0,0 +1,22 @@
+use clap::Parser;
+
+use device::config::DeviceCli;
+use device::Device;
+use sensor::Sensor;
+use sensor_climate::ClimateSensor;
+
+/// Measures the temperature, humidity, and CO₂ concentration of the `Environment` together, and
+/// reports them to the `Controller`.
+fn main() {
+    let config = DeviceCli::parse()
+        .load(8792, "_sensor")
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+
+    let sensor = ClimateSensor::new(config.id, config.name, &config.environment);
+    let listener = sensor.bind(config.address, config.port, &config.group);
+
+    sensor.serve(listener, config.push.as_ref())
+}
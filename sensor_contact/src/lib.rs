@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType};
+use device::{Device, Id, Name};
+use sensor::{Measurement, Sensor};
+
+/// A contact sensor on a door or window, which reads `true` when it is open and `false` when it
+/// is closed.
+pub struct ContactSensor {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` whose doors and windows this sensor watches
+    environment: String,
+}
+
+impl Device for ContactSensor {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Sensor for ContactSensor {
+    fn get_datum(&self) -> Result<Datum, String> {
+        sensor::query_environment(
+            self,
+            &self.environment,
+            DatumValueType::Bool,
+            DatumUnit::Unitless,
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "contact"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![Measurement::new(
+            "",
+            DatumValueType::Bool,
+            DatumUnit::Unitless,
+        )]
+    }
+}
+
+impl ContactSensor {
+    pub fn new(id: Id, name: Name, environment: &str) -> ContactSensor {
+        ContactSensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
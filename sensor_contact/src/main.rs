@@ -0,0 +1,21 @@
+use clap::Parser;
+
+use device::config::DeviceCli;
+use device::Device;
+use sensor::Sensor;
+use sensor_contact::ContactSensor;
+
+/// Reports whether a door or window in the `Environment` is open to the `Controller`.
+fn main() {
+    let config = DeviceCli::parse()
+        .load(8790, "_sensor")
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+
+    let sensor = ContactSensor::new(config.id, config.name, &config.environment);
+    let listener = sensor.bind(config.address, config.port, &config.group);
+
+    sensor.serve(listener, config.push.as_ref())
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType};
+use device::{Device, Id, Name};
+use sensor::{Measurement, Sensor};
+
+pub struct HumiditySensor {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` which this sensor measures the relative humidity of
+    environment: String,
+}
+
+impl Device for HumiditySensor {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Sensor for HumiditySensor {
+    fn get_datum(&self) -> Result<Datum, String> {
+        sensor::query_environment(
+            self,
+            &self.environment,
+            DatumValueType::Float,
+            DatumUnit::RelativeHumidity,
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "humidity"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![Measurement::new(
+            "",
+            DatumValueType::Float,
+            DatumUnit::RelativeHumidity,
+        )]
+    }
+}
+
+impl HumiditySensor {
+    pub fn new(id: Id, name: Name, environment: &str) -> HumiditySensor {
+        HumiditySensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType};
+use device::{Device, Id, Name};
+use sensor::{Measurement, Sensor};
+
+pub struct LightSensor {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` which this sensor measures the illuminance of
+    environment: String,
+}
+
+impl Device for LightSensor {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Sensor for LightSensor {
+    fn get_datum(&self) -> Result<Datum, String> {
+        sensor::query_environment(
+            self,
+            &self.environment,
+            DatumValueType::Float,
+            DatumUnit::Lux,
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "light"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![Measurement::new("", DatumValueType::Float, DatumUnit::Lux)]
+    }
+}
+
+impl LightSensor {
+    pub fn new(id: Id, name: Name, environment: &str) -> LightSensor {
+        LightSensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use clap::Parser;
+
+use device::config::DeviceCli;
+use device::Device;
+use sensor::Sensor;
+use sensor_light::LightSensor;
+
+/// Measures the illuminance of the `Environment`, in lux, and reports it to the `Controller`.
+fn main() {
+    let config = DeviceCli::parse()
+        .load(8789, "_sensor")
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+
+    let sensor = LightSensor::new(config.id, config.name, &config.environment);
+    let listener = sensor.bind(config.address, config.port, &config.group);
+
+    sensor.serve(listener, config.push.as_ref())
+}
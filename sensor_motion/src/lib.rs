@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType};
+use device::{Device, Id, Name};
+use sensor::{Measurement, Sensor};
+
+/// A motion (occupancy) sensor, which reads `true` when it detects someone and `false` when the
+/// space it watches is empty.
+pub struct MotionSensor {
+    id: Id,
+    name: Name,
+    /// The address of the `Environment` which this sensor watches for motion
+    environment: String,
+}
+
+impl Device for MotionSensor {
+    fn get_name(&self) -> &Name {
+        &self.name
+    }
+
+    fn get_id(&self) -> &Id {
+        &self.id
+    }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
+}
+
+impl Sensor for MotionSensor {
+    fn get_datum(&self) -> Result<Datum, String> {
+        sensor::query_environment(
+            self,
+            &self.environment,
+            DatumValueType::Bool,
+            DatumUnit::Unitless,
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "motion"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![Measurement::new(
+            "",
+            DatumValueType::Bool,
+            DatumUnit::Unitless,
+        )]
+    }
+}
+
+impl MotionSensor {
+    pub fn new(id: Id, name: Name, environment: &str) -> MotionSensor {
+        MotionSensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
+    }
+}
@@ -1,10 +1,14 @@
-use datum::{Datum, DatumUnit};
+use std::collections::HashMap;
+
+use datum::{Datum, DatumUnit, DatumValueType};
 use device::{Device, Id, Name};
-use sensor::Sensor;
+use sensor::{Measurement, Sensor};
 
 pub struct TemperatureSensor {
     id: Id,
     name: Name,
+    /// The address of the `Environment` which this sensor measures the temperature of
+    environment: String,
 }
 
 impl Device for TemperatureSensor {
@@ -15,17 +19,41 @@ impl Device for TemperatureSensor {
     fn get_id(&self) -> &Id {
         &self.id
     }
+
+    fn get_capabilities(&self) -> HashMap<String, String> {
+        self.describe()
+    }
 }
 
 impl Sensor for TemperatureSensor {
-    fn get_datum(&self) -> Datum {
-        // TODO should query Environment
-        Datum::new_now(25.0, DatumUnit::DegreesC)
+    fn get_datum(&self) -> Result<Datum, String> {
+        sensor::query_environment(
+            self,
+            &self.environment,
+            DatumValueType::Float,
+            DatumUnit::DegreesC,
+        )
+    }
+
+    fn get_kind(&self) -> &str {
+        "temperature"
+    }
+
+    fn get_measurements(&self) -> Vec<Measurement> {
+        vec![Measurement::new(
+            "",
+            DatumValueType::Float,
+            DatumUnit::DegreesC,
+        )]
     }
 }
 
 impl TemperatureSensor {
-    pub fn new(id: Id, name: Name) -> TemperatureSensor {
-        TemperatureSensor { id, name }
+    pub fn new(id: Id, name: Name, environment: &str) -> TemperatureSensor {
+        TemperatureSensor {
+            id,
+            name,
+            environment: String::from(environment),
+        }
     }
 }
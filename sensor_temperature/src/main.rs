@@ -0,0 +1,21 @@
+use clap::Parser;
+
+use device::config::DeviceCli;
+use device::Device;
+use sensor::Sensor;
+use sensor_temperature::TemperatureSensor;
+
+/// Measures the temperature of the `Environment`, and reports it to the `Controller`.
+fn main() {
+    let config = DeviceCli::parse()
+        .load(8787, "_sensor")
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+
+    let sensor = TemperatureSensor::new(config.id, config.name, &config.environment);
+    let listener = sensor.bind(config.address, config.port, &config.group);
+
+    sensor.serve(listener, config.push.as_ref())
+}